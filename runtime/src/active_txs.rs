@@ -4,11 +4,16 @@ use std::collections::HashMap;
 
 use std::thread;
 
+use std::sync::{
+    Arc,
+    RwLock,
+};
 use std::sync::mpsc::Sender;
 
 use sma::{
     ReadTx,
     WriteTx,
+    DbFileConfig,
 };
 
 use crate::messages::*;
@@ -18,16 +23,21 @@ use crate::runtime::{
     WtxReq,
     InputItem
 };
-use std::time::Duration;
 
 
 pub type FileAbs = String;
 type ReadTxsForFile = HashMap<TxId, ReadTx>;
 
+// A worker thread that waits for the write lock on one file; receives each request to retry, with its retry count.
+pub type WaiterIn = Sender<(WtxReq, u32)>;
+
 #[derive(Debug)]
 struct FileTxs {
     read_txs: ReadTxsForFile,
     pub write_queue: WriteQueue,
+
+    // Started on the first `SQLITE_BUSY`, then reused for every retry.
+    waiter: Option<WaiterIn>,
 }
 
 
@@ -38,6 +48,9 @@ pub struct WriteQueue {
 
     // Queue of input messages requesting a write tx.
     queue: VecDeque<WtxReq>,
+
+    // Times the `NextRetry` request has been retried.
+    retries: u32,
 }
 
 
@@ -52,6 +65,7 @@ impl WriteQueue {
         WriteQueue {
             state: QState::Empty,
             queue: VecDeque::new(),
+            retries: 0,
         }
     }
 
@@ -97,6 +111,9 @@ pub enum QState {
     // - If in this state, add any write tx requests to the queue.
     NextRetry(InMsgId),
 
+    // A backup of the file is running on a worker thread (with this `InMsgId`).
+    Backup(InMsgId),
+
     // No active wtx or retry in process; Can attempt to move to the `ActiveWtx` state.
     Empty,
 }
@@ -106,8 +123,17 @@ pub enum QState {
 pub struct ActiveTxs {
     txs: HashMap<FileAbs, FileTxs>,
 
+    // `file/subscribe_changes` input message ids.
+    change_subs: HashMap<FileAbs, Vec<InMsgId>>,
+
+    // `file/subscribe_checkpoint_warnings` input message ids.
+    checkpoint_subs: HashMap<FileAbs, Vec<InMsgId>>,
+
     // @todo/low Replace with closure to allow any method?
     event_loop_in: Sender<InputItem>,
+
+    // Written by the `Runtime` setters from the host thread.
+    config: Arc<RwLock<DbFileConfig>>,
 }
 
 impl Drop for ActiveTxs {
@@ -133,10 +159,13 @@ pub enum RWO {
 
 
 impl ActiveTxs {
-    pub fn new(event_loop_in: Sender<InputItem>) -> ActiveTxs {
+    pub fn new(event_loop_in: Sender<InputItem>, config: Arc<RwLock<DbFileConfig>>) -> ActiveTxs {
         ActiveTxs {
             txs: HashMap::new(),
+            change_subs: HashMap::new(),
+            checkpoint_subs: HashMap::new(),
             event_loop_in,
+            config,
         }
     }
 
@@ -145,19 +174,18 @@ impl ActiveTxs {
             FileTxs {
                 read_txs: HashMap::new(),
                 write_queue: WriteQueue::new(),
+                waiter: None,
             }
         })
     }
 
-    pub fn send_at(&self, in_msg: InMsgWithId, t: Duration) {
-        let tx = self.event_loop_in.clone();
+    pub fn get_event_loop_in(&self) -> Sender<InputItem> {
+        self.event_loop_in.clone()
+    }
 
-        thread::spawn(move || {
-            println!("TID: {:?}, sleeping for: {:?}", thread::current().id(), t);
-            thread::sleep(t);
-            println!("TID: {:?}, sleep complete", thread::current().id());
-            tx.send(InputItem::InMsgWithId(in_msg)).expect("Ok");
-        });
+    // A copy, so connections opened from other threads (E.g. a backup) do not hold the lock.
+    pub fn get_config(&self) -> DbFileConfig {
+        self.config.read().unwrap().clone()
     }
 
 
@@ -182,6 +210,53 @@ impl ActiveTxs {
         None
     }
 
+    pub fn subscribe_changes(&mut self, f: &FileAbs, in_msg_id: InMsgId) {
+        self.change_subs.entry(f.clone()).or_insert_with(Vec::new).push(in_msg_id);
+    }
+
+    // The file the subscription was for; `None` if `in_msg_id` is not subscribed.
+    pub fn unsubscribe_changes(&mut self, in_msg_id: &InMsgId) -> Option<FileAbs> {
+        remove_sub(&mut self.change_subs, in_msg_id)
+    }
+
+    pub fn get_change_subs(&self, f: &FileAbs) -> Vec<InMsgId> {
+        self.change_subs.get(f).cloned().unwrap_or_default()
+    }
+
+    pub fn subscribe_checkpoint_warnings(&mut self, f: &FileAbs, in_msg_id: InMsgId) {
+        self.checkpoint_subs.entry(f.clone()).or_insert_with(Vec::new).push(in_msg_id);
+    }
+
+    pub fn unsubscribe_checkpoint_warnings(&mut self, in_msg_id: &InMsgId) -> Option<FileAbs> {
+        remove_sub(&mut self.checkpoint_subs, in_msg_id)
+    }
+
+    pub fn get_checkpoint_subs(&self, f: &FileAbs) -> Vec<InMsgId> {
+        self.checkpoint_subs.get(f).cloned().unwrap_or_default()
+    }
+
+    // Sorted, so warnings list them in a stable order.
+    pub fn get_read_tx_ids(&self, f: &FileAbs) -> Vec<TxId> {
+        let mut ids: Vec<TxId> = match self.txs.get(f) {
+            Some(txs) => txs.read_txs.keys().cloned().collect(),
+            None => vec![]
+        };
+
+        ids.sort();
+        ids
+    }
+
+    // Read txs, and the active write tx.
+    pub fn get_tx_ids(&self, f: &FileAbs) -> Vec<TxId> {
+        let mut ids = self.get_read_tx_ids(f);
+
+        if let Some(FileTxs { write_queue: WriteQueue { state: QState::Active(wtx), .. }, .. }) = self.txs.get(f) {
+            ids.push(wtx.id.clone());
+        }
+
+        ids
+    }
+
     pub fn wr_qstate(&mut self, f: &FileAbs) -> &QState {
         &self.get_file_txs_mut(f).write_queue.state
     }
@@ -196,6 +271,7 @@ impl ActiveTxs {
 
         let next = QState::Active(wtx);
         let prev = mem::replace(&mut wq.state, next);
+        wq.retries = 0;
 
         // @todo/low Also assert prev != NextRetry(id) if id !== next_in_queue_id
         // - Encode these state transitions with the Rust ownership system? See per file db/tx. E.g. `Enum(s) => s.fn()`
@@ -205,8 +281,11 @@ impl ActiveTxs {
         }
     }
 
-    pub fn wr_retry(&mut self, f: &FileAbs, wtx_req: WtxReq, t: Duration) {
-        let wq = &mut self.get_file_txs_mut(f).write_queue;
+    // Sends `wtx_req` to the files waiter, which sends it back to the event loop when it is worth retrying (E.g. the write lock was released).
+    // - `start_waiter` is only called for the first retry on the file.
+    pub fn wr_retry<F>(&mut self, f: &FileAbs, wtx_req: WtxReq, start_waiter: F) where F: FnOnce() -> WaiterIn {
+        let ft = self.get_file_txs_mut(f);
+        let wq = &mut ft.write_queue;
 
         let next = QState::NextRetry(wtx_req.id.clone());
         let prev = mem::replace(&mut wq.state, next);
@@ -217,12 +296,51 @@ impl ActiveTxs {
             _ => assert!(false, "To transition to `NextRetry(x)`, current state must be `(NextRetry(x) | Empty)`")
         }
 
-        self.send_at(wtx_req.into(), t);
+        wq.retries += 1;
+        let retries = wq.retries;
+
+        // Note: Send fails only if the waiter thread has panicked.
+        ft.waiter.get_or_insert_with(start_waiter).send((wtx_req, retries)).expect("Ok");
     }
 
-    pub fn wr_fail(&mut self, f: &FileAbs, wtx_req: WtxReq) {
+    // Times the request in the `NextRetry` state has been retried.
+    pub fn wr_retries(&mut self, f: &FileAbs) -> u32 {
+        self.get_file_txs_mut(f).write_queue.retries
+    }
+
+    pub fn wr_backup(&mut self, f: &FileAbs, in_msg_id: InMsgId) {
         let wq = &mut self.get_file_txs_mut(f).write_queue;
+        let prev = mem::replace(&mut wq.state, QState::Backup(in_msg_id));
+        wq.retries = 0;
 
+        match prev {
+            QState::NextRetry(_) | QState::Empty => {}
+            _ => assert!(false, "To transition to `Backup(x)`, current state must be `(NextRetry(x) | Empty)`")
+        }
+    }
+
+    pub fn wr_backup_done(&mut self, f: &FileAbs) {
+        match self.wr_qstate(f) {
+            QState::Backup(_) => {}
+            _ => assert!(false, "wr_backup_done should only be called when qstate is backup.")
+        }
+
+        self.wr_next(f);
+    }
+
+    pub fn wr_fail(&mut self, f: &FileAbs, wtx_req: WtxReq) {
+        self.wr_next(f);
+    }
+
+    // A request that used its own write tx (E.g. `file/undo`) has finished.
+    pub fn wr_done(&mut self, f: &FileAbs) {
+        self.wr_next(f);
+    }
+
+    // Moves the next queued request to the front, or empties the queue.
+    fn wr_next(&mut self, f: &FileAbs) {
+        let wq = &mut self.get_file_txs_mut(f).write_queue;
+        wq.retries = 0;
 
         match wq.queue.pop_front() {
             Some(wtx_req) => {
@@ -286,3 +404,16 @@ impl ActiveTxs {
         file_txs.read_txs.insert(tx_id.clone(), read_tx);
     }
 }
+
+
+fn remove_sub(subs: &mut HashMap<FileAbs, Vec<InMsgId>>, in_msg_id: &InMsgId) -> Option<FileAbs> {
+    let f = subs.iter().find(|(_, ids)| ids.contains(in_msg_id)).map(|(f, _)| f.clone())?;
+
+    let ids = subs.get_mut(&f)?;
+    ids.retain(|id| id != in_msg_id);
+    if ids.is_empty() {
+        subs.remove(&f);
+    }
+
+    Some(f)
+}
@@ -73,6 +73,61 @@ pub fn in_json_to_rs(s: &String) -> Result<InMsgWithId, ParseError> {
                 GetWriteTx(from_value(a)?)
             )
         }
+        "file/backup" => {
+            File(
+                Backup(from_value(a)?)
+            )
+        }
+        "file/subscribe_changes" => {
+            File(
+                SubscribeChanges(from_value(a)?)
+            )
+        }
+        "file/unsubscribe_changes" => {
+            File(
+                UnsubscribeChanges(from_value(a)?)
+            )
+        }
+        "file/subscribe_checkpoint_warnings" => {
+            File(
+                SubscribeCheckpointWarnings(from_value(a)?)
+            )
+        }
+        "file/unsubscribe_checkpoint_warnings" => {
+            File(
+                UnsubscribeCheckpointWarnings(from_value(a)?)
+            )
+        }
+        "file/undo" => {
+            File(
+                Undo(from_value(a)?)
+            )
+        }
+        "file/redo" => {
+            File(
+                Redo(from_value(a)?)
+            )
+        }
+        "file/checkpoint" => {
+            File(
+                Checkpoint(from_value(a)?)
+            )
+        }
+        "file/serialize" => {
+            File(
+                Serialize(from_value(a)?)
+            )
+        }
+        "file/deserialize" => {
+            File(
+                Deserialize(from_value(a)?)
+            )
+        }
+        "file/slow_queries" => {
+            File(
+                SlowQueries(from_value(a)?)
+            )
+        }
         "tx/q" => {
             Tx(
                 Q(from_value(a)?)
@@ -103,6 +158,46 @@ pub fn in_json_to_rs(s: &String) -> Result<InMsgWithId, ParseError> {
                 WriteParams(from_value(a)?)
             )
         }
+        "tx/exec_script" => {
+            Tx(
+                ExecScript(from_value(a)?)
+            )
+        }
+        "tx/blob_read" => {
+            Tx(
+                BlobRead(from_value(a)?)
+            )
+        }
+        "tx/blob_write" => {
+            Tx(
+                BlobWrite(from_value(a)?)
+            )
+        }
+        "tx/explain" => {
+            Tx(
+                Explain(from_value(a)?)
+            )
+        }
+        "tx/session_start" => {
+            Tx(
+                SessionStart(from_value(a)?)
+            )
+        }
+        "tx/changeset" => {
+            Tx(
+                Changeset(from_value(a)?)
+            )
+        }
+        "tx/apply_changeset" => {
+            Tx(
+                ApplyChangeset(from_value(a)?)
+            )
+        }
+        "tx/enable_undo" => {
+            Tx(
+                EnableUndo(from_value(a)?)
+            )
+        }
         "tx/commit" => {
             Tx(
                 Commit(from_value(a)?)
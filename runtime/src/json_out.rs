@@ -19,6 +19,10 @@ use sqlite3ffi::{
         ErrorBind,
         ErrorBindType,
     },
+    backup::BackupProgress,
+    explain::QueryPlan,
+    limits::Limit,
+    auth::AuthContext,
 };
 
 use sma::{
@@ -26,6 +30,8 @@ use sma::{
     ReadBindRunError,
     WriteError,
     WriteBindRunError,
+    SessionError,
+    undo::UndoStep,
 };
 
 
@@ -60,6 +66,24 @@ use crate::json_in::{
 enum Res<'a> {
     TxIdOnly(&'a TxIdOnly),
     RSet(&'a RSet),
+    RSets(RSets<'a>),
+    BlobChunk(&'a BlobChunk),
+    SessionData(&'a SessionData),
+    QueryPlan(&'a QueryPlan),
+    BackupProgress(&'a BackupProgress),
+    UndoStep(&'a UndoStep),
+    TxChanges(&'a crate::messages::TxChanges),
+    CheckpointStatus(&'a CheckpointStatus),
+    FileImage(&'a FileImage),
+    FileOnly(&'a FileOnly),
+    SlowQueries(&'a crate::messages::SlowQueries),
+}
+
+// Wrapped so `res` is always a JSON object.
+#[derive(Debug)]
+#[derive(Serialize)]
+struct RSets<'a> {
+    rsets: &'a Vec<RSet>,
 }
 
 #[derive(Debug)]
@@ -115,7 +139,25 @@ pub struct E {
 #[derive(Default)]
 struct ErrData {
     #[serde(skip_serializing_if = "Option::is_none")]
-    return_status: Option<ReturnStatus>
+    return_status: Option<ReturnStatus>,
+
+    // Position and byte offset of the failed statement in a script.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stmt_index: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stmt_offset: Option<usize>,
+
+    // `TxOp/Timeout`: `true` when the tx was rolled back and restarted.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    is_rolled_back: Option<bool>,
+
+    // `TxOp/LimitExceeded`: E.g. `sql_length`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    limit: Option<Limit>,
+
+    // `TxOp/NotAuthorized`: the denied action.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    denied: Option<AuthContext>,
 }
 
 
@@ -178,7 +220,10 @@ impl From<FileOpErr> for E {
     fn from(e: FileOpErr) -> Self {
         match e {
             FileDirectoryDoesNotExist => E::new_key(to_path(vec!["FileOp", "FileDirectoryDoesNotExist"])),
-            FileOpErr::ReturnStatus(rs) => E::new_key_status(to_path(vec!["FileOp", "ReturnStatus"]), rs)
+            InvalidUri => E::new_key_msg(to_path(vec!["FileOp", "InvalidUri"]), "The URI must name a file on this host (not `:memory:` or `mode=memory`).".to_string()),
+            FileOpErr::ReturnStatus(rs) => E::new_key_status(to_path(vec!["FileOp", "ReturnStatus"]), rs),
+            TxsActive(ids) => E::new_key_msg(to_path(vec!["FileOp", "TxsActive"]), format!("Transactions are open on the file: {}", ids.join(", "))),
+            NotSubscribed => E::new_key(to_path(vec!["FileOp", "NotSubscribed"]))
         }
     }
 }
@@ -245,6 +290,10 @@ fn km(mut p: Path, a: &'static str, b: String) -> E {
     E::new_key_msg(k, b)
 }
 
+fn to_limit_key(l: &Limit) -> String {
+    serde_json::to_value(l).ok().and_then(|v| v.as_str().map(String::from)).unwrap_or_default()
+}
+
 
 use crate::messages::{TxOpErr::*};
 use std::error::Error;
@@ -291,6 +340,36 @@ impl From<TxOpErr> for E {
                     WriteBindRunError::BindRunError(bre) => bre_match(p, bre)
                 }
             }
+            TxOpErr::ScriptError(se) => {
+                let mut e = ks(p, "ScriptError", se.return_status);
+                e.data.stmt_index = Some(se.index);
+                e.data.stmt_offset = Some(se.offset);
+                e
+            }
+            TxOpErr::SessionError(se) => {
+                p.push("SessionError");
+                match se {
+                    SessionError::NotStarted => k(p, "NotStarted"),
+                    SessionError::ReturnStatus(rs) => ks(p, "ReturnStatus", rs),
+                }
+            }
+            TxOpErr::Timeout(qt) => {
+                let mut e = km(p, "Timeout", format!("Query interrupted after timeout_ms={}.", qt.timeout_ms));
+                e.data.is_rolled_back = Some(qt.is_rolled_back);
+                e
+            }
+            TxOpErr::NotAuthorized(na) => {
+                let mut e = km(p, "NotAuthorized", "Query denied by the auth policy of the file.".to_string());
+                e.data.denied = na.denied;
+                e.data.return_status = Some(na.return_status);
+                e
+            }
+            TxOpErr::LimitExceeded(le) => {
+                let mut e = km(p, "LimitExceeded", format!("Query exceeded the connection limit `{}`.", to_limit_key(&le.limit)));
+                e.data.limit = Some(le.limit);
+                e.data.return_status = Some(le.return_status);
+                e
+            }
         }
     }
 }
@@ -338,6 +417,118 @@ pub fn out_rs_to_json(o: &OutMsg) -> Result<String, SerdeError> {
                 let e: E = owned.into();
                 f(e)
             }
+        },
+        TxScript(tx_script_res) => match tx_script_res {
+            Ok(rsets) => {
+                t(Res::RSets(RSets { rsets }))
+            }
+            Err(e_orig) => {
+                let owned: TxOpErr = (*e_orig).clone();
+                let e: E = owned.into();
+                f(e)
+            }
+        },
+        TxBlob(tx_blob_res) => match tx_blob_res {
+            Ok(chunk) => {
+                t(Res::BlobChunk(chunk))
+            }
+            Err(e_orig) => {
+                let owned: TxOpErr = (*e_orig).clone();
+                let e: E = owned.into();
+                f(e)
+            }
+        },
+        TxSession(tx_session_res) => match tx_session_res {
+            Ok(data) => {
+                t(Res::SessionData(data))
+            }
+            Err(e_orig) => {
+                let owned: TxOpErr = (*e_orig).clone();
+                let e: E = owned.into();
+                f(e)
+            }
+        },
+        TxExplain(tx_explain_res) => match tx_explain_res {
+            Ok(plan) => {
+                t(Res::QueryPlan(plan))
+            }
+            Err(e_orig) => {
+                let owned: TxOpErr = (*e_orig).clone();
+                let e: E = owned.into();
+                f(e)
+            }
+        },
+        OutMsg::TxChanges(tx_changes) => t(Res::TxChanges(tx_changes)),
+        OutMsg::CheckpointWarning(status) => t(Res::CheckpointStatus(status)),
+        OutMsg::Backup(backup_res) => match backup_res {
+            Ok(progress) => {
+                t(Res::BackupProgress(progress))
+            }
+            Err(e_orig) => {
+                let owned: FileOpErr = (*e_orig).clone();
+                let e: E = owned.into();
+                f(e)
+            }
+        },
+        OutMsg::Undo(undo_res) => match undo_res {
+            Ok(step) => {
+                t(Res::UndoStep(step))
+            }
+            Err(e_orig) => {
+                let owned: FileOpErr = (*e_orig).clone();
+                let e: E = owned.into();
+                f(e)
+            }
+        },
+        OutMsg::Checkpoint(checkpoint_res) => match checkpoint_res {
+            Ok(status) => {
+                t(Res::CheckpointStatus(status))
+            }
+            Err(e_orig) => {
+                let owned: FileOpErr = (*e_orig).clone();
+                let e: E = owned.into();
+                f(e)
+            }
+        },
+        OutMsg::Serialize(serialize_res) => match serialize_res {
+            Ok(image) => {
+                t(Res::FileImage(image))
+            }
+            Err(e_orig) => {
+                let owned: FileOpErr = (*e_orig).clone();
+                let e: E = owned.into();
+                f(e)
+            }
+        },
+        OutMsg::Deserialize(deserialize_res) => match deserialize_res {
+            Ok(file) => {
+                t(Res::FileOnly(file))
+            }
+            Err(e_orig) => {
+                let owned: FileOpErr = (*e_orig).clone();
+                let e: E = owned.into();
+                f(e)
+            }
+        },
+        OutMsg::Unsubscribe(unsubscribe_res) => match unsubscribe_res {
+            Ok(file) => {
+                t(Res::FileOnly(file))
+            }
+            Err(e_orig) => {
+                let owned: FileOpErr = (*e_orig).clone();
+                let e: E = owned.into();
+                f(e)
+            }
+        },
+        OutMsg::SlowQueries(slow_queries_res) => match slow_queries_res {
+            Ok(qs) => {
+                t(Res::SlowQueries(qs))
+            }
+            Err(e_orig) => {
+                let owned: FileOpErr = (*e_orig).clone();
+                let e: E = owned.into();
+                f(e)
+            }
         }
     }
 }
\ No newline at end of file
@@ -4,6 +4,28 @@ use sqlite3ffi::stmt::RSet;
 
 use sqlite3ffi::err::ReturnStatus;
 use sqlite3ffi::db::BindRunError;
+use sqlite3ffi::script::ScriptError;
+use sqlite3ffi::open::OpenFlags;
+use sqlite3ffi::backup::BackupProgress;
+use sqlite3ffi::explain::QueryPlan;
+use sqlite3ffi::hooks::RowChange;
+use sqlite3ffi::session::{
+    Conflict,
+    ConflictAction,
+};
+use sqlite3ffi::checkpoint::{
+    Checkpoint,
+    CheckpointMode,
+};
+use sma::SlowQuery;
+use sqlite3ffi::limits::{
+    Limit,
+    get_limit_exceeded,
+};
+use sqlite3ffi::auth::{
+    AuthContext,
+    is_not_authorized,
+};
 
 use sma::{
     Params,
@@ -11,6 +33,8 @@ use sma::{
     ReadBindRunError,
     WriteError,
     WriteBindRunError,
+    SessionError,
+    undo::UndoStep,
 };
 
 // Question: How do you know which responses are compatible with which requests using just the pub type system?
@@ -42,6 +66,27 @@ pub enum InMsg {
 pub enum FileOp {
     GetReadTx(ArgsFile),
     GetWriteTx(ArgsFile),
+    // Queued with write tx requests for `file`; responds once per step with progress.
+    Backup(ArgsBackup),
+    // No direct response; every later write tx commit on `file` that changes rows responds with `TxChanges`.
+    SubscribeChanges(ArgsFile),
+    // Stops the `TxChanges` responses to a `SubscribeChanges` message.
+    UnsubscribeChanges(ArgsUnsubscribe),
+    // No direct response; every later automatic checkpoint on `file` that could not copy the whole WAL responds with `CheckpointWarning`.
+    SubscribeCheckpointWarnings(ArgsFile),
+    // Stops the `CheckpointWarning` responses to a `SubscribeCheckpointWarnings` message.
+    UnsubscribeCheckpointWarnings(ArgsUnsubscribe),
+    // Queued with write tx requests for `file`; each runs in its own write tx. See `sma::undo`.
+    Undo(ArgsFile),
+    Redo(ArgsFile),
+    // Queued with write tx requests for `file`, so it does not compete with this runtimes own writer.
+    Checkpoint(ArgsCheckpoint),
+    // The committed database as bytes; runs on its own connection, so it is not affected by active txs.
+    Serialize(ArgsFile),
+    // Queued with write tx requests for `file`; refused while `file` has txs open in this runtime.
+    Deserialize(ArgsDeserialize),
+    // Entries of the process wide slow query log for `file` (see `Runtime::set_slow_query_log`); does not open a tx.
+    SlowQueries(ArgsFile),
 }
 
 // @todo/low Use `enum` to categorise op into (read, write, r_or_w, end)?
@@ -56,6 +101,19 @@ pub enum TxOp {
     QParams(ArgsTxParams),
     ReadParams(ArgsTxParams),
     WriteParams(ArgsTxParams),
+    // Many statements in one string, only valid in a write tx.
+    ExecScript(ArgsTx),
+    // Incremental blob I/O, one chunk per message.
+    BlobRead(ArgsBlobRead),
+    BlobWrite(ArgsBlobWrite),
+    // Changesets for syncing files; write tx only.
+    SessionStart(ArgsSessionStart),
+    Changeset(ArgsChangeset),
+    ApplyChangeset(ArgsApplyChangeset),
+    // Opts tables in to `file/undo`, starting from the next write tx.
+    EnableUndo(ArgsEnableUndo),
+    // `EXPLAIN QUERY PLAN` for `q` (which is not run); read or write tx.
+    Explain(ArgsTx),
     Commit(TxIdOnly),
     Rollback(TxIdOnly),
 }
@@ -65,18 +123,33 @@ use TxOp::*;
 impl TxOp {
     pub fn get_tx_id(&self) -> TxId {
         let tx_id = match &self {
-            Q(a) | Read(a) | Write(a) => &a.tx_id,
+            Q(a) | Read(a) | Write(a) | ExecScript(a) | Explain(a) => &a.tx_id,
             QParams(a) | ReadParams(a) | WriteParams(a) => &a.tx_id,
+            BlobRead(a) => &a.tx_id,
+            BlobWrite(a) => &a.tx_id,
+            SessionStart(a) => &a.tx_id,
+            Changeset(a) => &a.tx_id,
+            ApplyChangeset(a) => &a.tx_id,
+            EnableUndo(a) => &a.tx_id,
             Commit(a) | Rollback(a) => &a.tx_id
         };
         tx_id.clone()
     }
 
+    pub fn get_timeout_ms(&self) -> Option<u32> {
+        match &self {
+            Q(a) | Read(a) | Write(a) | ExecScript(a) => a.timeout_ms,
+            QParams(a) | ReadParams(a) | WriteParams(a) => a.timeout_ms,
+            _ => None
+        }
+    }
+
     pub fn get_type(&self) -> TxOpType {
         match &self {
-            Read(_) | ReadParams(_) => TxOpType::Read,
-            Write(_) | WriteParams(_) => TxOpType::Write,
-            Q(_) | QParams(_) => TxOpType::Q,
+            Read(_) | ReadParams(_) | BlobRead(_) => TxOpType::Read,
+            Write(_) | WriteParams(_) | ExecScript(_) | BlobWrite(_) => TxOpType::Write,
+            SessionStart(_) | Changeset(_) | ApplyChangeset(_) | EnableUndo(_) => TxOpType::Write,
+            Q(_) | QParams(_) | Explain(_) => TxOpType::Q,
             Commit(a) | Rollback(a) => TxOpType::End
         }
     }
@@ -95,7 +168,53 @@ pub enum TxOpType {
 #[derive(PartialEq)]
 #[derive(Serialize, Deserialize)]
 pub struct ArgsFile {
-    pub file: FilePath
+    pub file: FilePath,
+
+    // `None` = read, write and create the file.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub flags: Option<OpenFlags>,
+}
+
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct ArgsCheckpoint {
+    pub file: FilePath,
+
+    #[serde(default)]
+    pub mode: CheckpointMode,
+}
+
+// `subscribe_id` = the input message id of the `file/subscribe_changes` (or `file/subscribe_checkpoint_warnings`) message.
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct ArgsUnsubscribe {
+    pub subscribe_id: String,
+}
+
+// `data` = a `file/serialize` image.
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct ArgsDeserialize {
+    pub file: FilePath,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct ArgsBackup {
+    // Source.
+    pub file: FilePath,
+    pub dst: FilePath,
+
+    // `None` = 100 pages.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pages_per_step: Option<u32>,
 }
 
 #[derive(Debug)]
@@ -104,6 +223,11 @@ pub struct ArgsFile {
 pub struct ArgsTx {
     pub tx_id: TxId,
     pub q: Query,
+
+    // Interrupts the query if it is still running after this many ms (see `TxOpErr::Timeout`). `None` = no limit.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<u32>,
 }
 
 #[derive(Debug)]
@@ -113,17 +237,120 @@ pub struct ArgsTxParams {
     pub tx_id: TxId,
     pub q: Query,
 
+    // Interrupts the query if it is still running after this many ms (see `TxOpErr::Timeout`). `None` = no limit.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<u32>,
+
     #[serde(flatten)]
     pub params: Params,
 }
 
 
+// A single BLOB cell.
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct ArgsBlob {
+    pub table: String,
+    pub column: String,
+    pub rowid: i64,
+}
+
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct ArgsBlobRead {
+    pub tx_id: TxId,
+
+    #[serde(flatten)]
+    pub blob: ArgsBlob,
+
+    pub offset: u32,
+    pub len: u32,
+}
+
+// The blob size cannot change; reserve space first with `zeroblob(N)`.
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct ArgsBlobWrite {
+    pub tx_id: TxId,
+
+    #[serde(flatten)]
+    pub blob: ArgsBlob,
+
+    pub offset: u32,
+    pub data: Vec<u8>,
+}
+
+
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct ArgsSessionStart {
+    pub tx_id: TxId,
+
+    // `None` = all tables.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tables: Option<Vec<String>>,
+}
+
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct ArgsChangeset {
+    pub tx_id: TxId,
+
+    #[serde(default)]
+    pub patchset: bool,
+}
+
+// The same action is used for every conflict.
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct ArgsApplyChangeset {
+    pub tx_id: TxId,
+    pub changeset: Vec<u8>,
+    pub on_conflict: ConflictAction,
+}
+
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct ArgsEnableUndo {
+    pub tx_id: TxId,
+    pub tables: Vec<String>,
+}
+
+
 // @todo/maybe Use pub enum tree paths to categorise response pub types so that the tree paths match the request pub types?
 #[derive(Debug)]
 #[derive(Serialize, Deserialize)]
 pub enum OutMsg {
     File(FileOpRes),
     Tx(TxOpRes),
+    // Response to `TxOp::ExecScript`; one `RSet` per statement.
+    TxScript(TxScriptRes),
+    TxBlob(TxBlobRes),
+    TxSession(TxSessionRes),
+    TxExplain(TxExplainRes),
+    Backup(BackupRes),
+    Undo(UndoRes),
+    Checkpoint(CheckpointRes),
+    Serialize(SerializeRes),
+    Deserialize(DeserializeRes),
+    SlowQueries(SlowQueriesRes),
+    // Response to `FileOp::UnsubscribeChanges` and `FileOp::UnsubscribeCheckpointWarnings`.
+    Unsubscribe(UnsubscribeRes),
+
+    // Response to `FileOp::SubscribeChanges`, sent after a write tx on the file commits.
+    TxChanges(TxChanges),
+
+    // Response to `FileOp::SubscribeCheckpointWarnings`; the automatic checkpoint after a commit could not copy the whole WAL (see `sma::CheckpointPolicy`).
+    CheckpointWarning(CheckpointStatus),
 //    RSet(RSetRes),
 }
 
@@ -138,6 +365,95 @@ pub struct TxIdOnly {
 
 pub type FileOpRes = Result<TxIdOnly, FileOpErr>;
 pub type TxOpRes = Result<RSet, TxOpErr>;
+pub type TxScriptRes = Result<Vec<RSet>, TxOpErr>;
+pub type TxBlobRes = Result<BlobChunk, TxOpErr>;
+pub type TxSessionRes = Result<SessionData, TxOpErr>;
+pub type TxExplainRes = Result<QueryPlan, TxOpErr>;
+pub type BackupRes = Result<BackupProgress, FileOpErr>;
+pub type UndoRes = Result<UndoStep, FileOpErr>;
+pub type CheckpointRes = Result<CheckpointStatus, FileOpErr>;
+pub type SerializeRes = Result<FileImage, FileOpErr>;
+pub type DeserializeRes = Result<FileOnly, FileOpErr>;
+pub type SlowQueriesRes = Result<SlowQueries, FileOpErr>;
+pub type UnsubscribeRes = Result<FileOnly, FileOpErr>;
+
+
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Clone)]
+#[derive(Serialize, Deserialize)]
+pub struct BlobChunk {
+    // Total size of the blob (so the client knows how many chunks remain).
+    pub blob_len: u32,
+    pub offset: u32,
+
+    // Empty for writes.
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Clone)]
+#[derive(Serialize, Deserialize)]
+pub struct SessionData {
+    // Only set for `Changeset`.
+    pub changeset: Vec<u8>,
+
+    // Only set for `ApplyChangeset`; every conflict, each resolved with `on_conflict`.
+    pub conflicts: Vec<Conflict>,
+}
+
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Clone)]
+#[derive(Serialize, Deserialize)]
+pub struct TxChanges {
+    pub tx_id: TxId,
+    pub file: FilePath,
+    pub changes: Vec<RowChange>,
+}
+
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Clone)]
+#[derive(Serialize, Deserialize)]
+pub struct CheckpointStatus {
+    pub file: FilePath,
+    pub checkpoint: Checkpoint,
+
+    // Every read tx open on `file` in this runtime, set only when the checkpoint is not complete.
+    // - Not only the ones blocking it: a read tx that began after the last commit does not (and a reader in another process can).
+    pub open_read_tx_ids: Vec<TxId>,
+}
+
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Clone)]
+#[derive(Serialize, Deserialize)]
+pub struct FileImage {
+    pub file: FilePath,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Clone)]
+#[derive(Serialize, Deserialize)]
+pub struct FileOnly {
+    pub file: FilePath,
+}
+
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Clone)]
+#[derive(Serialize, Deserialize)]
+pub struct SlowQueries {
+    pub file: FilePath,
+
+    // Oldest first.
+    pub queries: Vec<SlowQuery>,
+}
+
 //pub type RSetRes = std::result::Result<RSet, RSetErr>;
 
 
@@ -148,6 +464,15 @@ pub type TxOpRes = Result<RSet, TxOpErr>;
 pub enum FileOpErr {
     FileDirectoryDoesNotExist,
     ReturnStatus(ReturnStatus),
+
+    // When: `file/deserialize` with these txs open on the file.
+    TxsActive(Vec<TxId>),
+
+    // When: `flags.uri` is set and `file` is an in-memory, remote or malformed `file:` URI.
+    InvalidUri,
+
+    // When: `file/unsubscribe_changes` (or `file/unsubscribe_checkpoint_warnings`) with an id that is not a current subscription of that kind.
+    NotSubscribed,
 }
 
 // @todo/medium General error type with fields (kind, message, meta) for JSON-like response.
@@ -171,6 +496,98 @@ pub enum TxOpErr {
 
     WriteError(WriteError),
     WriteBindRunError(WriteBindRunError),
+
+    // When: exec_script()
+    ScriptError(ScriptError),
+
+    // When: write tx, changeset()
+    SessionError(SessionError),
+
+    // When: a query ran past its `timeout_ms`.
+    Timeout(QueryTimeout),
+
+    // When: a query hit a connection limit (see `Runtime::set_limits`).
+    LimitExceeded(LimitExceeded),
+
+    // When: a query was denied by the files auth policy (see `Runtime::set_auth_policy`).
+    NotAuthorized(NotAuthorized),
+}
+
+impl TxOpErr {
+    pub fn get_return_status(&self) -> Option<&ReturnStatus> {
+        match self {
+            TxOpErr::ReturnStatus(rs) |
+            TxOpErr::BindRunError(BindRunError::ReturnStatus(rs)) |
+            TxOpErr::ReadError(ReadError::ReturnStatus(rs)) |
+            TxOpErr::ReadBindRunError(ReadBindRunError::BindRunError(BindRunError::ReturnStatus(rs))) |
+            TxOpErr::WriteError(WriteError::ReturnStatus(rs)) |
+            TxOpErr::WriteBindRunError(WriteBindRunError::BindRunError(BindRunError::ReturnStatus(rs))) |
+            TxOpErr::SessionError(SessionError::ReturnStatus(rs)) => Some(rs),
+            TxOpErr::LimitExceeded(le) => Some(&le.return_status),
+            TxOpErr::NotAuthorized(na) => Some(&na.return_status),
+            _ => None
+        }
+    }
+
+    // Replaces the error with `LimitExceeded` when its `ReturnStatus` is from a limit.
+    // - `ScriptError` is kept, as it has the position of the failed statement.
+    pub fn to_limit_exceeded(self) -> TxOpErr {
+        if let TxOpErr::LimitExceeded(_) = self {
+            return self;
+        }
+
+        let limit = self.get_return_status().and_then(get_limit_exceeded);
+        match limit {
+            Some(limit) => TxOpErr::LimitExceeded(LimitExceeded { limit, return_status: self.get_return_status().unwrap().clone() }),
+            None => self
+        }
+    }
+
+    // Replaces authorizer errors with `NotAuthorized`; `take_denied` reads the denied action from the txs connection.
+    pub fn to_not_authorized<F>(self, take_denied: F) -> TxOpErr where F: FnOnce() -> Option<AuthContext> {
+        let return_status = match self.get_return_status() {
+            Some(rs) if is_not_authorized(rs) => rs.clone(),
+            _ => return self
+        };
+
+        if let TxOpErr::NotAuthorized(_) = self {
+            return self;
+        }
+
+        TxOpErr::NotAuthorized(NotAuthorized { denied: take_denied(), return_status })
+    }
+}
+
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Serialize, Deserialize)]
+#[derive(Clone)]
+pub struct NotAuthorized {
+    // `None` when the tx ended with the query (E.g. a denied `COMMIT`).
+    pub denied: Option<AuthContext>,
+    pub return_status: ReturnStatus,
+}
+
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Serialize, Deserialize)]
+#[derive(Clone)]
+pub struct LimitExceeded {
+    // Note: Statements longer than `sql_length` are reported as `length`.
+    pub limit: Limit,
+    pub return_status: ReturnStatus,
+}
+
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Serialize, Deserialize)]
+#[derive(Clone)]
+pub struct QueryTimeout {
+    pub timeout_ms: u32,
+
+    // `true` when the interrupted query was a write: SQLite rolls back the whole tx, so every earlier write in it is lost.
+    // - The tx is ended (its id is no longer valid), and the next queued write tx can start.
+    pub is_rolled_back: bool,
 }
 
 
@@ -187,7 +604,8 @@ fn example() {
         File(
             GetReadTx(
                 ArgsFile {
-                    file: "a/b/c.sqlite".to_string()
+                    file: "a/b/c.sqlite".to_string(),
+                    flags: None
                 }
             )
         )
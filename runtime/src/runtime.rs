@@ -3,10 +3,15 @@
 use crate::*;
 
 use std::fs;
-use std::path::Path;
+use std::io;
 
 
 use std::thread;
+use std::time::Duration;
+use std::sync::{
+    Arc,
+    RwLock,
+};
 use std::sync::mpsc;
 use std::sync::mpsc::{
     Sender,
@@ -27,9 +32,32 @@ use sma::{
     fns::get_unique_id,
     DbFile,
     ReadError,
+    ReadScriptError,
+    CheckpointPolicy,
+    SlowQueryLog,
+    AuthPolicy,
+    Extension,
+    DbFileConfig,
 };
 
 use sqlite3ffi::errmap::PrimaryRC;
+use sqlite3ffi::open::OpenFlags;
+use sqlite3ffi::blob::BlobHandle;
+use sqlite3ffi::limits::{
+    Limits,
+    set_soft_heap_limit,
+};
+use sqlite3ffi::hooks::RowChange;
+use sqlite3ffi::db_config::DbConfig;
+use sqlite3ffi::checkpoint::Checkpoint;
+use sqlite3ffi::function::{
+    ScalarFn,
+    ScalarFunction,
+};
+use sqlite3ffi::aggregate::{
+    AggregateFactory,
+    AggregateFunction,
+};
 
 pub type InMsgId = String;
 
@@ -55,7 +83,50 @@ impl InMsgWithId {
 #[derive(Debug)]
 pub struct WtxReq {
     pub id: InMsgId,
-    pub args: ArgsFile,
+    pub op: WriteReqOp,
+}
+
+// Requests that take the per-file write queue slot.
+// - `GetWriteTx` holds it until commit or rollback.
+// - `Backup` holds it until the backup completes, so the runtime does not write to the file during the backup (which would restart it).
+// - `Undo` and `Redo` hold it while their write tx runs, then respond.
+// - `Deserialize` holds it while the file is replaced.
+#[derive(Debug)]
+pub enum WriteReqOp {
+    GetWriteTx(ArgsFile),
+    Backup(ArgsBackup),
+    Undo(ArgsFile),
+    Redo(ArgsFile),
+    Checkpoint(ArgsCheckpoint),
+    Deserialize(ArgsDeserialize),
+}
+
+impl WtxReq {
+    fn get_flags(&self) -> Option<OpenFlags> {
+        match &self.op {
+            WriteReqOp::GetWriteTx(args) | WriteReqOp::Undo(args) | WriteReqOp::Redo(args) => args.flags.clone(),
+            WriteReqOp::Backup(_) | WriteReqOp::Checkpoint(_) | WriteReqOp::Deserialize(_) => None,
+        }
+    }
+
+    fn get_file_abs(&self) -> Result<FileAbs, FileOpErr> {
+        match &self.op {
+            WriteReqOp::GetWriteTx(args) | WriteReqOp::Undo(args) | WriteReqOp::Redo(args) => get_file_abs(&args.file, &args.flags),
+            WriteReqOp::Backup(args) => get_file_abs(&args.file, &None),
+            WriteReqOp::Checkpoint(args) => get_file_abs(&args.file, &None),
+            WriteReqOp::Deserialize(args) => get_file_abs(&args.file, &None),
+        }
+    }
+
+    fn to_err(&self, e: FileOpErr) -> OutMsg {
+        match &self.op {
+            WriteReqOp::GetWriteTx(_) => OutMsg::File(Err(e)),
+            WriteReqOp::Backup(_) => OutMsg::Backup(Err(e)),
+            WriteReqOp::Undo(_) | WriteReqOp::Redo(_) => OutMsg::Undo(Err(e)),
+            WriteReqOp::Checkpoint(_) => OutMsg::Checkpoint(Err(e)),
+            WriteReqOp::Deserialize(_) => OutMsg::Deserialize(Err(e)),
+        }
+    }
 }
 
 
@@ -66,10 +137,19 @@ pub struct WtxReq {
 // `wtx.into()` can be used as input to any type signature with `InMsgWithId`.
 impl From<WtxReq> for InMsgWithId {
     fn from(f: WtxReq) -> Self {
-        let WtxReq { id, args } = f;
+        let WtxReq { id, op } = f;
+        let msg = match op {
+            WriteReqOp::GetWriteTx(args) => File(GetWriteTx(args)),
+            WriteReqOp::Backup(args) => File(Backup(args)),
+            WriteReqOp::Undo(args) => File(Undo(args)),
+            WriteReqOp::Redo(args) => File(Redo(args)),
+            WriteReqOp::Checkpoint(args) => File(Checkpoint(args)),
+            WriteReqOp::Deserialize(args) => File(Deserialize(args)),
+        };
+
         InMsgWithId {
             id,
-            msg: File(GetWriteTx(args)),
+            msg,
         }
     }
 }
@@ -86,6 +166,9 @@ pub struct OutMsgWithId {
 pub struct Runtime {
     tx: Sender<InputItem>,
     thread_handle: Option<thread::JoinHandle<()>>,
+
+    // Shared with `ActiveTxs`; read each time a connection is opened.
+    config: Arc<RwLock<DbFileConfig>>,
 }
 
 
@@ -99,6 +182,17 @@ pub enum InputError {
 //    Duration::from_millis(i)
 //}
 
+// Longest single wait for another process to release a write lock; the request is retried (and waits again) after it.
+static WRITE_LOCK_WAIT_MS: u64 = 5000;
+
+// Pause before retrying when the wait fails at once (E.g. the lock is held by another connection in this process, which the busy timeout cannot wait on).
+// - Doubled on each retry of the same request, up to `WRITE_LOCK_RETRY_MAX_MS`.
+static WRITE_LOCK_RETRY_MS: u64 = 10;
+static WRITE_LOCK_RETRY_MAX_MS: u64 = 1000;
+
+// Retries of one request before it fails with `SQLITE_BUSY`.
+static WRITE_LOCK_MAX_RETRIES: u32 = 50;
+
 
 // A `Runtime` instance is intended to be owned by FFI host process.
 // - Assumption: When an FFI function is called from the host, it runs sync with the host.
@@ -111,10 +205,13 @@ impl Runtime {
 
         let (tx, rx): (Sender<InputItem>, Receiver<InputItem>) = mpsc::channel();
 
+        let config = Arc::new(RwLock::new(DbFileConfig::default()));
+
         // @todo/next keep index of outstanding requests; do not allow two ids.
         Runtime {
             tx: tx.clone(),
-            thread_handle: Some(start_thread(tx.clone(), rx, output_fn)),
+            thread_handle: Some(start_thread(tx.clone(), rx, config.clone(), output_fn)),
+            config,
         }
     }
 
@@ -139,11 +236,73 @@ impl Runtime {
 
         Ok(())
     }
+
+    // Makes a Rust closure callable from SQL in every tx started after this call, E.g. `SELECT name(?)`.
+    // - Applies to all connections opened by the process (see `sma::register_scalar_function`).
+    pub fn register_scalar_function(&self, name: &str, n_args: i32, deterministic: bool, f: Box<ScalarFn>) {
+        sma::register_scalar_function(ScalarFunction::new(name, n_args, deterministic, f));
+    }
+
+    // `new_state` is called once per group; see `sqlite3ffi::aggregate::Aggregate`.
+    pub fn register_aggregate_function(&self, name: &str, n_args: i32, deterministic: bool, is_window: bool, new_state: Box<AggregateFactory>) {
+        sma::register_aggregate_function(AggregateFunction::new(name, n_args, deterministic, is_window, new_state));
+    }
+
+    // When to checkpoint after a write tx commits; `None` = SQLites auto checkpoint, without warnings.
+    // - Applies to connections this runtime opens after this call (see `sma::DbFileConfig`).
+    // - Subscribers of `file/subscribe_checkpoint_warnings` get a `CheckpointWarning` when the checkpoint is blocked by a reader.
+    pub fn set_checkpoint_policy(&self, p: Option<CheckpointPolicy>) {
+        self.config.write().unwrap().checkpoint_policy = p;
+    }
+
+    // Logs statements slower than `threshold_ms` (read with `file/slow_queries`); `None` = off (the default).
+    // - Applies to connections this runtime opens after this call.
+    pub fn set_slow_query_log(&self, l: Option<SlowQueryLog>) {
+        self.config.write().unwrap().slow_query_log = l;
+    }
+
+    // Limits for every connection this runtime opens after this call (E.g. `Limits::untrusted()`); queries that hit one fail with `TxOpErr::LimitExceeded`.
+    pub fn set_limits(&self, l: Option<Limits>) {
+        self.config.write().unwrap().limits = l;
+    }
+
+    // Options for every connection this runtime opens after this call; `None` = `DbConfig::secure()`.
+    // - A flag that cannot be set fails the open (and so the tx request) instead of being skipped.
+    pub fn set_db_config(&self, c: Option<DbConfig>) {
+        self.config.write().unwrap().db_config = c;
+    }
+
+    // Call at startup, before the first tx; every connection this runtime opens after this call loads `e`, so tx queries can use their functions.
+    // - The paths are also added to the process wide allowlist (see `sma::DbFileConfig::set_extensions`).
+    pub fn set_extensions(&self, e: Vec<Extension>) -> Result<(), io::Error> {
+        self.config.write().unwrap().set_extensions(e)
+    }
+
+    // Checked as each statement on `file` is prepared, for txs started after this call; `None` removes it.
+    // - Denied queries fail with `TxOpErr::NotAuthorized`. See `sma::AuthPolicy`.
+    pub fn set_auth_policy(&self, file: &FilePath, p: Option<AuthPolicy>) -> Result<(), FileOpErr> {
+        let f_abs = get_file_abs(file, &None)?;
+        sma::set_auth_policy(&f_abs, p);
+        Ok(())
+    }
+
+    // Process wide, in bytes; `0` = no limit. Returns the previous limit.
+    // - Soft: SQLite frees cache memory to stay below it, but queries do not fail past it (see `sqlite3ffi::limits::set_soft_heap_limit`).
+    pub fn set_soft_heap_limit(&self, bytes: i64) -> i64 {
+        set_soft_heap_limit(bytes)
+    }
 }
 
 
 pub enum InputItem {
     InMsgWithId(InMsgWithId),
+
+    // Output from a worker thread (E.g. backup progress), passed to `output_fn` by the event loop.
+    Out(OutMsgWithId),
+
+    // Backup of `FileAbs` has finished; release its write queue slot, then respond.
+    BackupDone(FileAbs, OutMsgWithId),
+
     BreakLoop,
 }
 
@@ -175,14 +334,14 @@ impl Drop for Runtime {
 // @todo/medium Ensure all threads exits when `Runtime` is dropped.
 // @see evernote:///view/14186947/s134/b66753bd-d37c-4b40-8b10-6a57de70e765/b66753bd-d37c-4b40-8b10-6a57de70e765/
 // @todo/medium Auto rollback if no interaction.
-fn start_thread<F>(tx: Sender<InputItem>, rx: Receiver<InputItem>, output_fn: F) -> thread::JoinHandle<()> where F: Fn(OutMsgWithId) + Send + 'static {
+fn start_thread<F>(tx: Sender<InputItem>, rx: Receiver<InputItem>, config: Arc<RwLock<DbFileConfig>>, output_fn: F) -> thread::JoinHandle<()> where F: Fn(OutMsgWithId) + Send + 'static {
     let t = thread::Builder::new().name("sqlite-mpi.bg".to_string());
 
     return t.spawn(move || {
 //        println!("BG: {:?}", thread::current().id());
 
         // @see https://stackoverflow.com/questions/57578601/how-to-wait-on-multiple-mpsc-channels-with-different-priorities-to-create-an-ord
-        let mut at = ActiveTxs::new(tx);
+        let mut at = ActiveTxs::new(tx, config);
 
         // @todo/low Use binary heap with custom sort to order incoming messages (W.O, W, R.O, R).
         //  - Writes take priority.
@@ -194,16 +353,77 @@ fn start_thread<F>(tx: Sender<InputItem>, rx: Receiver<InputItem>, output_fn: F)
                         File(GetWriteTx(args)) => {
                             let wtx = WtxReq {
                                 id: in_msg.id,
-                                args,
+                                op: WriteReqOp::GetWriteTx(args),
+                            };
+
+                            maybe_queue_write_tx_req(&mut at, wtx, &output_fn);
+                        }
+                        File(Backup(args)) => {
+                            let wtx = WtxReq {
+                                id: in_msg.id,
+                                op: WriteReqOp::Backup(args),
                             };
 
                             maybe_queue_write_tx_req(&mut at, wtx, &output_fn);
                         }
+                        File(Undo(args)) => {
+                            let wtx = WtxReq {
+                                id: in_msg.id,
+                                op: WriteReqOp::Undo(args),
+                            };
+
+                            maybe_queue_write_tx_req(&mut at, wtx, &output_fn);
+                        }
+                        File(Redo(args)) => {
+                            let wtx = WtxReq {
+                                id: in_msg.id,
+                                op: WriteReqOp::Redo(args),
+                            };
+
+                            maybe_queue_write_tx_req(&mut at, wtx, &output_fn);
+                        }
+                        File(Checkpoint(args)) => {
+                            let wtx = WtxReq {
+                                id: in_msg.id,
+                                op: WriteReqOp::Checkpoint(args),
+                            };
+
+                            maybe_queue_write_tx_req(&mut at, wtx, &output_fn);
+                        }
+                        File(Deserialize(args)) => {
+                            let wtx = WtxReq {
+                                id: in_msg.id,
+                                op: WriteReqOp::Deserialize(args),
+                            };
+
+                            // Refuse instead of queuing behind an active write tx.
+                            match wtx.get_file_abs().map(|f_abs| at.get_tx_ids(&f_abs)) {
+                                Ok(ref ids) if !ids.is_empty() => output_fn(OutMsgWithId { in_msg_id: wtx.id.clone(), msg: wtx.to_err(FileOpErr::TxsActive(ids.clone())) }),
+                                _ => maybe_queue_write_tx_req(&mut at, wtx, &output_fn)
+                            }
+                        }
+                        File(SubscribeChanges(args)) => {
+                            match get_file_abs(&args.file, &args.flags) {
+                                Ok(f_abs) => at.subscribe_changes(&f_abs, in_msg.id),
+                                Err(e) => output_fn(OutMsgWithId { in_msg_id: in_msg.id, msg: file_err(e) })
+                            }
+                        }
+                        File(SubscribeCheckpointWarnings(args)) => {
+                            match get_file_abs(&args.file, &args.flags) {
+                                Ok(f_abs) => at.subscribe_checkpoint_warnings(&f_abs, in_msg.id),
+                                Err(e) => output_fn(OutMsgWithId { in_msg_id: in_msg.id, msg: file_err(e) })
+                            }
+                        }
                         _ => {
                             immediate_response(&mut at, in_msg, &output_fn)
                         }
                     }
                 }
+                InputItem::Out(out_msg) => output_fn(out_msg),
+                InputItem::BackupDone(f_abs, out_msg) => {
+                    at.wr_backup_done(&f_abs);
+                    output_fn(out_msg);
+                }
                 InputItem::BreakLoop => break
             }
         }
@@ -220,15 +440,14 @@ fn tx_err(e: TxOpErr) -> OutMsg {
 
 fn maybe_queue_write_tx_req<F>(at: &mut ActiveTxs, wtx_req: WtxReq, output_fn: &F) where F: Fn(OutMsgWithId) + Send + 'static {
     let id = wtx_req.id.clone();
-    let file = &wtx_req.args.file;
 
-    let file_op_res = process_write_req(at, wtx_req);
+    let out_msg = process_write_req(at, wtx_req);
 
     // If not queued, respond.
-    if let Some(res) = file_op_res {
+    if let Some(msg) = out_msg {
         let out_with_id = OutMsgWithId {
             in_msg_id: id,
-            msg: OutMsg::File(res),
+            msg,
         };
         output_fn(out_with_id);
     } else {
@@ -246,25 +465,31 @@ fn maybe_queue_write_tx_req<F>(at: &mut ActiveTxs, wtx_req: WtxReq, output_fn: &
 //}
 
 
-fn process_write_req(at: &mut ActiveTxs, wtx_req: WtxReq) -> Option<FileOpRes> {
+fn process_write_req(at: &mut ActiveTxs, wtx_req: WtxReq) -> Option<OutMsg> {
     use QState::*;
 
-    let WtxReq { id, args } = &wtx_req;
-    let file_path = &args.file;
-
-
-    let f_abs = match get_file_abs(file_path) {
+    let f_abs = match wtx_req.get_file_abs() {
         Ok(f_abs) => f_abs,
-        Err(e) => return Some(Err(e))
+        Err(e) => return Some(wtx_req.to_err(e))
     };
 
-    match at.wr_qstate(&f_abs) {
-        NextRetry(in_msg_id) if *id == *in_msg_id => process_wtx_req(at, wtx_req, &f_abs),
-        Empty => process_wtx_req(at, wtx_req, &f_abs),
-        Active(_) | NextRetry(_) => {
-            at.wr_queue(&f_abs, wtx_req);
-            None
-        }
+    let is_next = match at.wr_qstate(&f_abs) {
+        NextRetry(in_msg_id) if wtx_req.id == *in_msg_id => true,
+        Empty => true,
+        Active(_) | NextRetry(_) | QState::Backup(_) => false
+    };
+
+    if !is_next {
+        at.wr_queue(&f_abs, wtx_req);
+        return None;
+    }
+
+    match wtx_req.op {
+        WriteReqOp::GetWriteTx(_) => process_wtx_req(at, wtx_req, &f_abs).map(OutMsg::File),
+        WriteReqOp::Backup(_) => process_backup_req(at, wtx_req, &f_abs),
+        WriteReqOp::Undo(_) | WriteReqOp::Redo(_) => process_undo_req(at, wtx_req, &f_abs),
+        WriteReqOp::Checkpoint(_) => process_checkpoint_req(at, wtx_req, &f_abs),
+        WriteReqOp::Deserialize(_) => process_deserialize_req(at, wtx_req, &f_abs),
     }
 }
 
@@ -276,7 +501,7 @@ fn process_write_req(at: &mut ActiveTxs, wtx_req: WtxReq) -> Option<FileOpRes> {
 fn process_wtx_req(at: &mut ActiveTxs, wtx_req: WtxReq, f_abs: &FileAbs) -> Option<FileOpRes> {
 
     // @todo/low Make sure all `DbHandle::new` calls use `FileAbs` instead of `FilePath` (contains relative components; exact copy of API message input).
-    let f_res = get_file(&f_abs);
+    let f_res = get_file(&at.get_config(), &f_abs, &wtx_req.get_flags());
 
     match f_res {
         Ok(f) => {
@@ -288,15 +513,9 @@ fn process_wtx_req(at: &mut ActiveTxs, wtx_req: WtxReq, f_abs: &FileAbs) -> Opti
                 }
                 Err((_, rs)) => {
                     match rs.primary.id {
-                        PrimaryRC::SQLITE_BUSY => {
-
-
-                            // @todo/next Only retry a set amount of times: `if limit reached then wr_fail()`.
+                        PrimaryRC::SQLITE_BUSY if at.wr_retries(f_abs) < WRITE_LOCK_MAX_RETRIES => {
                             // @todo/important Cancel write tx's with no interactions after a certain time period.
-
-                            let d = ms(2000);
-                            at.wr_retry(f_abs, wtx_req, d);
-
+                            retry_when_unlocked(at, f_abs, wtx_req);
 
                             None
                         }
@@ -320,34 +539,318 @@ fn process_wtx_req(at: &mut ActiveTxs, wtx_req: WtxReq, f_abs: &FileAbs) -> Opti
 }
 
 
+// Another process holds the write lock: the files waiter waits for it to be released, then the request is retried on the event loop.
+// - The waiter only waits; it cannot hand its lock over, as a `WriteTx` cannot be moved between threads.
+// - If the lock is taken again in between (or the wait times out), the retry gets `SQLITE_BUSY` and waits again, up to `WRITE_LOCK_MAX_RETRIES` times.
+// - Locks held in this process are not waited on by the busy timeout (see `DbHandle::set_busy_timeout`); they are polled with a backoff (see `get_retry_backoff`).
+fn retry_when_unlocked(at: &mut ActiveTxs, f_abs: &FileAbs, wtx_req: WtxReq) {
+    let f = f_abs.clone();
+    let flags = wtx_req.get_flags();
+    let config = at.get_config();
+    let event_loop_in = at.get_event_loop_in();
+
+    at.wr_retry(f_abs, wtx_req, move || start_write_lock_waiter(config, f, flags, event_loop_in));
+}
+
+// One thread per file; keeps a single connection open for all of its waits.
+// - Exits when `ActiveTxs` (which holds the `WaiterIn`) is dropped.
+fn start_write_lock_waiter(config: DbFileConfig, f: FileAbs, flags: Option<OpenFlags>, event_loop_in: Sender<InputItem>) -> WaiterIn {
+    let (tx, rx): (WaiterIn, Receiver<(WtxReq, u32)>) = mpsc::channel();
+
+    thread::spawn(move || {
+        let mut db: Option<DbFile> = None;
+
+        for (wtx_req, retries) in rx.iter() {
+            // Errors are returned by the retry; opening is tried again on the next one.
+            if db.is_none() {
+                db = get_file(&config, &f, &flags).ok();
+            }
+
+            let is_unlocked = match &db {
+                Some(db) => db.wait_for_write_lock(ms(WRITE_LOCK_WAIT_MS)).is_ok(),
+                None => false
+            };
+
+            if !is_unlocked {
+                thread::sleep(get_retry_backoff(retries));
+            }
+
+            // Note: Send fails only when the runtime has been dropped.
+            if event_loop_in.send(InputItem::InMsgWithId(wtx_req.into())).is_err() {
+                break;
+            }
+        }
+    });
+
+    tx
+}
+
+// `retries` starts at 1.
+fn get_retry_backoff(retries: u32) -> Duration {
+    ms((WRITE_LOCK_RETRY_MS << retries.saturating_sub(1).min(16)).min(WRITE_LOCK_RETRY_MAX_MS))
+}
+
+
+// Runs the undo/redo in its own write tx, then releases the write queue slot.
+// - Change subscribers are notified via the event loop, so they receive `TxChanges` after this response.
+fn process_undo_req(at: &mut ActiveTxs, wtx_req: WtxReq, f_abs: &FileAbs) -> Option<OutMsg> {
+    let is_redo = match &wtx_req.op {
+        WriteReqOp::Undo(_) => false,
+        WriteReqOp::Redo(_) => true,
+        _ => unreachable!()
+    };
+
+    let f = match get_file(&at.get_config(), f_abs, &wtx_req.get_flags()) {
+        Ok(f) => f,
+        Err(e) => {
+            let out = wtx_req.to_err(e);
+            at.wr_fail(f_abs, wtx_req);
+            return Some(out);
+        }
+    };
+
+    let wtx = match f.get_write_tx() {
+        Ok(wtx) => wtx,
+        Err((_, rs)) => {
+            if rs.primary.id == PrimaryRC::SQLITE_BUSY && at.wr_retries(f_abs) < WRITE_LOCK_MAX_RETRIES {
+                retry_when_unlocked(at, f_abs, wtx_req);
+                return None;
+            }
+
+            let out = wtx_req.to_err(FileOpErr::ReturnStatus(rs));
+            at.wr_fail(f_abs, wtx_req);
+            return Some(out);
+        }
+    };
+
+    let tx_id = wtx.id.clone();
+    let step = if is_redo { wtx.redo() } else { wtx.undo() };
+
+    let res = match step {
+        Err(rs) => {
+            let _ = wtx.rollback();
+            Err(FileOpErr::ReturnStatus(rs))
+        }
+        Ok(step) => match wtx.commit_with_changes() {
+            (_, Err(rs)) => Err(FileOpErr::ReturnStatus(rs)),
+            (_, Ok((_, changes))) => {
+                let event_loop_in = at.get_event_loop_in();
+                for e in get_change_events(at, &tx_id, f_abs, changes) {
+                    let _ = event_loop_in.send(InputItem::Out(e));
+                }
+                Ok(step)
+            }
+        }
+    };
+
+    // The `DbFile` has been dropped, releasing the write lock for the next request.
+    at.wr_done(f_abs);
+    Some(OutMsg::Undo(res))
+}
+
+
+// Runs between write txs on its own connection, then releases the write queue slot.
+// - `SQLITE_BUSY` (E.g. `Full` while another process writes) is not an error; see `Checkpoint::is_busy`.
+fn process_checkpoint_req(at: &mut ActiveTxs, wtx_req: WtxReq, f_abs: &FileAbs) -> Option<OutMsg> {
+    let mode = match &wtx_req.op {
+        WriteReqOp::Checkpoint(args) => args.mode.clone(),
+        _ => unreachable!()
+    };
+
+    let res = get_file(&at.get_config(), f_abs, &None)
+        .and_then(|f| f.checkpoint(mode).map_err(FileOpErr::ReturnStatus))
+        .map(|c| get_checkpoint_status(at, f_abs, c));
+
+    at.wr_done(f_abs);
+    Some(OutMsg::Checkpoint(res))
+}
+
+// Read txs can start while the request is queued, so they are checked again.
+fn process_deserialize_req(at: &mut ActiveTxs, wtx_req: WtxReq, f_abs: &FileAbs) -> Option<OutMsg> {
+    let data = match &wtx_req.op {
+        WriteReqOp::Deserialize(args) => &args.data,
+        _ => unreachable!()
+    };
+
+    let tx_ids = at.get_tx_ids(f_abs);
+    let res = match tx_ids.is_empty() {
+        false => Err(FileOpErr::TxsActive(tx_ids)),
+        true => get_file(&at.get_config(), f_abs, &None)
+            .and_then(|f| f.deserialize(data).map_err(FileOpErr::ReturnStatus))
+            .map(|_| FileOnly { file: f_abs.clone() })
+    };
+
+    at.wr_done(f_abs);
+    Some(OutMsg::Deserialize(res))
+}
+
+fn get_checkpoint_status(at: &ActiveTxs, f_abs: &FileAbs, checkpoint: Checkpoint) -> CheckpointStatus {
+    let open_read_tx_ids = match checkpoint.is_complete() {
+        true => vec![],
+        false => at.get_read_tx_ids(f_abs),
+    };
+
+    CheckpointStatus {
+        file: f_abs.clone(),
+        checkpoint,
+        open_read_tx_ids,
+    }
+}
+
+
+// Runs the backup on a worker thread; progress and the final response are sent back via the event loop.
+// - `None` = the response is sent later by the worker.
+// - Reads and writes to other files continue while the backup runs. Reads of the source file continue; write tx requests for it are queued.
+fn process_backup_req(at: &mut ActiveTxs, wtx_req: WtxReq, f_abs: &FileAbs) -> Option<OutMsg> {
+    let args = match &wtx_req.op {
+        WriteReqOp::Backup(args) => args,
+        _ => unreachable!()
+    };
+
+    let dst_abs = match get_file_abs(&args.dst, &None) {
+        Ok(d) => d,
+        Err(e) => {
+            let out = wtx_req.to_err(e);
+            at.wr_fail(f_abs, wtx_req);
+            return Some(out);
+        }
+    };
+
+    let pages_per_step = args.pages_per_step.unwrap_or(100) as i32;
+    let id = wtx_req.id.clone();
+    let src_abs = f_abs.clone();
+    let config = at.get_config();
+    let event_loop_in = at.get_event_loop_in();
+
+    at.wr_backup(f_abs, id.clone());
+
+    thread::spawn(move || {
+        let to_out = |res: BackupRes| OutMsgWithId {
+            in_msg_id: id.clone(),
+            msg: OutMsg::Backup(res),
+        };
+
+        let mut last = None;
+        let res = DbFile::new_with_config(src_abs.clone(), &OpenFlags::default(), &config).and_then(|f| {
+            f.backup_to(dst_abs, pages_per_step, |p| {
+                if p.is_done {
+                    last = Some(p.clone());
+                    return;
+                }
+
+                // Note: Send fails only when the runtime has been dropped.
+                let _ = event_loop_in.send(InputItem::Out(to_out(Ok(p.clone()))));
+            })
+        });
+
+        let done = match (res, last) {
+            (Ok(_), Some(p)) => Ok(p),
+            (Ok(_), None) => unreachable!("Backup completed without a final step."),
+            (Err(rs), _) => Err(FileOpErr::ReturnStatus(rs)),
+        };
+
+        let _ = event_loop_in.send(InputItem::BackupDone(src_abs, to_out(done)));
+    });
+
+    None
+}
+
+
 fn immediate_response<F>(at: &mut ActiveTxs, in_msg: InMsgWithId, output_fn: &F) where F: Fn(OutMsgWithId) + Send + 'static {
     let InMsgWithId { id, msg } = in_msg;
 
-    let out_msg = get_res(at, &msg);
+    // Output for other input messages, sent after the response. E.g. `TxChanges` for subscribers.
+    let mut events = vec![];
+    let out_msg = get_res(at, &msg, &mut events);
+    let out_msg = to_typed_errs(at, &msg, out_msg);
 
     let out_with_id = OutMsgWithId {
         in_msg_id: id,
         msg: out_msg,
     };
     output_fn(out_with_id);
+
+    for e in events {
+        output_fn(e);
+    }
 }
 
 
-fn get_res(at: &mut ActiveTxs, i: &InMsg) -> OutMsg {
+// Errors caused by a connection limit or the files auth policy are reported as `TxOpErr::LimitExceeded` and `TxOpErr::NotAuthorized`, for every tx op.
+fn to_typed_errs(at: &ActiveTxs, i: &InMsg, o: OutMsg) -> OutMsg {
+    let take_denied = || match i {
+        Tx(op) => match get_tx(at, op) {
+            Ok(RW::Read(rtx)) => rtx.take_auth_denied(),
+            Ok(RW::Write(wtx)) => wtx.take_auth_denied(),
+            Err(_) => None
+        },
+        File(_) => None
+    };
+    let to_typed = |e: TxOpErr| e.to_limit_exceeded().to_not_authorized(take_denied);
+
+    match o {
+        OutMsg::Tx(Err(e)) => OutMsg::Tx(Err(to_typed(e))),
+        OutMsg::TxScript(Err(e)) => OutMsg::TxScript(Err(to_typed(e))),
+        OutMsg::TxBlob(Err(e)) => OutMsg::TxBlob(Err(to_typed(e))),
+        OutMsg::TxSession(Err(e)) => OutMsg::TxSession(Err(to_typed(e))),
+        OutMsg::TxExplain(Err(e)) => OutMsg::TxExplain(Err(to_typed(e))),
+        o => o
+    }
+}
+
+fn get_res(at: &mut ActiveTxs, i: &InMsg, events: &mut Vec<OutMsgWithId>) -> OutMsg {
 
     // @todo/next Store list of transactions, write queue. See `del_tx_data.json`.
 
     match i {
+        File(Serialize(args)) => {
+            let o = get_file(&at.get_config(), &args.file, &args.flags)
+                .and_then(|f| f.serialize().map(|data| FileImage { file: f.get_file_abs(), data }).map_err(FileOpErr::ReturnStatus));
+            return OutMsg::Serialize(o);
+        }
+        File(UnsubscribeChanges(args)) => {
+            let o = at.unsubscribe_changes(&args.subscribe_id)
+                .map(|file| FileOnly { file })
+                .ok_or(FileOpErr::NotSubscribed);
+            return OutMsg::Unsubscribe(o);
+        }
+        File(UnsubscribeCheckpointWarnings(args)) => {
+            let o = at.unsubscribe_checkpoint_warnings(&args.subscribe_id)
+                .map(|file| FileOnly { file })
+                .ok_or(FileOpErr::NotSubscribed);
+            return OutMsg::Unsubscribe(o);
+        }
+        File(SlowQueries(args)) => {
+            let o = get_file_abs(&args.file, &args.flags)
+                .map(|f_abs| crate::messages::SlowQueries { queries: sma::get_slow_queries(Some(&f_abs)), file: f_abs });
+            return OutMsg::SlowQueries(o);
+        }
         File(op) => {
             let o = process_file_op(at, &op);
             return OutMsg::File(o);
         }
+        Tx(op @ ExecScript(_)) => {
+            let o = with_timeout(at, &op, |at| process_tx_op_script(at, &op));
+            return OutMsg::TxScript(o);
+        }
+        Tx(op @ BlobRead(_)) | Tx(op @ BlobWrite(_)) => {
+            let o = process_tx_op_blob(at, &op);
+            return OutMsg::TxBlob(o);
+        }
+        Tx(op @ Explain(_)) => {
+            let o = process_tx_op_explain(at, &op);
+            return OutMsg::TxExplain(o);
+        }
+        Tx(op @ SessionStart(_)) | Tx(op @ Changeset(_)) | Tx(op @ ApplyChangeset(_)) => {
+            let o = process_tx_op_session(at, &op);
+            return OutMsg::TxSession(o);
+        }
         Tx(op) => {
             // @todo/low Use traits for the same functions on read/write txs?
 
             let o = match op.get_type() {
-                TxOpType::End => process_tx_op_end(at, &op),
-                _ => process_tx_op_body(at, &op)
+                TxOpType::End => process_tx_op_end(at, &op, events),
+                _ => with_timeout(at, &op, |at| process_tx_op_body(at, &op))
             };
             return OutMsg::Tx(o);
         }
@@ -358,7 +861,7 @@ fn get_res(at: &mut ActiveTxs, i: &InMsg) -> OutMsg {
 fn process_file_op(at: &mut ActiveTxs, op: &FileOp) -> FileOpRes {
     match op {
         GetReadTx(args) => {
-            let f = get_file(&args.file)?;
+            let f = get_file(&at.get_config(), &args.file, &args.flags)?;
             let f_abs = f.get_file_abs();
 
             match f.get_read_tx() {
@@ -375,7 +878,7 @@ fn process_file_op(at: &mut ActiveTxs, op: &FileOp) -> FileOpRes {
             // If active write, add to end of queue
             // Else begin
 
-            let f = get_file(&args.file)?;
+            let f = get_file(&at.get_config(), &args.file, &args.flags)?;
             let f_abs = f.get_file_abs();
 
             match f.get_write_tx() {
@@ -388,21 +891,98 @@ fn process_file_op(at: &mut ActiveTxs, op: &FileOp) -> FileOpRes {
                 Err((f, rs)) => return Err(FileOpErr::ReturnStatus(rs))
             }
         }
+        Backup(_) => unreachable!("`file/backup` is processed by the write queue."),
+        SubscribeChanges(_) => unreachable!("`file/subscribe_changes` has no direct response."),
+        SubscribeCheckpointWarnings(_) => unreachable!("`file/subscribe_checkpoint_warnings` has no direct response."),
+        Undo(_) | Redo(_) => unreachable!("`file/undo` and `file/redo` are processed by the write queue."),
+        Checkpoint(_) => unreachable!("`file/checkpoint` is processed by the write queue."),
+        Serialize(_) => unreachable!("`file/serialize` has its own response type."),
+        SlowQueries(_) => unreachable!("`file/slow_queries` has its own response type."),
+        UnsubscribeChanges(_) | UnsubscribeCheckpointWarnings(_) => unreachable!("Unsubscribing has its own response type."),
+        Deserialize(_) => unreachable!("`file/deserialize` is processed by the write queue.")
     }
 }
 
-fn get_file_abs(f_path: &FilePath) -> Result<FileAbs, FileOpErr> {
-    match normalize_file_path(f_path) {
+// Txs, write queues and auth policies are keyed on the canonical path, so a URI and a plain path to the same file share them.
+// - The URI is only used to open the file (see `get_file`).
+fn get_file_abs(f_path: &FilePath, flags: &Option<OpenFlags>) -> Result<FileAbs, FileOpErr> {
+    let path = match get_uri(f_path, flags) {
+        Some(uri) => get_uri_path(uri).ok_or(FileOpErr::InvalidUri)?,
+        None => f_path.clone()
+    };
+
+    match normalize_file_path(&path) {
         Some(s) => Ok(s),
         None => Err(FileOpErr::FileDirectoryDoesNotExist)
     }
 }
 
+fn get_uri<'a>(f_path: &'a FilePath, flags: &Option<OpenFlags>) -> Option<&'a FilePath> {
+    match flags {
+        Some(OpenFlags { uri: true, .. }) if f_path.starts_with("file:") => Some(f_path),
+        _ => None
+    }
+}
+
+// The file path of a `file:` URI; `None` for in-memory databases, remote authorities and invalid escapes.
+// @see https://www.sqlite.org/uri.html
+fn get_uri_path(uri: &str) -> Option<String> {
+    let rest = &uri["file:".len()..];
+
+    let (path, query) = match rest.find(|c| c == '?' || c == '#') {
+        Some(i) if rest[i..].starts_with('?') => (&rest[..i], rest[i + 1..].split('#').next().unwrap_or("")),
+        Some(i) => (&rest[..i], ""),
+        None => (rest, "")
+    };
+
+    // `file://localhost/x` and `file:///x` are the same as `file:/x`.
+    let path = match path.strip_prefix("//") {
+        Some(p) => {
+            let i = p.find('/').unwrap_or(p.len());
+            if !(p[..i].is_empty() || &p[..i] == "localhost") {
+                return None;
+            }
+            &p[i..]
+        }
+        None => path
+    };
+
+    let path = percent_decode(path)?;
+    let is_memory = path.is_empty() || path == ":memory:" || query.split('&').any(|kv| kv == "mode=memory");
+
+    if is_memory {
+        return None;
+    }
+
+    Some(path)
+}
+
+fn percent_decode(s: &str) -> Option<String> {
+    let b = s.as_bytes();
+    let mut out = Vec::with_capacity(b.len());
+    let mut i = 0;
+
+    while i < b.len() {
+        if b[i] == b'%' {
+            let hex = s.get(i + 1..i + 3)?;
+            out.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            out.push(b[i]);
+            i += 1;
+        }
+    }
+
+    String::from_utf8(out).ok()
+}
+
 
-fn get_file(f_path: &FilePath) -> Result<DbFile, FileOpErr> {
-    let file_abs = get_file_abs(f_path)?;
+fn get_file(config: &DbFileConfig, f_path: &FilePath, flags: &Option<OpenFlags>) -> Result<DbFile, FileOpErr> {
+    let file_abs = get_file_abs(f_path, flags)?;
+    let open_as = get_uri(f_path, flags).cloned().unwrap_or(file_abs);
+    let flags = flags.clone().unwrap_or_default();
 
-    match DbFile::new(file_abs.to_string()) {
+    match DbFile::new_with_config(open_as, &flags, config) {
         Ok(f) => Ok(f),
         Err(rs) => Err(FileOpErr::ReturnStatus(rs))
     }
@@ -428,7 +1008,7 @@ fn process_tx_op_body(at: &ActiveTxs, op: &TxOp) -> TxOpRes {
                             Ok(rs) => return Ok(rs)
                         }
                     }
-                    Write(_) | WriteParams(_) => {
+                    Write(_) | WriteParams(_) | EnableUndo(_) => {
                         return Err(TxOpErr::ReadError(ReadError::QueryIsWrite));
                     }
                     _ => {
@@ -476,6 +1056,12 @@ fn process_tx_op_body(at: &ActiveTxs, op: &TxOp) -> TxOpRes {
                             Ok(rs) => return Ok(rs)
                         }
                     }
+                    EnableUndo(a) => {
+                        match wtx.enable_undo(&a.tables) {
+                            Err(e) => return Err(TxOpErr::ReturnStatus(e)),
+                            Ok(rs) => return Ok(rs)
+                        }
+                    }
                     _ => {
                         assert!(false, "Commit or Rollback not possible");
                         return Err(TxOpErr::InvalidTxId);
@@ -486,9 +1072,142 @@ fn process_tx_op_body(at: &ActiveTxs, op: &TxOp) -> TxOpRes {
     }
 }
 
+// Interrupts `f` if it runs past the ops `timeout_ms`.
+// - SQLite rolls back a write tx when one of its writes is interrupted; the tx is then removed (releasing the write queue slot), so its id cannot write outside of a tx.
+fn with_timeout<T, F>(at: &mut ActiveTxs, op: &TxOp, f: F) -> Result<T, TxOpErr> where F: FnOnce(&ActiveTxs) -> Result<T, TxOpErr> {
+    let timeout_ms = match op.get_timeout_ms() {
+        Some(ms) => ms,
+        None => return f(at)
+    };
+    let d = Duration::from_millis(timeout_ms as u64);
+
+    let is_rolled_back = match get_tx(at, op)? {
+        RW::Read(rtx) => match rtx.with_timeout(d, |_| f(at)) {
+            (r, false) => return r,
+            (_, true) => false,
+        },
+        RW::Write(wtx) => match wtx.with_timeout(d, |_| f(at)) {
+            (r, false) => return r,
+            (_, true) => !wtx.is_active(),
+        }
+    };
+
+    if is_rolled_back {
+        if let Ok(RWO::Write(wtx)) = remove_tx(at, op) {
+            // Fails with "no transaction is active"; only drops the `WriteTx`.
+            let _ = wtx.rollback();
+        }
+    }
+
+    Err(TxOpErr::Timeout(QueryTimeout { timeout_ms, is_rolled_back }))
+}
+
+// A script may contain writes, so it is only run in a write tx (the same as `tx/write`).
+fn process_tx_op_script(at: &ActiveTxs, op: &TxOp) -> TxScriptRes {
+    let q = match op {
+        ExecScript(a) => &a.q,
+        _ => {
+            assert!(false, "Only ExecScript possible");
+            return Err(TxOpErr::InvalidTxId);
+        }
+    };
+
+    match get_tx(at, op)? {
+        RW::Read(rtx) => rtx.exec_script(q).map_err(|e| match e {
+            ReadScriptError::QueryIsWrite => TxOpErr::ReadError(ReadError::QueryIsWrite),
+            ReadScriptError::ScriptError(se) => TxOpErr::ScriptError(se)
+        }),
+        RW::Write(wtx) => wtx.exec_script(q).map_err(TxOpErr::ScriptError)
+    }
+}
+
+// Each chunk opens and closes its own blob handle, so no handle is left open between messages (an open handle would block `commit`).
+// @todo/low Keep the handle open between chunks of the same blob and use `sqlite3_blob_reopen` for other rows.
+fn process_tx_op_blob(at: &ActiveTxs, op: &TxOp) -> TxBlobRes {
+    let to_chunk = |b: &BlobHandle, offset: u32, data: Vec<u8>| BlobChunk {
+        blob_len: b.len(),
+        offset,
+        data,
+    };
+
+    match (get_tx(at, op)?, op) {
+        (RW::Read(rtx), BlobRead(a)) => {
+            let ArgsBlob { table, column, rowid } = &a.blob;
+            let b = rtx.open_blob(table, column, *rowid).map_err(TxOpErr::ReturnStatus)?;
+            let data = b.read_at(a.offset, a.len).map_err(TxOpErr::ReturnStatus)?;
+            Ok(to_chunk(&b, a.offset, data))
+        }
+        (RW::Read(_), BlobWrite(_)) => Err(TxOpErr::ReadError(ReadError::QueryIsWrite)),
+        (RW::Write(wtx), BlobRead(a)) => {
+            let ArgsBlob { table, column, rowid } = &a.blob;
+            let b = wtx.open_blob(table, column, *rowid, false).map_err(TxOpErr::ReturnStatus)?;
+            let data = b.read_at(a.offset, a.len).map_err(TxOpErr::ReturnStatus)?;
+            Ok(to_chunk(&b, a.offset, data))
+        }
+        (RW::Write(wtx), BlobWrite(a)) => {
+            let ArgsBlob { table, column, rowid } = &a.blob;
+            let b = wtx.open_blob(table, column, *rowid, true).map_err(TxOpErr::ReturnStatus)?;
+            b.write_at(a.offset, &a.data).map_err(TxOpErr::ReturnStatus)?;
+            Ok(to_chunk(&b, a.offset, vec![]))
+        }
+        _ => {
+            assert!(false, "Only BlobRead or BlobWrite possible");
+            return Err(TxOpErr::InvalidTxId);
+        }
+    }
+}
+
+fn process_tx_op_explain(at: &ActiveTxs, op: &TxOp) -> TxExplainRes {
+    let q = match op {
+        Explain(a) => &a.q,
+        _ => unreachable!()
+    };
+
+    let plan = match get_tx(at, op)? {
+        RW::Read(rtx) => rtx.explain_query_plan(q),
+        RW::Write(wtx) => wtx.explain_query_plan(q),
+    };
+
+    plan.map_err(TxOpErr::ReturnStatus)
+}
+
+fn process_tx_op_session(at: &ActiveTxs, op: &TxOp) -> TxSessionRes {
+    let wtx = match get_tx(at, op)? {
+        RW::Read(_) => return Err(TxOpErr::ReadError(ReadError::QueryIsWrite)),
+        RW::Write(wtx) => wtx,
+    };
+
+    let mut data = SessionData {
+        changeset: vec![],
+        conflicts: vec![],
+    };
+
+    match op {
+        SessionStart(a) => {
+            wtx.start_session(&a.tables).map_err(TxOpErr::ReturnStatus)?;
+        }
+        Changeset(a) => {
+            data.changeset = wtx.get_changeset(a.patchset).map_err(TxOpErr::SessionError)?;
+        }
+        ApplyChangeset(a) => {
+            let conflicts = &mut data.conflicts;
+            wtx.apply_changeset(&a.changeset, &mut |c| {
+                conflicts.push(c.clone());
+                a.on_conflict.clone()
+            }).map_err(TxOpErr::ReturnStatus)?;
+        }
+        _ => {
+            assert!(false, "Only SessionStart, Changeset or ApplyChangeset possible");
+            return Err(TxOpErr::InvalidTxId);
+        }
+    }
+
+    Ok(data)
+}
+
 // `commit` or `rollback`
 // Note: This removes the tx from the `ActiveTxs` so requires a mutable reference.
-fn process_tx_op_end(at: &mut ActiveTxs, op: &TxOp) -> TxOpRes {
+fn process_tx_op_end(at: &mut ActiveTxs, op: &TxOp, events: &mut Vec<OutMsgWithId>) -> TxOpRes {
     match remove_tx(at, &op) {
         Err(e) => return Err(e),
         Ok(t) => match t {
@@ -515,9 +1234,20 @@ fn process_tx_op_end(at: &mut ActiveTxs, op: &TxOp) -> TxOpRes {
             RWO::Write(wtx) => {
                 match op {
                     Commit(_) => {
-                        match wtx.commit() {
+                        let tx_id = wtx.id.clone();
+
+                        match wtx.commit_with_changes() {
                             (_, Err(e)) => return Err(TxOpErr::ReturnStatus(e)),
-                            (_, Ok(rs)) => return Ok(rs)
+                            (mut f, Ok((rs, changes))) => {
+                                let f_abs = f.get_file_abs();
+                                events.extend(get_change_events(at, &tx_id, &f_abs, changes));
+
+                                if let Some(c) = f.take_auto_checkpoint() {
+                                    events.extend(get_checkpoint_warnings(at, &f_abs, c));
+                                }
+
+                                return Ok(rs);
+                            }
                         }
                     }
                     Rollback(_) => {
@@ -537,6 +1267,38 @@ fn process_tx_op_end(at: &mut ActiveTxs, op: &TxOp) -> TxOpRes {
 }
 
 
+// One `TxChanges` per `file/subscribe_changes` subscriber of `f_abs`.
+fn get_change_events(at: &ActiveTxs, tx_id: &TxId, f_abs: &FileAbs, changes: Vec<RowChange>) -> Vec<OutMsgWithId> {
+    if changes.is_empty() {
+        return vec![];
+    }
+
+    at.get_change_subs(f_abs).into_iter().map(|in_msg_id| {
+        let msg = OutMsg::TxChanges(TxChanges {
+            tx_id: tx_id.clone(),
+            file: f_abs.clone(),
+            changes: changes.clone(),
+        });
+
+        OutMsgWithId { in_msg_id, msg }
+    }).collect()
+}
+
+
+// One `CheckpointWarning` per `file/subscribe_checkpoint_warnings` subscriber of `f_abs`, when the checkpoint was not complete.
+fn get_checkpoint_warnings(at: &ActiveTxs, f_abs: &FileAbs, c: Checkpoint) -> Vec<OutMsgWithId> {
+    if c.is_complete() {
+        return vec![];
+    }
+
+    let status = get_checkpoint_status(at, f_abs, c);
+
+    at.get_checkpoint_subs(f_abs).into_iter().map(|in_msg_id| {
+        OutMsgWithId { in_msg_id, msg: OutMsg::CheckpointWarning(status.clone()) }
+    }).collect()
+}
+
+
 fn get_tx<'a>(at: &'a ActiveTxs, tx_op: &TxOp) -> Result<RW<'a>, TxOpErr> {
     let tx_id = tx_op.get_tx_id();
 
@@ -558,21 +1320,5 @@ fn remove_tx(at: &mut ActiveTxs, tx_op: &TxOp) -> Result<RWO, TxOpErr> {
 
 // Expects a path to an existing directory, but the file does not have to exist.
 fn normalize_file_path(f: &String) -> Option<String> {
-    let full = Path::new(&f);
-
-    let dir_file = (
-        full.parent(),
-        full.file_name()
-    );
-
-    if let (Some(dir), Some(file)) = dir_file {
-
-        // SQLite FFI will create a file, but will not create directories.
-        if !full.is_dir() && dir.is_dir() {
-            let normal_dir = fs::canonicalize(dir).expect("Ok");
-            return Some(normal_dir.join(file).to_str().unwrap().to_string());
-        }
-    }
-
-    None
+    sma::fns::get_canonical_file(f)
 }
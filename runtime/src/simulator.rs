@@ -62,6 +62,33 @@ pub fn get_tx_q(tx_id: &str, q: &str) -> InMsg {
     Tx(Q(ArgsTx {
         tx_id: tx_id.to_string(),
         q: q.to_string(),
+        timeout_ms: None,
+    }))
+}
+
+pub fn get_tx_exec_script(tx_id: &str, q: &str) -> InMsg {
+    Tx(ExecScript(ArgsTx {
+        tx_id: tx_id.to_string(),
+        q: q.to_string(),
+        timeout_ms: None,
+    }))
+}
+
+pub fn get_tx_blob_read(tx_id: &str, blob: ArgsBlob, offset: u32, len: u32) -> InMsg {
+    Tx(BlobRead(ArgsBlobRead {
+        tx_id: tx_id.to_string(),
+        blob,
+        offset,
+        len,
+    }))
+}
+
+pub fn get_tx_blob_write(tx_id: &str, blob: ArgsBlob, offset: u32, data: Vec<u8>) -> InMsg {
+    Tx(BlobWrite(ArgsBlobWrite {
+        tx_id: tx_id.to_string(),
+        blob,
+        offset,
+        data,
     }))
 }
 
@@ -115,7 +142,7 @@ pub fn integrity_check(f: String) -> bool {
 // - Why start with tx? Initial focus is on clear semantics of SQLite lock mapping to async/await state.
 pub fn one_read(f: String, q: &str) -> RSet {
     let (i, o) = get_new_runtime_async();
-    let a = i(File(GetReadTx(ArgsFile { file: f.clone() })));
+    let a = i(File(GetReadTx(ArgsFile { file: f.clone(), flags: None })));
     if let OutMsg::File(Ok(TxIdOnly { tx_id })) = get_single(&o, &a) {
         let b = i(get_tx_q(&tx_id, &q));
 
@@ -166,7 +193,7 @@ pub fn no_response(o: &O, ids: &Vec<&InMsgId>) {
 pub fn writes(i: &I, f: &FileAbs, n: u32) -> Vec<InMsgId> {
     let mut v = vec![];
     for _ in 0..n {
-        v.push(i(File(GetWriteTx(ArgsFile { file: f.clone() }))));
+        v.push(i(File(GetWriteTx(ArgsFile { file: f.clone(), flags: None }))));
     }
     v
 }
@@ -174,7 +201,7 @@ pub fn writes(i: &I, f: &FileAbs, n: u32) -> Vec<InMsgId> {
 pub fn reads(i: &I, f: &FileAbs, n: u32) -> Vec<InMsgId> {
     let mut v = vec![];
     for _ in 0..n {
-        v.push(i(File(GetReadTx(ArgsFile { file: f.clone() }))));
+        v.push(i(File(GetReadTx(ArgsFile { file: f.clone(), flags: None }))));
     }
     v
 }
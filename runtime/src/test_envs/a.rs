@@ -25,9 +25,9 @@ impl EnvA {
     pub fn new(f: String) -> EnvA {
         let (i, o) = get_new_runtime_async();
 
-        let a = i(File(GetReadTx(ArgsFile { file: f.clone() })));
-        let b = i(File(GetWriteTx(ArgsFile { file: f.clone() })));
-        let c = i(File(GetWriteTx(ArgsFile { file: f.clone() })));
+        let a = i(File(GetReadTx(ArgsFile { file: f.clone(), flags: None })));
+        let b = i(File(GetWriteTx(ArgsFile { file: f.clone(), flags: None })));
+        let c = i(File(GetWriteTx(ArgsFile { file: f.clone(), flags: None })));
 
         // Assert: `a` and `b` have responses.
         let mut h = get_response(&o, &vec![&a, &b]);
@@ -215,7 +215,7 @@ impl EnvA {
             ..
         } = self;
 
-        let a = i(File(GetReadTx(ArgsFile { file: file.to_string() })));
+        let a = i(File(GetReadTx(ArgsFile { file: file.to_string(), flags: None })));
         let mut h = get_response(&o, &vec![&a]);
 
         match h.remove(&a) {
@@ -13,6 +13,9 @@ use serde_json::{
 use sma::Params;
 
 use sqlite3ffi::stmt::KeyVal;
+use sqlite3ffi::open::{OpenFlags, OpenMode, Threading};
+use sqlite3ffi::session::ConflictAction;
+use sqlite3ffi::checkpoint::CheckpointMode;
 
 
 #[cfg(test)]
@@ -36,7 +39,317 @@ mod tests {
 
         let to = InMsgWithId {
             id,
-            msg: InMsg::File(FileOp::GetReadTx(ArgsFile { file: "a/b/c.sqlite".to_string() })),
+            msg: InMsg::File(FileOp::GetReadTx(ArgsFile { file: "a/b/c.sqlite".to_string(), flags: None })),
+        };
+
+        assert_eq!(in_json_to_rs(&from).expect("Ok"), to);
+    }
+
+
+    #[test]
+    fn test_read_tx_flags() {
+        let id = "example-uuid".to_string();
+        let from = json!({
+            "id": id,
+            "fn": "file/get_read_tx",
+            "args": {
+                "file": "a/b/c.sqlite",
+                "flags": {
+                    "mode": "read_only",
+                    "threading": "no_mutex"
+                }
+            }
+        }).to_string();
+
+        let flags = OpenFlags {
+            mode: OpenMode::ReadOnly,
+            threading: Some(Threading::NoMutex),
+            ..Default::default()
+        };
+
+        let to = InMsgWithId {
+            id,
+            msg: InMsg::File(FileOp::GetReadTx(ArgsFile { file: "a/b/c.sqlite".to_string(), flags: Some(flags) })),
+        };
+
+        assert_eq!(in_json_to_rs(&from).expect("Ok"), to);
+    }
+
+
+    #[test]
+    fn test_backup() {
+        let id = "example-uuid".to_string();
+        let from = json!({
+            "id": id,
+            "fn": "file/backup",
+            "args": {
+                "file": "a/b/c.sqlite",
+                "dst": "a/b/c-backup.sqlite"
+            }
+        }).to_string();
+
+        let to = InMsgWithId {
+            id,
+            msg: InMsg::File(FileOp::Backup(ArgsBackup { file: "a/b/c.sqlite".to_string(), dst: "a/b/c-backup.sqlite".to_string(), pages_per_step: None })),
+        };
+
+        assert_eq!(in_json_to_rs(&from).expect("Ok"), to);
+    }
+
+
+    #[test]
+    fn test_subscribe_changes() {
+        let id = "example-uuid".to_string();
+        let from = json!({
+            "id": id,
+            "fn": "file/subscribe_changes",
+            "args": {
+                "file": "a/b/c.sqlite"
+            }
+        }).to_string();
+
+        let to = InMsgWithId {
+            id,
+            msg: InMsg::File(FileOp::SubscribeChanges(ArgsFile { file: "a/b/c.sqlite".to_string(), flags: None })),
+        };
+
+        assert_eq!(in_json_to_rs(&from).expect("Ok"), to);
+    }
+
+
+    #[test]
+    fn test_subscribe_checkpoint_warnings() {
+        let id = "example-uuid".to_string();
+        let from = json!({
+            "id": id,
+            "fn": "file/subscribe_checkpoint_warnings",
+            "args": {
+                "file": "a/b/c.sqlite"
+            }
+        }).to_string();
+
+        let to = InMsgWithId {
+            id,
+            msg: InMsg::File(FileOp::SubscribeCheckpointWarnings(ArgsFile { file: "a/b/c.sqlite".to_string(), flags: None })),
+        };
+
+        assert_eq!(in_json_to_rs(&from).expect("Ok"), to);
+    }
+
+
+    #[test]
+    fn test_unsubscribe_changes() {
+        let id = "example-uuid".to_string();
+        let from = json!({
+            "id": id,
+            "fn": "file/unsubscribe_changes",
+            "args": {
+                "subscribe_id": "subscribe-uuid"
+            }
+        }).to_string();
+
+        let to = InMsgWithId {
+            id,
+            msg: InMsg::File(FileOp::UnsubscribeChanges(ArgsUnsubscribe { subscribe_id: "subscribe-uuid".to_string() })),
+        };
+
+        assert_eq!(in_json_to_rs(&from).expect("Ok"), to);
+    }
+
+
+    #[test]
+    fn test_apply_changeset() {
+        let id = "example-uuid".to_string();
+        let from = json!({
+            "id": id,
+            "fn": "tx/apply_changeset",
+            "args": {
+                "tx_id": "abc",
+                "changeset": [1, 2, 3],
+                "on_conflict": "replace"
+            }
+        }).to_string();
+
+        let to = InMsgWithId {
+            id,
+            msg: InMsg::Tx(TxOp::ApplyChangeset(ArgsApplyChangeset { tx_id: "abc".to_string(), changeset: vec![1, 2, 3], on_conflict: ConflictAction::Replace })),
+        };
+
+        assert_eq!(in_json_to_rs(&from).expect("Ok"), to);
+    }
+
+
+    #[test]
+    fn test_undo() {
+        let id = "example-uuid".to_string();
+        let from = json!({
+            "id": id,
+            "fn": "file/undo",
+            "args": {
+                "file": "a/b/c.sqlite"
+            }
+        }).to_string();
+
+        let to = InMsgWithId {
+            id,
+            msg: InMsg::File(FileOp::Undo(ArgsFile { file: "a/b/c.sqlite".to_string(), flags: None })),
+        };
+
+        assert_eq!(in_json_to_rs(&from).expect("Ok"), to);
+    }
+
+
+    #[test]
+    fn test_checkpoint() {
+        let id = "example-uuid".to_string();
+        let from = json!({
+            "id": id,
+            "fn": "file/checkpoint",
+            "args": {
+                "file": "a/b/c.sqlite",
+                "mode": "truncate"
+            }
+        }).to_string();
+
+        let to = InMsgWithId {
+            id,
+            msg: InMsg::File(FileOp::Checkpoint(ArgsCheckpoint { file: "a/b/c.sqlite".to_string(), mode: CheckpointMode::Truncate })),
+        };
+
+        assert_eq!(in_json_to_rs(&from).expect("Ok"), to);
+    }
+
+
+    #[test]
+    fn test_deserialize() {
+        let id = "example-uuid".to_string();
+        let from = json!({
+            "id": id,
+            "fn": "file/deserialize",
+            "args": {
+                "file": "a/b/c.sqlite",
+                "data": [83, 81, 76]
+            }
+        }).to_string();
+
+        let to = InMsgWithId {
+            id,
+            msg: InMsg::File(FileOp::Deserialize(ArgsDeserialize { file: "a/b/c.sqlite".to_string(), data: vec![83, 81, 76] })),
+        };
+
+        assert_eq!(in_json_to_rs(&from).expect("Ok"), to);
+    }
+
+
+    #[test]
+    fn test_slow_queries() {
+        let id = "example-uuid".to_string();
+        let from = json!({
+            "id": id,
+            "fn": "file/slow_queries",
+            "args": {
+                "file": "a/b/c.sqlite"
+            }
+        }).to_string();
+
+        let to = InMsgWithId {
+            id,
+            msg: InMsg::File(FileOp::SlowQueries(ArgsFile { file: "a/b/c.sqlite".to_string(), flags: None })),
+        };
+
+        assert_eq!(in_json_to_rs(&from).expect("Ok"), to);
+    }
+
+
+    #[test]
+    fn test_explain() {
+        let id = "example-uuid".to_string();
+        let from = json!({
+            "id": id,
+            "fn": "tx/explain",
+            "args": {
+                "tx_id": "x",
+                "q": "SELECT * FROM t1"
+            }
+        }).to_string();
+
+        let to = InMsgWithId {
+            id,
+            msg: InMsg::Tx(TxOp::Explain(ArgsTx { tx_id: "x".to_string(), q: "SELECT * FROM t1".to_string(), timeout_ms: None })),
+        };
+
+        assert_eq!(in_json_to_rs(&from).expect("Ok"), to);
+    }
+
+
+    #[test]
+    fn test_exec_script() {
+        let id = "example-uuid".to_string();
+        let from = json!({
+            "id": id,
+            "fn": "tx/exec_script",
+            "args": {
+                "tx_id": "abc",
+                "q": "CREATE TABLE t1(a); CREATE TABLE t2(a);"
+            }
+        }).to_string();
+
+
+        let to = InMsgWithId {
+            id,
+            msg: InMsg::Tx(TxOp::ExecScript(ArgsTx { tx_id: "abc".to_string(), q: "CREATE TABLE t1(a); CREATE TABLE t2(a);".to_string(), timeout_ms: None })),
+        };
+
+        assert_eq!(in_json_to_rs(&from).expect("Ok"), to);
+    }
+
+
+    #[test]
+    fn test_q_timeout() {
+        let id = "example-uuid".to_string();
+        let from = json!({
+            "id": id,
+            "fn": "tx/read",
+            "args": {
+                "tx_id": "abc",
+                "q": "SELECT 1",
+                "timeout_ms": 500
+            }
+        }).to_string();
+
+        let to = InMsgWithId {
+            id,
+            msg: InMsg::Tx(TxOp::Read(ArgsTx { tx_id: "abc".to_string(), q: "SELECT 1".to_string(), timeout_ms: Some(500) })),
+        };
+
+        assert_eq!(in_json_to_rs(&from).expect("Ok"), to);
+    }
+
+
+    #[test]
+    fn test_blob_write() {
+        let id = "example-uuid".to_string();
+        let from = json!({
+            "id": id,
+            "fn": "tx/blob_write",
+            "args": {
+                "tx_id": "abc",
+                "table": "t1",
+                "column": "data",
+                "rowid": 1,
+                "offset": 4,
+                "data": [1, 2, 3]
+            }
+        }).to_string();
+
+        let to = InMsgWithId {
+            id,
+            msg: InMsg::Tx(TxOp::BlobWrite(ArgsBlobWrite {
+                tx_id: "abc".to_string(),
+                blob: ArgsBlob { table: "t1".to_string(), column: "data".to_string(), rowid: 1 },
+                offset: 4,
+                data: vec![1, 2, 3],
+            })),
         };
 
         assert_eq!(in_json_to_rs(&from).expect("Ok"), to);
@@ -56,6 +369,7 @@ mod tests {
             tx_id: "1".to_string(),
             q: "123".to_string(),
             params: Params::Index(vec![Val::String("abc".to_string())]),
+            timeout_ms: None,
         };
         let j = serde_json::to_string(&a).expect("Ok");
 
@@ -102,6 +416,7 @@ mod tests {
             tx_id: "1".to_string(),
             q: "123".to_string(),
             params: Params::Key(KeyVal { data: data.clone() }),
+            timeout_ms: None,
         };
         let j = serde_json::to_string(&a).expect("Ok");
 
@@ -15,6 +15,9 @@ use sqlite3ffi::stmt::KeyVal;
 use sqlite3ffi::err::{to_return_status, ReturnStatus};
 use sqlite3ffi::errmap::PrimaryRC;
 use sqlite3ffi::db::BindRunError;
+use sqlite3ffi::script::ScriptError;
+use sqlite3ffi::limits::Limit;
+use sqlite3ffi::auth::{AuthAction, AuthContext};
 
 
 use crate::messages::{
@@ -43,6 +46,7 @@ impl ErrTx {
         o.push(TxOpErr::InvalidTxId);
         o.push(TxOpErr::ReturnStatus(busy.clone()));
         o.push(TxOpErr::BindRunError(BindRunError::ReturnStatus(busy.clone())));
+        o.push(TxOpErr::ScriptError(ScriptError { index: 1, offset: 10, return_status: busy.clone() }));
 
         // @todo/low Test all variants.
 
@@ -81,6 +85,11 @@ mod tests {
         out_rs_to_json(&o).unwrap()
     }
 
+    fn res_ok_tx_script() -> String {
+        let o = TxScript(Ok(vec![RSet::default(), RSet::default()]));
+        out_rs_to_json(&o).unwrap()
+    }
+
     fn res_err_file_no_dir() -> String {
         let o = File(Err(FileOpErr::FileDirectoryDoesNotExist));
         out_rs_to_json(&o).unwrap()
@@ -165,9 +174,14 @@ mod tests {
     fn test_ok() {
         let a = res_ok_file();
         let b = res_ok_tx();
+        let c = res_ok_tx_script();
 
         assert!(is_ok_res_str(&a));
         assert!(is_ok_res_str(&b));
+        assert!(is_ok_res_str(&c));
+
+        let v: Value = serde_json::from_str(&c).unwrap();
+        assert_eq!(v["res"]["rsets"].as_array().unwrap().len(), 2);
     }
 
     #[test]
@@ -193,4 +207,54 @@ mod tests {
             assert!(is_err_res_str(&string));
         }
     }
+
+    // Assert: Script errors include the position of the failed statement.
+    #[test]
+    fn test_err_tx_script() {
+        let busy: ReturnStatus = PrimaryRC::SQLITE_BUSY.into();
+        let string = ErrTx::enum_to_json(TxOpErr::ScriptError(ScriptError { index: 1, offset: 10, return_status: busy }));
+        let v: Value = serde_json::from_str(&string).unwrap();
+
+        assert_eq!(v["error"]["error_type"], "TxOp/ScriptError");
+        assert_eq!(v["error"]["data"]["stmt_index"], 1);
+        assert_eq!(v["error"]["data"]["stmt_offset"], 10);
+        assert!(err_has_rs(&v));
+    }
+
+    // Assert: Timeouts say whether the tx was rolled back.
+    #[test]
+    fn test_err_tx_timeout() {
+        let string = ErrTx::enum_to_json(TxOpErr::Timeout(QueryTimeout { timeout_ms: 20, is_rolled_back: true }));
+        let v: Value = serde_json::from_str(&string).unwrap();
+
+        assert_eq!(v["error"]["error_type"], "TxOp/Timeout");
+        assert_eq!(v["error"]["data"]["is_rolled_back"], true);
+        assert!(is_err_res_str(&string));
+    }
+
+    // Assert: Limit errors name the limit.
+    #[test]
+    fn test_err_tx_limit_exceeded() {
+        let e: ReturnStatus = PrimaryRC::SQLITE_TOOBIG.into();
+        let string = ErrTx::enum_to_json(TxOpErr::LimitExceeded(LimitExceeded { limit: Limit::SqlLength, return_status: e }));
+        let v: Value = serde_json::from_str(&string).unwrap();
+
+        assert_eq!(v["error"]["error_type"], "TxOp/LimitExceeded");
+        assert_eq!(v["error"]["message"], "Query exceeded the connection limit `sql_length`.");
+        assert_eq!(v["error"]["data"]["limit"], "sql_length");
+        assert!(err_has_rs(&v));
+    }
+
+
+    #[test]
+    fn test_err_tx_not_authorized() {
+        let e: ReturnStatus = PrimaryRC::SQLITE_AUTH.into();
+        let denied = AuthContext { action: AuthAction::Attach { file: "x.sqlite".to_string() }, db_name: None, accessor: None };
+        let string = ErrTx::enum_to_json(TxOpErr::NotAuthorized(NotAuthorized { denied: Some(denied), return_status: e }));
+        let v: Value = serde_json::from_str(&string).unwrap();
+
+        assert_eq!(v["error"]["error_type"], "TxOp/NotAuthorized");
+        assert_eq!(v["error"]["data"]["denied"]["action"]["attach"]["file"], "x.sqlite");
+        assert!(err_has_rs(&v));
+    }
 }
@@ -15,6 +15,15 @@ use test_envs::a::EnvA;
 // Use library directly to check file state (instead of going through sma/runtime).
 use sqlite3ffi::db::DbHandle;
 use sqlite3ffi::stmt::Val;
+use sqlite3ffi::open::OpenFlags;
+use sqlite3ffi::hooks::ChangeOp;
+use sqlite3ffi::session::{ConflictAction, ConflictType};
+use sqlite3ffi::checkpoint::CheckpointMode;
+use sma::SessionError;
+use sma::SlowQueryLog;
+use sqlite3ffi::limits::{Limit, Limits};
+use sqlite3ffi::auth::AuthAction;
+use sma::AuthPolicy;
 
 // Place outside src so that file writes do not trigger `cargo watch`.
 static TEST_OUTPUT_DIR: &'static str = "/tmp";
@@ -53,14 +62,16 @@ mod tests {
         let req_a = File(
             GetReadTx(
                 ArgsFile {
-                    file: fa.clone()
+                    file: fa.clone(),
+                    flags: None
                 }
             )
         );
         let req_b = File(
             GetReadTx(
                 ArgsFile {
-                    file: fb.clone()
+                    file: fb.clone(),
+                    flags: None
                 }
             )
         );
@@ -142,6 +153,664 @@ mod tests {
     }
 
 
+    // Assert: Open flags are used for the file connection.
+    #[test]
+    fn test_runtime_open_flags() {
+        let get = get_new_runtime();
+        let f = get_test_file();
+
+        let read_only = || Some(OpenFlags::read_only());
+
+        // Assert: A missing file is not created.
+        match get(File(GetReadTx(ArgsFile { file: f.clone(), flags: read_only() }))) {
+            OutMsg::File(Err(FileOpErr::ReturnStatus(rs))) => assert_eq!(rs.primary.id, PrimaryRC::SQLITE_CANTOPEN),
+            _ => assert!(false)
+        };
+
+        let w = get_tx_id(get(File(GetWriteTx(ArgsFile { file: f.clone(), flags: None }))));
+        get(get_tx_q(&w, "CREATE TABLE t1(a)"));
+        get(get_tx_commit(&w));
+
+        let r = get_tx_id(get(File(GetReadTx(ArgsFile { file: f.clone(), flags: read_only() }))));
+        match get(get_tx_q(&r, "SELECT * FROM t1")) {
+            OutMsg::Tx(Ok(_)) => assert!(true),
+            _ => assert!(false)
+        };
+
+        integrity(f);
+    }
+
+
+    // Assert: A URI and a plain path to the same file share the write queue.
+    #[test]
+    fn test_runtime_uri_file_key() {
+        let (i, o) = get_new_runtime_async();
+        let f = get_test_file();
+        let uri_flags = Some(OpenFlags { uri: true, ..Default::default() });
+        let write_uri = |uri: String| i(File(GetWriteTx(ArgsFile { file: uri, flags: uri_flags.clone() })));
+
+        let w_uri = get_tx_id(get_single(&o, &write_uri(format!("file://localhost{}?cache=private", f.replace(".sqlite3", "%2Esqlite3")))));
+        q(&i, &o, &w_uri, &"CREATE TABLE t1(a)".to_string());
+
+        let w_path = writes(&i, &f, 1);
+        no_response(&o, &w_path.iter().collect());
+
+        commit(&i, &o, &w_uri);
+        let w = get_tx_id(get_single(&o, &w_path[0]));
+        commit(&i, &o, &w);
+
+        for uri in vec!["file::memory:".to_string(), format!("file:{}?mode=memory", f), format!("file://other-host{}", f)] {
+            match get_single(&o, &i(File(GetReadTx(ArgsFile { file: uri, flags: uri_flags.clone() })))) {
+                OutMsg::File(Err(FileOpErr::InvalidUri)) => {}
+                _ => assert!(false)
+            }
+        }
+    }
+
+
+    // Assert: Subscribers get the rows changed by each committed write tx, and nothing for rolled back txs.
+    #[test]
+    fn test_runtime_subscribe_changes() {
+        let (i, o) = get_new_runtime_async();
+        let f = get_test_file();
+
+        let s = i(File(SubscribeChanges(ArgsFile { file: f.clone(), flags: None })));
+        no_response(&o, &vec![&s]);
+
+        let w1 = get_tx_id(get_single(&o, &writes(&i, &f, 1)[0]));
+        q(&i, &o, &w1, &"CREATE TABLE t1(id INTEGER PRIMARY KEY, a)".to_string());
+        q(&i, &o, &w1, &"INSERT INTO t1 (id, a) VALUES (1, 'a'), (2, 'b')".to_string());
+        q(&i, &o, &w1, &"DELETE FROM t1 WHERE id = 1".to_string());
+        commit(&i, &o, &w1);
+
+        match get_single(&o, &s) {
+            OutMsg::TxChanges(c) => {
+                assert_eq!(c.tx_id, w1);
+                assert_eq!(c.changes.iter().map(|rc| (rc.op.clone(), rc.rowid)).collect::<Vec<_>>(), vec![
+                    (ChangeOp::Insert, 1),
+                    (ChangeOp::Insert, 2),
+                    (ChangeOp::Delete, 1),
+                ]);
+            }
+            _ => assert!(false)
+        }
+
+        let w2 = get_tx_id(get_single(&o, &writes(&i, &f, 1)[0]));
+        q(&i, &o, &w2, &"INSERT INTO t1 (id, a) VALUES (3, 'c')".to_string());
+        get_single(&o, &i(get_tx_rollback(&w2)));
+        no_response(&o, &vec![&s]);
+
+        let unsub = |id: &String| match get_single(&o, &i(File(UnsubscribeChanges(ArgsUnsubscribe { subscribe_id: id.clone() })))) {
+            OutMsg::Unsubscribe(r) => r,
+            _ => panic!("Expected Unsubscribe")
+        };
+        assert!(unsub(&s).expect("Ok").file.ends_with(&f));
+
+        let w3 = get_tx_id(get_single(&o, &writes(&i, &f, 1)[0]));
+        q(&i, &o, &w3, &"INSERT INTO t1 (id, a) VALUES (4, 'd')".to_string());
+        commit(&i, &o, &w3);
+        no_response(&o, &vec![&s]);
+
+        match unsub(&s) {
+            Err(FileOpErr::NotSubscribed) => assert!(true),
+            _ => assert!(false)
+        }
+    }
+
+
+    // Assert: A changeset recorded in a write tx on one file can be applied in a write tx on another.
+    #[test]
+    fn test_runtime_changeset() {
+        let (i, o) = get_new_runtime_async();
+        let f1 = get_test_file();
+        let f2 = format!("{}-copy.sqlite3", f1);
+
+        for f in vec![&f1, &f2] {
+            let w = get_tx_id(get_single(&o, &writes(&i, f, 1)[0]));
+            q(&i, &o, &w, &"CREATE TABLE t1(id INTEGER PRIMARY KEY, a)".to_string());
+            q(&i, &o, &w, &"INSERT INTO t1 (id, a) VALUES (1, 'a')".to_string());
+            commit(&i, &o, &w);
+        }
+
+        let w1 = get_tx_id(get_single(&o, &writes(&i, &f1, 1)[0]));
+        let session = |op: TxOp| match get_single(&o, &i(Tx(op))) {
+            OutMsg::TxSession(r) => r,
+            _ => panic!("Expected TxSession")
+        };
+
+        match session(TxOp::Changeset(ArgsChangeset { tx_id: w1.clone(), patchset: false })) {
+            Err(TxOpErr::SessionError(SessionError::NotStarted)) => assert!(true),
+            _ => assert!(false)
+        }
+
+        session(TxOp::SessionStart(ArgsSessionStart { tx_id: w1.clone(), tables: None })).expect("Ok");
+        q(&i, &o, &w1, &"INSERT INTO t1 (id, a) VALUES (2, 'c')".to_string());
+        q(&i, &o, &w1, &"UPDATE t1 SET a = 'd' WHERE id = 1".to_string());
+        let cs = session(TxOp::Changeset(ArgsChangeset { tx_id: w1.clone(), patchset: false })).expect("Ok").changeset;
+        commit(&i, &o, &w1);
+
+        // Assert: Changing the row on `f2` first creates a conflict; `replace` uses the changeset values.
+        let w2 = get_tx_id(get_single(&o, &writes(&i, &f2, 1)[0]));
+        q(&i, &o, &w2, &"UPDATE t1 SET a = 'e' WHERE id = 1".to_string());
+        let res = session(TxOp::ApplyChangeset(ArgsApplyChangeset { tx_id: w2.clone(), changeset: cs, on_conflict: ConflictAction::Replace })).expect("Ok");
+        assert_eq!(res.conflicts.iter().map(|c| c.kind.clone()).collect::<Vec<_>>(), vec![ConflictType::Data]);
+
+        let rset = q(&i, &o, &w2, &"SELECT id, a FROM t1".to_string());
+        commit(&i, &o, &w2);
+
+        assert_eq!(rset.rows.data, vec![
+            vec![Val::I64(1), Val::String("d".to_string())],
+            vec![Val::I64(2), Val::String("c".to_string())],
+        ]);
+    }
+
+
+    // Assert: A read tx blocking the automatic checkpoint is named in a warning; `file/checkpoint` reports it too.
+    #[test]
+    fn test_runtime_checkpoint() {
+        let (i, o) = get_new_runtime_async();
+        let f = get_test_file();
+        let checkpoint = |mode: CheckpointMode| match get_single(&o, &i(File(Checkpoint(ArgsCheckpoint { file: f.clone(), mode })))) {
+            OutMsg::Checkpoint(Ok(status)) => status,
+            _ => panic!("Expected Checkpoint")
+        };
+
+        let w = get_tx_id(get_single(&o, &writes(&i, &f, 1)[0]));
+        q(&i, &o, &w, &"CREATE TABLE t1(a)".to_string());
+        commit(&i, &o, &w);
+
+        let r = get_tx_id(get_single(&o, &reads(&i, &f, 1)[0]));
+        q(&i, &o, &r, &"SELECT * FROM t1".to_string());
+        let s = i(File(SubscribeCheckpointWarnings(ArgsFile { file: f.clone(), flags: None })));
+        let s_changes = i(File(SubscribeChanges(ArgsFile { file: f.clone(), flags: None })));
+
+        // Assert: The default policy checkpoints once the WAL has 1000 frames.
+        let w = get_tx_id(get_single(&o, &writes(&i, &f, 1)[0]));
+        q(&i, &o, &w, &"INSERT INTO t1 (a) VALUES (zeroblob(5000000))".to_string());
+        commit(&i, &o, &w);
+
+        // Assert: Change subscribers do not get the warning.
+        match get_single(&o, &s_changes) {
+            OutMsg::TxChanges(_) => assert!(true),
+            _ => assert!(false)
+        }
+        no_response(&o, &vec![&s_changes]);
+
+        match get_single(&o, &s) {
+            OutMsg::CheckpointWarning(status) => {
+                assert!(status.checkpoint.wal_frames >= 1000 && !status.checkpoint.is_complete());
+                assert_eq!(status.open_read_tx_ids, vec![r.clone()]);
+            }
+            _ => assert!(false)
+        }
+
+        let status = checkpoint(CheckpointMode::Truncate);
+        assert!(status.checkpoint.is_busy);
+        assert_eq!(status.open_read_tx_ids, vec![r.clone()]);
+
+        commit(&i, &o, &r);
+        let status = checkpoint(CheckpointMode::Truncate);
+        assert_eq!((status.checkpoint.is_complete(), status.checkpoint.wal_frames, status.open_read_tx_ids.len()), (true, 0, 0));
+    }
+
+
+    // Assert: `file/deserialize` replaces another file with a `file/serialize` image, but not while it has txs open.
+    #[test]
+    fn test_runtime_serialize() {
+        let (i, o) = get_new_runtime_async();
+        let (f1, f2) = (get_test_file(), get_test_file());
+        let count = |f: &String| {
+            let r = get_tx_id(get_single(&o, &reads(&i, f, 1)[0]));
+            let rset = q(&i, &o, &r, &"SELECT count(*) FROM t1".to_string());
+            commit(&i, &o, &r);
+            rset.rows.data[0][0].clone()
+        };
+
+        let w = get_tx_id(get_single(&o, &writes(&i, &f1, 1)[0]));
+        q(&i, &o, &w, &"CREATE TABLE t1(a)".to_string());
+        q(&i, &o, &w, &"INSERT INTO t1 (a) VALUES (1), (2)".to_string());
+        commit(&i, &o, &w);
+
+        let data = match get_single(&o, &i(File(Serialize(ArgsFile { file: f1.clone(), flags: None })))) {
+            OutMsg::Serialize(Ok(img)) => img.data,
+            _ => panic!("Expected Serialize")
+        };
+
+        let deserialize = |data: &Vec<u8>| get_single(&o, &i(File(Deserialize(ArgsDeserialize { file: f2.clone(), data: data.clone() }))));
+
+        let r = get_tx_id(get_single(&o, &reads(&i, &f2, 1)[0]));
+        match deserialize(&data) {
+            OutMsg::Deserialize(Err(FileOpErr::TxsActive(ids))) => assert_eq!(ids, vec![r.clone()]),
+            _ => assert!(false)
+        }
+        commit(&i, &o, &r);
+
+        match deserialize(&data) {
+            OutMsg::Deserialize(Ok(FileOnly { file })) => assert_eq!(file, f2),
+            _ => assert!(false)
+        }
+        assert_eq!(count(&f2), Val::I64(2));
+    }
+
+
+    #[test]
+    fn test_runtime_explain() {
+        let (i, o) = get_new_runtime_async();
+        let f = get_test_file();
+        let explain = |tx_id: &TxId, q: &str| get_single(&o, &i(Tx(TxOp::Explain(ArgsTx { tx_id: tx_id.clone(), q: q.to_string(), timeout_ms: None }))));
+
+        let w = get_tx_id(get_single(&o, &writes(&i, &f, 1)[0]));
+        q(&i, &o, &w, &"CREATE TABLE t1(a, b)".to_string());
+        q(&i, &o, &w, &"CREATE INDEX t1_a ON t1(a)".to_string());
+
+        // Assert: Schema changes in the write tx are used; the query is not run.
+        match explain(&w, "DELETE FROM t1 WHERE b = 1") {
+            OutMsg::TxExplain(Ok(plan)) => assert!(plan.has_full_scan && plan.nodes[0].is_full_scan),
+            _ => assert!(false)
+        }
+        commit(&i, &o, &w);
+
+        let r = get_tx_id(get_single(&o, &reads(&i, &f, 1)[0]));
+        match explain(&r, "SELECT * FROM t1 WHERE a = 1") {
+            OutMsg::TxExplain(Ok(plan)) => assert!(!plan.has_full_scan),
+            _ => assert!(false)
+        }
+        assert_eq!(q(&i, &o, &r, &"SELECT count(*) FROM t1".to_string()).rows.data, vec![vec![Val::I64(0)]]);
+
+        match explain(&r, "SELECT * FROM missing") {
+            OutMsg::TxExplain(Err(TxOpErr::ReturnStatus(_))) => {}
+            _ => assert!(false)
+        }
+    }
+
+
+    // Assert: A write tx request waiting on another connections write lock starts soon after the lock is released.
+    #[test]
+    fn test_runtime_write_lock_wait() {
+        let (i, o) = get_new_runtime_async();
+        let f = get_test_file();
+
+        let w = get_tx_id(get_single(&o, &writes(&i, &f, 1)[0]));
+        q(&i, &o, &w, &"CREATE TABLE t1(a)".to_string());
+        commit(&i, &o, &w);
+
+        // Another connection in this process; polled, as the busy timeout only waits on other processes.
+        let other = DbHandle::new(f.clone()).unwrap();
+
+        // Assert: The second wait reuses the files waiter started by the first.
+        for _ in 0..2 {
+            other.run("BEGIN IMMEDIATE").unwrap();
+
+            let start = time::Instant::now();
+            let w_id = writes(&i, &f, 1).pop().unwrap();
+            thread::sleep(time::Duration::from_millis(150));
+            other.run("ROLLBACK").unwrap();
+
+            let w = get_tx_id(get_single(&o, &w_id));
+            let waited = start.elapsed();
+            commit(&i, &o, &w);
+
+            assert!(waited >= time::Duration::from_millis(150) && waited < time::Duration::from_millis(1500));
+        }
+    }
+
+
+    // Assert: A query past its `timeout_ms` returns `TxOpErr::Timeout`; a read tx stays usable, a rolled back write tx is ended.
+    #[test]
+    fn test_runtime_query_timeout() {
+        let (i, o) = get_new_runtime_async();
+        let f = get_test_file();
+        let forever = "WITH RECURSIVE c(x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM c) SELECT count(*) FROM c";
+        let with_timeout = |tx_id: &TxId, q: String| match get_single(&o, &i(Tx(TxOp::Q(ArgsTx { tx_id: tx_id.clone(), q, timeout_ms: Some(20) })))) {
+            OutMsg::Tx(r) => r,
+            _ => panic!("Expected Tx")
+        };
+
+        let w = get_tx_id(get_single(&o, &writes(&i, &f, 1)[0]));
+        q(&i, &o, &w, &"CREATE TABLE t1(a)".to_string());
+        commit(&i, &o, &w);
+
+        let r = get_tx_id(get_single(&o, &reads(&i, &f, 1)[0]));
+        match with_timeout(&r, forever.to_string()) {
+            Err(TxOpErr::Timeout(QueryTimeout { timeout_ms: 20, is_rolled_back: false })) => assert!(true),
+            _ => assert!(false)
+        }
+        assert_eq!(q(&i, &o, &r, &"SELECT count(*) FROM t1".to_string()).rows.data, vec![vec![Val::I64(0)]]);
+        commit(&i, &o, &r);
+
+        // Assert: An interrupted write rolls back the earlier writes, ends the tx, and releases the write queue slot.
+        let ws = writes(&i, &f, 2);
+        let w = get_tx_id(get_single(&o, &ws[0]));
+        q(&i, &o, &w, &"INSERT INTO t1 (a) VALUES (1)".to_string());
+        no_response(&o, &vec![&ws[1]]);
+        match with_timeout(&w, format!("INSERT INTO t1 (a) {}", forever)) {
+            Err(TxOpErr::Timeout(QueryTimeout { is_rolled_back: true, .. })) => assert!(true),
+            _ => assert!(false)
+        }
+        match with_timeout(&w, "INSERT INTO t1 (a) VALUES (3)".to_string()) {
+            Err(TxOpErr::InvalidTxId) => assert!(true),
+            _ => assert!(false)
+        }
+
+        let w2 = get_tx_id(get_single(&o, &ws[1]));
+        assert!(with_timeout(&w2, "INSERT INTO t1 (a) VALUES (2)".to_string()).is_ok());
+        commit(&i, &o, &w2);
+
+        assert_eq!(one_read(f, "SELECT a FROM t1").rows.data, vec![vec![Val::I64(2)]]);
+    }
+
+
+    // Assert: `file/undo` and `file/redo` revert and re-apply committed write txs on opted in tables, and notify subscribers.
+    #[test]
+    fn test_runtime_undo_redo() {
+        let (i, o) = get_new_runtime_async();
+        let f = get_test_file();
+        let args = || ArgsFile { file: f.clone(), flags: None };
+
+        let w1 = get_tx_id(get_single(&o, &writes(&i, &f, 1)[0]));
+        q(&i, &o, &w1, &"CREATE TABLE t1(id INTEGER PRIMARY KEY, a)".to_string());
+        get_single(&o, &i(Tx(TxOp::EnableUndo(ArgsEnableUndo { tx_id: w1.clone(), tables: vec!["t1".to_string()] }))));
+        commit(&i, &o, &w1);
+
+        let w2 = get_tx_id(get_single(&o, &writes(&i, &f, 1)[0]));
+        q(&i, &o, &w2, &"INSERT INTO t1 (id, a) VALUES (1, 'a')".to_string());
+        commit(&i, &o, &w2);
+
+        let s = i(File(SubscribeChanges(args())));
+        let count = || {
+            let w = get_tx_id(get_single(&o, &writes(&i, &f, 1)[0]));
+            let rset = q(&i, &o, &w, &"SELECT count(*) FROM t1".to_string());
+            commit(&i, &o, &w);
+            rset.rows.data[0][0].clone()
+        };
+
+        match get_single(&o, &i(File(Undo(args())))) {
+            OutMsg::Undo(Ok(step)) => assert_eq!((step.is_applied, step.undo_len, step.redo_len), (true, 0, 1)),
+            _ => assert!(false)
+        }
+
+        match get_single(&o, &s) {
+            OutMsg::TxChanges(c) => assert_eq!(c.changes[0].op, ChangeOp::Delete),
+            _ => assert!(false)
+        }
+        assert_eq!(count(), Val::I64(0));
+
+        match get_single(&o, &i(File(Redo(args())))) {
+            OutMsg::Undo(Ok(step)) => assert_eq!((step.is_applied, step.undo_len, step.redo_len), (true, 1, 0)),
+            _ => assert!(false)
+        }
+        get_single(&o, &s);
+        assert_eq!(count(), Val::I64(1));
+    }
+
+
+    // Assert: Functions registered on the runtime can be called from SQL in any tx.
+    #[test]
+    fn test_runtime_scalar_function() {
+        let (tx, rx) = channel();
+        let rt = Runtime::new(move |r| tx.send(r).expect("Ok"));
+
+        rt.register_scalar_function("rt_reverse", 1, true, Box::new(|args| {
+            match &args[0] {
+                Val::String(s) => Ok(Val::String(s.chars().rev().collect())),
+                _ => Err("Expected a string.".to_string())
+            }
+        }));
+
+        let get = |i: InMsg| -> OutMsg {
+            rt.input(InMsgWithId::new_gen_id(i)).expect("Ok");
+            rx.recv().expect("Ok").msg
+        };
+
+        let r = get_tx_id(get(File(GetReadTx(ArgsFile { file: get_test_file(), flags: None }))));
+
+        match get(get_tx_q(&r, "SELECT rt_reverse('abc')")) {
+            OutMsg::Tx(Ok(rset)) => assert_eq!(rset.rows.data[0][0], Val::String("cba".to_string())),
+            _ => assert!(false)
+        };
+
+        match get(get_tx_q(&r, "SELECT rt_reverse(1)")) {
+            OutMsg::Tx(Err(TxOpErr::ReadError(_))) => assert!(true),
+            _ => assert!(false)
+        };
+    }
+
+
+    // Assert: Statements run by txs are returned by `file/slow_queries` for their file only.
+    #[test]
+    fn test_runtime_slow_queries() {
+        let (tx, rx) = channel();
+        let rt = Runtime::new(move |r| tx.send(r).expect("Ok"));
+
+        rt.set_slow_query_log(Some(SlowQueryLog { threshold_ms: 0, max_entries: 10000, rset_timing: false }));
+
+        let get = |i: InMsg| -> OutMsg {
+            rt.input(InMsgWithId::new_gen_id(i)).expect("Ok");
+            rx.recv().expect("Ok").msg
+        };
+        let (f1, f2) = (get_test_file(), get_test_file());
+
+        let w = get_tx_id(get(File(GetWriteTx(ArgsFile { file: f1.clone(), flags: None }))));
+        get(get_tx_q(&w, "CREATE TABLE t1(a)"));
+        get(get_tx_q(&w, "INSERT INTO t1 (a) VALUES (1), (2), (3)"));
+        get(get_tx_q(&w, "SELECT * FROM t1 WHERE a > 1"));
+        get(get_tx_commit(&w));
+
+        match get(File(SlowQueries(ArgsFile { file: f1.clone(), flags: None }))) {
+            OutMsg::SlowQueries(Ok(s)) => {
+                assert_eq!(s.file, f1);
+                let sel = s.queries.iter().find(|x| x.sql == "SELECT * FROM t1 WHERE a > 1").expect("Some");
+                assert_eq!(sel.normalized_sql, Some("SELECT*FROM t1 WHERE a>?;".to_string()));
+                assert_eq!((sel.rows, sel.status.fullscan_steps), (2, 2));
+            }
+            _ => assert!(false)
+        }
+
+        match get(File(SlowQueries(ArgsFile { file: f2.clone(), flags: None }))) {
+            OutMsg::SlowQueries(Ok(s)) => assert_eq!(s.queries, vec![]),
+            _ => assert!(false)
+        }
+
+        rt.set_slow_query_log(None);
+    }
+
+
+    // Assert: Queries that hit a connection limit fail with `LimitExceeded`, including parameter binding errors.
+    #[test]
+    fn test_runtime_limits() {
+        let (tx, rx) = channel();
+        let rt = Runtime::new(move |r| tx.send(r).expect("Ok"));
+
+        let get = |i: InMsg| -> OutMsg {
+            rt.input(InMsgWithId::new_gen_id(i)).expect("Ok");
+            rx.recv().expect("Ok").msg
+        };
+
+        // Note: Only applies to connections opened after it is set.
+        rt.set_limits(Some(Limits { attached: Some(0), ..Default::default() }));
+        let w = get_tx_id(get(File(GetWriteTx(ArgsFile { file: get_test_file(), flags: None }))));
+        rt.set_limits(None);
+
+        match get(get_tx_q(&w, "ATTACH ':memory:' AS other")) {
+            OutMsg::Tx(Err(TxOpErr::LimitExceeded(le))) => assert_eq!(le.limit, Limit::Attached),
+            _ => assert!(false)
+        }
+
+        // Assert: Other errors are unchanged.
+        match get(get_tx_q(&w, "SELECT * FROM missing")) {
+            OutMsg::Tx(Err(TxOpErr::ReturnStatus(_))) => {}
+            _ => assert!(false)
+        }
+    }
+
+
+    // Assert: Queries denied by the files auth policy fail with `NotAuthorized`, including the denied action.
+    #[test]
+    fn test_runtime_auth_policy() {
+        let (tx, rx) = channel();
+        let rt = Runtime::new(move |r| tx.send(r).expect("Ok"));
+
+        let get = |i: InMsg| -> OutMsg {
+            rt.input(InMsgWithId::new_gen_id(i)).expect("Ok");
+            rx.recv().expect("Ok").msg
+        };
+
+        let f = get_test_file();
+        rt.set_auth_policy(&f, Some(AuthPolicy::sandbox())).expect("Ok");
+        let w = get_tx_id(get(File(GetWriteTx(ArgsFile { file: f.clone(), flags: None }))));
+
+        match get(get_tx_q(&w, "SELECT load_extension('x')")) {
+            OutMsg::Tx(Err(TxOpErr::NotAuthorized(na))) => {
+                assert_eq!(na.denied.expect("Some").action, AuthAction::Function { name: "load_extension".to_string() });
+            }
+            _ => assert!(false)
+        }
+
+        match get(get_tx_q(&w, "ATTACH ':memory:' AS other")) {
+            OutMsg::Tx(Err(TxOpErr::NotAuthorized(na))) => assert_eq!(na.return_status.primary.id, PrimaryRC::SQLITE_AUTH),
+            _ => assert!(false)
+        }
+
+        // Assert: The tx is still usable, and other errors are unchanged.
+        match get(get_tx_q(&w, "CREATE TABLE t1(a)")) {
+            OutMsg::Tx(Ok(_)) => {}
+            _ => assert!(false)
+        }
+        match get(get_tx_q(&w, "SELECT * FROM missing")) {
+            OutMsg::Tx(Err(TxOpErr::ReturnStatus(_))) => {}
+            _ => assert!(false)
+        }
+
+        assert!(rt.set_auth_policy(&"/missing-dir/x.sqlite".to_string(), None).is_err());
+        rt.set_auth_policy(&f, None).expect("Ok");
+    }
+
+
+    // Assert: Blobs can be written and read in chunks inside a tx.
+    #[test]
+    fn test_runtime_blob_chunks() {
+        let get = get_new_runtime();
+        let f = get_test_file();
+        let loc = || ArgsBlob { table: "t1".to_string(), column: "data".to_string(), rowid: 1 };
+
+        let w = get_tx_id(get(File(GetWriteTx(ArgsFile { file: f.clone(), flags: None }))));
+        get(get_tx_q(&w, "CREATE TABLE t1(id INTEGER PRIMARY KEY, data BLOB)"));
+        get(get_tx_q(&w, "INSERT INTO t1 (id, data) VALUES (1, zeroblob(6))"));
+
+        for (offset, data) in vec![(0, vec![1, 2, 3]), (3, vec![4, 5, 6])] {
+            match get(get_tx_blob_write(&w, loc(), offset, data)) {
+                OutMsg::TxBlob(Ok(BlobChunk { blob_len: 6, .. })) => assert!(true),
+                _ => assert!(false)
+            };
+        }
+
+        // Assert: Writes past the end of the blob fail.
+        match get(get_tx_blob_write(&w, loc(), 5, vec![1, 2])) {
+            OutMsg::TxBlob(Err(TxOpErr::ReturnStatus(_))) => assert!(true),
+            _ => assert!(false)
+        };
+
+        match get(get_tx_commit(&w)) {
+            OutMsg::Tx(Ok(_)) => assert!(true),
+            _ => assert!(false)
+        };
+
+        let r = get_tx_id(get(File(GetReadTx(ArgsFile { file: f.clone(), flags: None }))));
+        match get(get_tx_blob_read(&r, loc(), 2, 3)) {
+            OutMsg::TxBlob(Ok(chunk)) => assert_eq!(chunk, BlobChunk { blob_len: 6, offset: 2, data: vec![3, 4, 5] }),
+            _ => assert!(false)
+        };
+
+        match get(get_tx_blob_write(&r, loc(), 0, vec![1])) {
+            OutMsg::TxBlob(Err(TxOpErr::ReadError(ReadError::QueryIsWrite))) => assert!(true),
+            _ => assert!(false)
+        };
+
+        integrity(f);
+    }
+
+
+    // Assert: Backups wait for the active write tx, report progress, and hold the write queue until done.
+    #[test]
+    fn test_runtime_backup() {
+        let (i, o) = get_new_runtime_async();
+        let f = get_test_file();
+        let dst = format!("{}-backup.sqlite3", f);
+
+        let w1 = get_tx_id(get_single(&o, &writes(&i, &f, 1)[0]));
+        q(&i, &o, &w1, &"CREATE TABLE t1(id INTEGER PRIMARY KEY, data BLOB)".to_string());
+        q(&i, &o, &w1, &"WITH RECURSIVE c(x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM c WHERE x < 50) INSERT INTO t1 (data) SELECT zeroblob(4000) FROM c".to_string());
+
+        let b = i(File(Backup(ArgsBackup { file: f.clone(), dst: dst.clone(), pages_per_step: Some(10) })));
+        no_response(&o, &vec![&b]);
+
+        commit(&i, &o, &w1);
+        let w2 = writes(&i, &f, 1).pop().unwrap();
+
+        let mut steps = 0;
+        loop {
+            match get_single(&o, &b) {
+                OutMsg::Backup(Ok(p)) => {
+                    steps += 1;
+                    if p.is_done {
+                        assert_eq!(p.remaining, 0);
+                        break;
+                    }
+                }
+                _ => assert!(false)
+            }
+        }
+        assert!(steps > 1);
+
+        get_tx_id(get_single(&o, &w2));
+
+        let rows = DbHandle::new(dst.clone()).unwrap().run("SELECT count(*) FROM t1").unwrap().rows.data;
+        assert_eq!(rows[0][0], Val::I64(50));
+
+        integrity(dst);
+    }
+
+
+    // Assert: Every statement in a script runs in a write tx; read txs only run scripts that are read only.
+    #[test]
+    fn test_runtime_exec_script() {
+        let get = get_new_runtime();
+        let f = get_test_file();
+
+        let w = get_tx_id(get(File(GetWriteTx(ArgsFile { file: f.clone(), flags: None }))));
+        let script = "CREATE TABLE t1(a PRIMARY KEY); INSERT INTO t1 VALUES (1); INSERT INTO t1 VALUES (2);";
+
+        match get(get_tx_exec_script(&w, script)) {
+            OutMsg::TxScript(Ok(rsets)) => assert_eq!(rsets.len(), 3),
+            _ => assert!(false)
+        };
+
+        match get(get_tx_exec_script(&w, "INSERT INTO t1 VALUES (3); INSERT INTO t1 VALUES (1);")) {
+            OutMsg::TxScript(Err(TxOpErr::ScriptError(e))) => assert_eq!((e.index, e.offset), (1, 27)),
+            _ => assert!(false)
+        };
+
+        get(get_tx_commit(&w));
+
+        let r = get_tx_id(get(File(GetReadTx(ArgsFile { file: f.clone(), flags: None }))));
+        match get(get_tx_exec_script(&r, "SELECT 1; SELECT count(*) FROM t1;")) {
+            OutMsg::TxScript(Ok(rsets)) => assert_eq!(rsets[1].rows.data[0][0], Val::I64(3)),
+            _ => assert!(false)
+        };
+
+        match get(get_tx_exec_script(&r, "SELECT 1; INSERT INTO t1 VALUES (4);")) {
+            OutMsg::TxScript(Err(TxOpErr::ReadError(ReadError::QueryIsWrite))) => assert!(true),
+            _ => assert!(false)
+        };
+
+        // Assert: Statements before the failed statement are kept.
+        let rset = one_read(f.clone(), "SELECT count(*) FROM t1");
+        assert_eq!(rset.rows.data[0][0], Val::I64(3));
+
+        integrity(f);
+    }
+
+
     // Assert: Transactions work as expected when other processes on the OS are writing to the same file.
     // Assert: `SQLITE_BUSY` is handled correctly and predictably.
     // - Uses many threads to simulate multiple processes on an OS.
@@ -224,7 +893,8 @@ mod tests {
         let req_a = File(
             GetReadTx(
                 ArgsFile {
-                    file: "/a.sqlite".to_string()
+                    file: "/a.sqlite".to_string(),
+                    flags: None
                 }
             )
         );
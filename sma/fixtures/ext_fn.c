@@ -0,0 +1,16 @@
+// A minimal loadable extension for `test_extensions_load`; adds `ext_fn()`, which returns 42.
+// @see https://www.sqlite.org/loadext.html
+#include "sqlite3ext.h"
+SQLITE_EXTENSION_INIT1
+
+static void ext_fn(sqlite3_context *ctx, int argc, sqlite3_value **argv) {
+    (void)argc;
+    (void)argv;
+    sqlite3_result_int(ctx, 42);
+}
+
+int sqlite3_extension_init(sqlite3 *db, char **err_msg, const sqlite3_api_routines *api) {
+    (void)err_msg;
+    SQLITE_EXTENSION_INIT2(api);
+    return sqlite3_create_function_v2(db, "ext_fn", 0, SQLITE_UTF8 | SQLITE_DETERMINISTIC, 0, ext_fn, 0, 0, 0);
+}
@@ -1,5 +1,27 @@
+use std::fs;
+use std::path::Path;
+
 use uuid::Uuid;
 
 pub fn get_unique_id() -> String {
     Uuid::new_v4().to_hyphenated().to_string()
-}
\ No newline at end of file
+}
+
+// The same path for every way of naming a database file (symlinks, `..`, relative paths); used as the key for per file state.
+// - The directory must exist, the file does not have to (SQLite creates files, but not directories).
+pub fn get_canonical_file(f: &str) -> Option<String> {
+    let full = Path::new(f);
+
+    if full.is_file() {
+        return fs::canonicalize(full).ok().map(|p| p.to_string_lossy().into_owned());
+    }
+
+    if let (Some(dir), Some(file)) = (full.parent(), full.file_name()) {
+        if !full.is_dir() && dir.is_dir() {
+            let normal_dir = fs::canonicalize(dir).ok()?;
+            return Some(normal_dir.join(file).to_string_lossy().into_owned());
+        }
+    }
+
+    None
+}
@@ -15,6 +15,11 @@
 use std::time::{Duration, Instant};
 use serde::{Deserialize, Serialize};
 use std::thread;
+use std::sync::{RwLock, Mutex};
+use std::collections::VecDeque;
+use std::io;
+use std::rc::Rc;
+use std::cell::{Cell, RefCell};
 
 extern crate chrono;
 
@@ -22,8 +27,13 @@ use chrono::{DateTime, Utc};
 
 
 pub mod fns;
+pub mod undo;
 
-use fns::get_unique_id;
+use fns::{
+    get_unique_id,
+    get_canonical_file,
+};
+use undo::UndoStep;
 
 
 #[cfg(test)]
@@ -43,6 +53,38 @@ use sqlite3ffi::{
         IndexVal,
         ErrorBind,
     },
+    script::ScriptError,
+    open::OpenFlags,
+    blob::BlobHandle,
+    backup::{
+        Backup,
+        BackupProgress,
+    },
+    function::ScalarFunction,
+    aggregate::AggregateFunction,
+    collation::create_builtin_collations,
+    hooks::RowChange,
+    session::{
+        Session,
+        ConflictFn,
+    },
+    checkpoint::{
+        Checkpoint,
+        CheckpointMode,
+    },
+    explain::QueryPlan,
+    db_config::DbConfig,
+    extension::allow_extensions,
+    trace::{
+        TraceEvent,
+        StmtStatus,
+    },
+    limits::Limits,
+    auth::{
+        AuthAction,
+        AuthContext,
+        Authorization,
+    },
     err::ReturnStatus,
     errmap::{
         PrimaryRow,
@@ -56,12 +98,34 @@ use std::hint::unreachable_unchecked;
 pub struct DbFile {
     id: String,
     db_handle: DbHandle,
+
+    // The canonical path SQLite opened; the same when the file is opened by a URI or another path form.
+    file_abs: String,
+
+    // Frames in the WAL after this connections last commit (see `sqlite3ffi::hooks::WalHookFn`).
+    wal_frames: Rc<Cell<i32>>,
+
+    // The policy when the file was opened; `None` leaves SQLites auto checkpoint on.
+    checkpoint_policy: Option<CheckpointPolicy>,
+
+    // Set by `CheckpointPolicy` after a commit; read with `take_auto_checkpoint`.
+    last_checkpoint: Option<Checkpoint>,
 }
 
 #[derive(Debug)]
 pub struct WriteTx {
     pub id: String,
+
+    // Declared before `db_file` so they are dropped first; a session must be deleted before its connection closes.
+    // - `'static` as they are created with `Session::new_unbound` on `db_file`.
+    session: RefCell<Option<Session<'static>>>,
+
+    // Records changes to the tables opted in to undo; `None` when there are none.
+    undo_session: RefCell<Option<Session<'static>>>,
     db_file: DbFile,
+
+    // Rows changed so far, collected by the update hook.
+    changes: Rc<RefCell<Vec<RowChange>>>,
 }
 
 #[derive(Debug)]
@@ -95,6 +159,251 @@ fn ms(i: u64) -> Duration {
     Duration::from_millis(i)
 }
 
+// Long enough for other write txs to commit; short enough that a lock held by another process does not stall the files write queue.
+const BACKUP_MAX_BUSY: Duration = Duration::from_secs(10);
+
+// Installed on every connection opened by `DbFile::new`.
+// - Process wide (like `sqlite3_auto_extension`); connections are opened per transaction, so the functions are available to every tx that starts after registering.
+static SCALAR_FUNCTIONS: RwLock<Vec<ScalarFunction>> = RwLock::new(Vec::new());
+
+// Replaces any function with the same name and number of args (SQLite function names are case insensitive).
+pub fn register_scalar_function(f: ScalarFunction) {
+    let mut fns = SCALAR_FUNCTIONS.write().unwrap();
+    fns.retain(|x| !(x.name.eq_ignore_ascii_case(&f.name) && x.n_args == f.n_args));
+    fns.push(f);
+}
+
+static AGGREGATE_FUNCTIONS: RwLock<Vec<AggregateFunction>> = RwLock::new(Vec::new());
+
+// Aggregate and window functions; installed after scalar functions.
+pub fn register_aggregate_function(f: AggregateFunction) {
+    let mut fns = AGGREGATE_FUNCTIONS.write().unwrap();
+    fns.retain(|x| !(x.name.eq_ignore_ascii_case(&f.name) && x.n_args == f.n_args));
+    fns.push(f);
+}
+
+// When to checkpoint after a write tx commits.
+// - Replaces SQLites auto checkpoint, as `DbFile` sets a WAL hook to track the WAL size.
+//      - Without a policy no hook is set, so SQLites auto checkpoint runs instead.
+// - The default is the same as SQLites: `Passive` once the WAL has 1000 frames.
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Serialize, Deserialize)]
+#[derive(Clone)]
+pub struct CheckpointPolicy {
+    pub wal_frames: i32,
+    pub mode: CheckpointMode,
+}
+
+
+// A native extension loaded into every connection, so its functions can be used in any tx.
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Serialize, Deserialize)]
+#[derive(Clone)]
+pub struct Extension {
+    pub path: String,
+
+    // `None` = SQLites default (`sqlite3_extension_init` or one derived from the file name).
+    #[serde(default)]
+    pub entry_point: Option<String>,
+}
+
+
+// Settings for the connections opened by `DbFile::new_with_config`.
+// - Owned by the caller (E.g. one per `Runtime`), so runtimes and tests in the same process do not change each others connections.
+#[derive(Debug)]
+#[derive(Clone)]
+pub struct DbFileConfig {
+    // E.g. `Limits::untrusted()` when SQL comes from the client; `None` = SQLites defaults.
+    pub limits: Option<Limits>,
+
+    // `None` = SQLites auto checkpoint (`Passive` once the WAL has 1000 frames, not reported by `take_auto_checkpoint`).
+    pub checkpoint_policy: Option<CheckpointPolicy>,
+
+    // `None` = not logged.
+    pub slow_query_log: Option<SlowQueryLog>,
+
+    // `None` = `DbConfig::secure()`.
+    pub db_config: Option<DbConfig>,
+
+    // Loaded into every connection; set with `set_extensions`.
+    extensions: Vec<Extension>,
+}
+
+impl Default for DbFileConfig {
+    fn default() -> DbFileConfig {
+        DbFileConfig {
+            limits: None,
+            checkpoint_policy: Some(CheckpointPolicy { wal_frames: 1000, mode: CheckpointMode::Passive }),
+            slow_query_log: None,
+            db_config: None,
+            extensions: vec![],
+        }
+    }
+}
+
+impl DbFileConfig {
+    // The paths are added to the process wide allowlist (see `sqlite3ffi::extension`); no other extension can be loaded.
+    // - Fails without changing either if a path does not exist.
+    pub fn set_extensions(&mut self, e: Vec<Extension>) -> Result<(), io::Error> {
+        let paths: Vec<String> = e.iter().map(|x| x.path.clone()).collect();
+        allow_extensions(&paths)?;
+        self.extensions = e;
+        Ok(())
+    }
+
+    pub fn get_extensions(&self) -> &Vec<Extension> {
+        &self.extensions
+    }
+}
+
+
+// Declarative rules for what SQL may do on a file, checked as each statement is prepared (see `sqlite3ffi::auth`).
+// - Names are case insensitive.
+// - Denied statements fail to prepare (see `sqlite3ffi::auth::is_not_authorized`); the denied action is returned by `take_auth_denied` on the tx.
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Serialize, Deserialize)]
+#[derive(Clone)]
+#[derive(Default)]
+#[serde(default)]
+pub struct AuthPolicy {
+    // `ATTACH` and `DETACH`.
+    pub deny_attach: bool,
+
+    // E.g. `writable_schema`; denied when read or set.
+    pub deny_pragmas: Vec<String>,
+
+    // E.g. `load_extension`.
+    pub deny_functions: Vec<String>,
+
+    // No `CREATE`, `DROP` or `ALTER` of these tables, or of indexes and triggers on them.
+    pub deny_ddl_tables: Vec<String>,
+}
+
+impl AuthPolicy {
+    // For SQL from an untrusted source; use with `Limits::untrusted()`.
+    pub fn sandbox() -> AuthPolicy {
+        AuthPolicy {
+            deny_attach: true,
+            deny_pragmas: vec!["writable_schema".to_string()],
+            deny_functions: vec!["load_extension".to_string()],
+            deny_ddl_tables: vec![],
+        }
+    }
+
+    pub fn check(&self, c: &AuthContext) -> Authorization {
+        let has = |names: &Vec<String>, name: &str| names.iter().any(|n| n.eq_ignore_ascii_case(name));
+
+        let is_denied = match &c.action {
+            AuthAction::Attach { .. } | AuthAction::Detach { .. } => self.deny_attach,
+            AuthAction::Pragma { name, .. } => has(&self.deny_pragmas, name),
+            AuthAction::Function { name } => has(&self.deny_functions, name),
+
+            AuthAction::CreateTable { table } |
+            AuthAction::CreateTempTable { table } |
+            AuthAction::DropTable { table } |
+            AuthAction::DropTempTable { table } |
+            AuthAction::AlterTable { table, .. } |
+            AuthAction::CreateIndex { table, .. } |
+            AuthAction::CreateTempIndex { table, .. } |
+            AuthAction::DropIndex { table, .. } |
+            AuthAction::DropTempIndex { table, .. } |
+            AuthAction::CreateTrigger { table, .. } |
+            AuthAction::CreateTempTrigger { table, .. } |
+            AuthAction::DropTrigger { table, .. } |
+            AuthAction::DropTempTrigger { table, .. } => has(&self.deny_ddl_tables, table),
+
+            _ => false
+        };
+
+        if is_denied { Authorization::Deny } else { Authorization::Allow }
+    }
+}
+
+static AUTH_POLICIES: RwLock<Vec<(String, AuthPolicy)>> = RwLock::new(Vec::new());
+
+// Applies to connections opened after this call. `None` removes the policy.
+// - Keyed on the canonical path (see `fns::get_canonical_file`), so it applies however the file is named when opened (E.g. a `file:` URI, `..`, a symlink).
+pub fn set_auth_policy(file: &str, p: Option<AuthPolicy>) {
+    let file = get_canonical_file(file).unwrap_or_else(|| file.to_string());
+
+    let mut ps = AUTH_POLICIES.write().unwrap();
+    ps.retain(|(f, _)| *f != file);
+
+    if let Some(p) = p {
+        ps.push((file, p));
+    }
+}
+
+// `file_abs` = `DbFile::get_file_abs`.
+fn get_auth_policy(file_abs: &str) -> Option<AuthPolicy> {
+    AUTH_POLICIES.read().unwrap().iter().find(|(f, _)| f == file_abs).map(|(_, p)| p.clone())
+}
+
+
+// Statement runs that take at least `threshold_ms` are kept in a process wide log (see `get_slow_queries`).
+// - Set per `DbFileConfig`; each runtime request opens its own `DbFile`, so it applies to txs started after it is set.
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Serialize, Deserialize)]
+#[derive(Clone)]
+pub struct SlowQueryLog {
+    // `0` logs every statement.
+    pub threshold_ms: u64,
+
+    // The oldest entries are dropped after this.
+    pub max_entries: usize,
+
+    // Also set `RSet.timing` for every query.
+    pub rset_timing: bool,
+}
+
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Serialize, Deserialize)]
+#[derive(Clone)]
+pub struct SlowQuery {
+    pub file: String,
+
+    // When the statement finished.
+    pub at: DateTime<Utc>,
+
+    // Group entries by `normalized_sql`; literals are replaced with `?`.
+    pub sql: String,
+    pub normalized_sql: Option<String>,
+
+    // Millisecond resolution (see `sqlite3ffi::trace::Profile`).
+    pub duration_ms: u64,
+
+    // Rows returned.
+    pub rows: u64,
+
+    // Full scan steps (~rows scanned without an index), VM steps, sorts and automatic indexes.
+    pub status: StmtStatus,
+}
+
+// Entries are kept after the log is turned off; `max_entries` is applied by each connection when it logs.
+static SLOW_QUERIES: Mutex<VecDeque<SlowQuery>> = Mutex::new(VecDeque::new());
+
+// Oldest first; `file` = `None` for every file.
+pub fn get_slow_queries(file: Option<&str>) -> Vec<SlowQuery> {
+    SLOW_QUERIES.lock().unwrap().iter()
+        .filter(|q| file.map_or(true, |f| q.file == f))
+        .cloned()
+        .collect()
+}
+
+fn log_slow_query(l: &SlowQueryLog, q: SlowQuery) {
+    let mut qs = SLOW_QUERIES.lock().unwrap();
+    qs.push_back(q);
+
+    while qs.len() > l.max_entries {
+        qs.pop_front();
+    }
+}
+
 impl JournalMode {
     pub fn new(mode: &str) -> JournalMode {
         match mode {
@@ -130,7 +439,17 @@ impl JournalMode {
 
 impl<'a> DbFile {
     pub fn new(file: String) -> Result<DbFile, ReturnStatus> {
-        let r = DbHandle::new(file);
+        DbFile::new_with_flags(file, &OpenFlags::default())
+    }
+
+    // E.g. `OpenFlags::read_only()` for a connection that can never write, or `OpenMode::ReadWrite` to fail when the file does not exist.
+    pub fn new_with_flags(file: String, flags: &OpenFlags) -> Result<DbFile, ReturnStatus> {
+        DbFile::new_with_config(file, flags, &DbFileConfig::default())
+    }
+
+    // `new_with_flags` uses `DbFileConfig::default()`.
+    pub fn new_with_config(file: String, flags: &OpenFlags, config: &DbFileConfig) -> Result<DbFile, ReturnStatus> {
+        let r = DbHandle::new_with_flags(file, flags);
 
 
         let db_handle = match r {
@@ -142,21 +461,89 @@ impl<'a> DbFile {
         };
 
 
-        DbFile::set_connection_pragmas(&db_handle)?;
+        DbFile::set_connection_pragmas(&db_handle, &config.db_config)?;
+
+        let file_abs = db_handle.get_db_filename(None)
+            .and_then(|f| get_canonical_file(&f))
+            .unwrap_or_else(|| db_handle.file.clone());
+
+        if let Some(l) = config.limits.as_ref() {
+            db_handle.set_limits(l);
+        }
+
+        // After the connection pragmas, so a policy cannot stop the file being opened in WAL mode.
+        if let Some(p) = get_auth_policy(&file_abs) {
+            db_handle.set_authorizer(Some(Box::new(move |c| p.check(c))));
+        }
+
+        // `NATSORT` and `CASEFOLD`; available on every connection so indexes using them work for every tx.
+        create_builtin_collations(&db_handle)?;
+
+        for e in config.extensions.iter() {
+            if let Err(rs) = db_handle.load_extension(&e.path, e.entry_point.as_deref()) {
+                dbg!(("Error when loading extension", &e.path, &rs));
+                return Err(rs);
+            }
+        }
+
+        for f in SCALAR_FUNCTIONS.read().unwrap().iter() {
+            f.install(&db_handle)?;
+        }
+
+        for f in AGGREGATE_FUNCTIONS.read().unwrap().iter() {
+            f.install(&db_handle)?;
+        }
+
+        // Setting a WAL hook turns off SQLites auto checkpoint.
+        let checkpoint_policy = config.checkpoint_policy.clone();
+        let wal_frames = Rc::new(Cell::new(0));
+        if checkpoint_policy.is_some() {
+            let w = wal_frames.clone();
+            db_handle.set_wal_hook(Some(Box::new(move |_, n| w.set(n))));
+        }
+
+        if let Some(l) = config.slow_query_log.clone() {
+            DbFile::set_slow_query_trace(&db_handle, &file_abs, l);
+        }
 
         Ok(
             DbFile {
                 id: get_unique_id(),
                 db_handle,
+                file_abs,
+                wal_frames,
+                checkpoint_policy,
+                last_checkpoint: None,
             }
         )
     }
 
+    fn set_slow_query_trace(db_handle: &DbHandle, file_abs: &str, l: SlowQueryLog) {
+        db_handle.set_rset_timing(l.rset_timing);
+
+        let file = file_abs.to_string();
+        db_handle.set_trace(Some(Box::new(move |e| {
+            if let TraceEvent::Profile(p) = e {
+                if p.duration_ms >= l.threshold_ms {
+                    log_slow_query(&l, SlowQuery {
+                        file: file.clone(),
+                        at: Utc::now(),
+                        sql: p.sql.clone(),
+                        normalized_sql: p.normalized_sql.clone(),
+                        duration_ms: p.duration_ms,
+                        rows: p.rows,
+                        status: p.status.clone(),
+                    });
+                }
+            }
+        })));
+    }
+
     // @todo/low Are these consistent across db handles (once set they are persisted in the db file)?
     // @todo/low Is it better to set these options via a SQLite compile flag?
     // @todo/low Block changing concurrency PRAGMAs so there is only one state machine for the above layers?
     // Allow customization via options?
-    fn set_connection_pragmas(db_handle: &DbHandle) -> Result<(), ReturnStatus> {
+    fn set_connection_pragmas(db_handle: &DbHandle, db_config: &Option<DbConfig>) -> Result<(), ReturnStatus> {
         DbFile::set_journal_mode_wal(db_handle)?;
 
         // Note: unlike `PRAGMA journal_mode=WAL`, this does not need a write lock, and only applies per connection.
@@ -173,6 +560,14 @@ impl<'a> DbFile {
             _ => {}
         }
 
+        // Intended: a flag that cannot be set fails the open instead of falling back to a connection without it.
+        // - The defaults are security settings (E.g. `defensive`); a tx that silently runs without them is worse than no tx.
+        let c = db_config.clone().unwrap_or_else(DbConfig::secure);
+        if let Err(e) = db_handle.set_db_config(&c) {
+            dbg!(("Error when setting `DbConfig`", &e));
+            return Err(e);
+        }
+
         Ok(())
     }
 
@@ -228,6 +623,10 @@ impl<'a> DbFile {
                             thread::sleep(t);
                             continue;
                         }
+                        Err(e @ ReturnStatus { primary: PrimaryRow { id: PrimaryRC::SQLITE_READONLY, .. }, .. }) => {
+                            // A read only connection cannot convert a file to WAL mode; it must be opened with write access once first.
+                            return Err(e);
+                        }
                         Err(e) => {
                             dbg!(&e);
                             unreachable!("Unexpected error when *writing* `PRAGMA journal_mode=WAL`. SQLite return code = {:?}", e.primary.id);
@@ -281,17 +680,107 @@ impl<'a> DbFile {
             return Err((self, e));
         }
 
+        // Safety: stored in `WriteTx.undo_session`, which is dropped before `WriteTx.db_file`.
+        let undo_session = match unsafe { undo::start_session(&self.db_handle) } {
+            Ok(s) => s,
+            Err(e) => {
+                let _ = self.db_handle.run("ROLLBACK");
+                return Err((self, e));
+            }
+        };
+
+        let changes = Rc::new(RefCell::new(vec![]));
+        let (c1, c2) = (changes.clone(), changes.clone());
+        self.db_handle.set_update_hook(Some(Box::new(move |rc| {
+            if !undo::is_internal_table(&rc.table) {
+                c1.borrow_mut().push(rc.clone());
+            }
+        })));
+
+        // E.g. `SQLITE_FULL` can roll back the whole tx; none of the changes will be committed.
+        self.db_handle.set_rollback_hook(Some(Box::new(move || c2.borrow_mut().clear())));
+
         Ok(
             WriteTx {
                 id: get_unique_id(),
+                session: RefCell::new(None),
+                undo_session: RefCell::new(undo_session),
                 db_file: self,
+                changes,
             }
         )
     }
 
-    // Assumption: `DbFile::new` is always called with the normalized file path.
+    // Blocks until no other connection holds the write lock (`SQLITE_BUSY` after `timeout`), then releases it again.
+    // - For waiting off the event loop thread; the lock is not kept, so `get_write_tx` can still fail if another process takes it first.
+    pub fn wait_for_write_lock(&self, timeout: Duration) -> Result<(), ReturnStatus> {
+        let h = &self.db_handle;
+
+        h.set_busy_timeout(timeout)?;
+        let r = h.run("BEGIN IMMEDIATE").and_then(|_| h.run("ROLLBACK"));
+        h.set_busy_timeout(ms(0))?;
+
+        r.map(|_| ())
+    }
+
+    // Copies the WAL back into the database file; for `Full` and stronger modes see `CheckpointMode`.
+    // - Readers of older snapshots (in this or other processes) stop the WAL from being fully checkpointed; `Checkpoint::is_complete` is then `false`.
+    // Note: Does not wait for locks (no busy timeout), so `Full` and stronger return `is_busy` at once while another connection writes or reads.
+    pub fn checkpoint(&self, mode: CheckpointMode) -> Result<Checkpoint, ReturnStatus> {
+        self.db_handle.checkpoint(None, mode)
+    }
+
+    // The checkpoint run by `CheckpointPolicy` after the last commit, if any.
+    pub fn take_auto_checkpoint(&mut self) -> Option<Checkpoint> {
+        self.last_checkpoint.take()
+    }
+
+    fn auto_checkpoint(&mut self) {
+        if let Some(p) = self.checkpoint_policy.clone() {
+            if self.wal_frames.get() >= p.wal_frames {
+                self.last_checkpoint = self.checkpoint(p.mode).ok();
+            }
+        }
+    }
+
+    // The canonical path of the file, also when it was opened by a URI (see `fns::get_canonical_file`).
     pub fn get_file_abs(&self) -> String {
-        self.db_handle.file.clone()
+        self.file_abs.clone()
+    }
+
+    // The effective config of the connection, for diagnostics.
+    pub fn get_db_config(&self) -> Result<DbConfig, ReturnStatus> {
+        self.db_handle.get_db_config()
+    }
+
+    // Copies this file to `dst_file` (created if it does not exist, replaced if it does).
+    // - Other connections can read and write the file during the backup, but writes from them restart it.
+    // - Fails with `SQLITE_BUSY` if another connection blocks every step for `BACKUP_MAX_BUSY`.
+    pub fn backup_to<F>(&self, dst_file: String, pages_per_step: i32, progress_fn: F) -> Result<(), ReturnStatus>
+        where F: FnMut(&BackupProgress) {
+        let dst = DbHandle::new(dst_file)?;
+        let b = Backup::new(&self.db_handle, &dst)?;
+
+        b.run(pages_per_step, ms(16), BACKUP_MAX_BUSY, progress_fn)
+    }
+
+    // The committed database as bytes (the same as a checkpointed copy of the file).
+    pub fn serialize(&self) -> Result<Vec<u8>, ReturnStatus> {
+        self.db_handle.serialize(None)
+    }
+
+    // Replaces the contents of this file with a `serialize` image, in a single write tx.
+    // - The image is opened in memory, then copied with a backup (which keeps this file's journal mode).
+    // - Fails with `SQLITE_BUSY` when another connection has the write lock, and with `SQLITE_READONLY` when this file is in WAL mode and the image has a different page size.
+    pub fn deserialize(&self, data: &[u8]) -> Result<(), ReturnStatus> {
+        let src = DbHandle::new(":memory:".to_string())?;
+        src.deserialize(None, data)?;
+
+        // Fail on an invalid image before the file is written to.
+        src.run("SELECT count(*) FROM sqlite_master")?;
+
+        let b = Backup::new(&src, &self.db_handle)?;
+        b.step(-1).map(|_| ())
     }
 
     // @todo/low Single queries,  make sure these route to correct read/write queues.
@@ -315,6 +804,36 @@ impl<'a> ReadTx {
         run_params_read_only(h, q, p)
     }
 
+    // Runs every statement in `q` when all of them are read only; otherwise nothing runs.
+    pub fn exec_script(&self, q: &str) -> Result<Vec<RSet>, ReadScriptError> {
+        let h = &self.db_file.db_handle;
+
+        if !h.is_read_only_script(q)? {
+            return Err(ReadScriptError::QueryIsWrite);
+        }
+
+        Ok(h.exec_script(q)?)
+    }
+
+    // Read only; reads see the same snapshot as `q`.
+    pub fn open_blob(&self, table: &str, column: &str, rowid: i64) -> Result<BlobHandle, ReturnStatus> {
+        self.db_file.db_handle.open_blob(table, column, rowid, false)
+    }
+
+    pub fn explain_query_plan(&self, q: &str) -> Result<QueryPlan, ReturnStatus> {
+        self.db_file.db_handle.explain_query_plan(q)
+    }
+
+    // The action denied by the files `AuthPolicy` when the last query failed to prepare.
+    pub fn take_auth_denied(&self) -> Option<AuthContext> {
+        self.db_file.db_handle.take_auth_denied()
+    }
+
+    // Interrupts any query `f` is still running after `timeout`; returns `(f(self), is_timeout)`.
+    pub fn with_timeout<T, F>(&self, timeout: Duration, f: F) -> (T, bool) where F: FnOnce(&Self) -> T {
+        self.db_file.db_handle.with_timeout(timeout, || f(self))
+    }
+
     pub fn commit(self) -> (DbFile, Result<RSet, ReturnStatus>) {
         // @todo/medium In what ways can this fail? Should a fail prevent transfer of ownership and allow a re-try?
         let r = self.db_file.db_handle.run("COMMIT");
@@ -349,6 +868,14 @@ pub enum ReadBindRunError {
     BindRunError(BindRunError),
 }
 
+#[derive(Debug)]
+#[derive(Serialize, Deserialize)]
+#[derive(Clone)]
+pub enum ReadScriptError {
+    QueryIsWrite,
+    ScriptError(ScriptError),
+}
+
 
 impl From<ReturnStatus> for ReadError {
     fn from(rs: ReturnStatus) -> Self {
@@ -377,6 +904,12 @@ impl From<ErrorBind> for ReadBindRunError {
     }
 }
 
+impl From<ScriptError> for ReadScriptError {
+    fn from(se: ScriptError) -> Self {
+        ReadScriptError::ScriptError(se)
+    }
+}
+
 
 #[derive(Debug)]
 #[derive(Serialize, Deserialize)]
@@ -396,6 +929,22 @@ pub enum WriteBindRunError {
 }
 
 
+#[derive(Debug)]
+#[derive(Serialize, Deserialize)]
+#[derive(Clone)]
+pub enum SessionError {
+    // `get_changeset` called before `start_session`.
+    NotStarted,
+    ReturnStatus(ReturnStatus),
+}
+
+
+impl From<ReturnStatus> for SessionError {
+    fn from(rs: ReturnStatus) -> Self {
+        SessionError::ReturnStatus(rs)
+    }
+}
+
 impl From<ReturnStatus> for WriteError {
     fn from(rs: ReturnStatus) -> Self {
         WriteError::ReturnStatus(rs)
@@ -443,38 +992,178 @@ impl<'a> WriteTx {
     pub fn write(&self, q: &str) -> Result<RSet, WriteError> {
         // @todo/low confirm only write
         let h = &self.db_file.db_handle;
-        run_write_only(h, q)
+        self.drop_changes_on_err(|| run_write_only(h, q))
     }
 
     pub fn write_params(&self, q: &str, p: &Params) -> Result<RSet, WriteBindRunError> {
         // @todo/low confirm only write
         let h = &self.db_file.db_handle;
-        run_params_write_only(h, q, p)
+        self.drop_changes_on_err(|| run_params_write_only(h, q, p))
     }
 
     pub fn q(&self, q: &str) -> Result<RSet, ReturnStatus> {
-        self.db_file.db_handle.run(&q)
+        self.drop_changes_on_err(|| self.db_file.db_handle.run(&q))
     }
 
     pub fn q_params(&self, q: &str, p: &Params) -> Result<RSet, BindRunError> {
-        run_params(&self.db_file.db_handle, &q, &p)
+        self.drop_changes_on_err(|| run_params(&self.db_file.db_handle, &q, &p))
+    }
+
+    // Runs every statement in `q` (E.g. a schema setup script), one `RSet` per statement.
+    // - Rows changed by the statements before a failed one are kept (they are still part of the tx).
+    pub fn exec_script(&self, q: &str) -> Result<Vec<RSet>, ScriptError> {
+        let mark = Cell::new(0);
+        let r = self.db_file.db_handle.exec_script_each(&q, &mut |_| mark.set(self.changes.borrow().len()));
+
+        if r.is_err() {
+            self.changes.borrow_mut().truncate(mark.get());
+        }
+        r
+    }
+
+    // A statement that fails is undone by SQLite, so the rows the update hook collected for it are dropped.
+    // Note: `OR FAIL` keeps the rows changed before the error; they are not reported either.
+    // @todo/low Rows from `ROLLBACK TO` a savepoint are still reported.
+    fn drop_changes_on_err<T, E, F>(&self, f: F) -> Result<T, E> where F: FnOnce() -> Result<T, E> {
+        let mark = self.changes.borrow().len();
+        let r = f();
+
+        if r.is_err() {
+            self.changes.borrow_mut().truncate(mark);
+        }
+        r
+    }
+
+    pub fn open_blob(&self, table: &str, column: &str, rowid: i64, is_write: bool) -> Result<BlobHandle, ReturnStatus> {
+        self.db_file.db_handle.open_blob(table, column, rowid, is_write)
+    }
+
+    pub fn explain_query_plan(&self, q: &str) -> Result<QueryPlan, ReturnStatus> {
+        self.db_file.db_handle.explain_query_plan(q)
+    }
+
+    // The action denied by the files `AuthPolicy` when the last query failed to prepare.
+    pub fn take_auth_denied(&self) -> Option<AuthContext> {
+        self.db_file.db_handle.take_auth_denied()
+    }
+
+
+    // Records the changes to `tables` (`None` = all tables, except the internal `_sma_undo_*` ones) from now until the tx ends, for `get_changeset`.
+    // - Calling again discards the previous recording.
+    pub fn start_session(&self, tables: &Option<Vec<String>>) -> Result<(), ReturnStatus> {
+        // Safety: stored in `self.session`, which is dropped before `self.db_file`.
+        let mut s = unsafe { Session::new_unbound(&self.db_file.db_handle, "main")? };
+
+        match tables {
+            None => {
+                s.attach(None)?;
+                s.set_table_filter(Some(Box::new(|t| !undo::is_internal_table(t))));
+            }
+            Some(ts) => for t in ts {
+                s.attach(Some(t))?;
+            }
+        }
+
+        self.session.replace(Some(s));
+        Ok(())
     }
 
+    // A patchset is smaller, but conflicts on changed rows are not detected when it is applied.
+    pub fn get_changeset(&self, is_patchset: bool) -> Result<Vec<u8>, SessionError> {
+        match &*self.session.borrow() {
+            None => Err(SessionError::NotStarted),
+            Some(s) if is_patchset => Ok(s.patchset()?),
+            Some(s) => Ok(s.changeset()?),
+        }
+    }
+
+    // Applies a changeset from `get_changeset` (usually of another file); `on_conflict` decides what happens to each change that does not apply cleanly.
+    pub fn apply_changeset(&self, changeset: &[u8], on_conflict: &mut ConflictFn) -> Result<(), ReturnStatus> {
+        self.drop_changes_on_err(|| self.db_file.db_handle.apply_changeset(changeset, on_conflict))
+    }
+
+
+    // Opts `tables` in to undo/redo, starting from the next write tx (see `undo`). Returns every opted in table.
+    pub fn enable_undo(&self, tables: &Vec<String>) -> Result<RSet, ReturnStatus> {
+        let h = &self.db_file.db_handle;
+        undo::enable(h, tables)?;
+        h.run("SELECT name FROM _sma_undo_tables ORDER BY name")
+    }
+
+    // Reverts the last undoable write tx (or the last `redo`). Changes are only visible to other txs after `commit`.
+    pub fn undo(&self) -> Result<UndoStep, ReturnStatus> {
+        self.drop_changes_on_err(|| undo::step(&self.db_file.db_handle, &*self.undo_session.borrow(), false))
+    }
+
+    pub fn redo(&self) -> Result<UndoStep, ReturnStatus> {
+        self.drop_changes_on_err(|| undo::step(&self.db_file.db_handle, &*self.undo_session.borrow(), true))
+    }
+
+
+    // Interrupts any query `f` is still running after `timeout`; returns `(f(self), is_timeout)`.
+    // - An interrupted write rolls back the whole tx; see `is_active`.
+    pub fn with_timeout<T, F>(&self, timeout: Duration, f: F) -> (T, bool) where F: FnOnce(&Self) -> T {
+        self.db_file.db_handle.with_timeout(timeout, || f(self))
+    }
+
+    // `false` when SQLite rolled back the tx on its own (E.g. an interrupted write, `SQLITE_FULL`).
+    // - Every change is lost and the write lock has been released; the tx can only be ended with `rollback`.
+    pub fn is_active(&self) -> bool {
+        !self.db_file.db_handle.is_autocommit()
+    }
 
     pub fn commit(self) -> (DbFile, Result<RSet, ReturnStatus>) {
+        let (f, r) = self.commit_with_changes();
+        (f, r.map(|(rset, _)| rset))
+    }
+
+    // Also returns the `(table, op, rowid)` of every row the tx changed (see `sqlite3ffi::hooks::UpdateHookFn` for the exceptions).
+    // - Rows from statements that returned an error are not included (SQLite undid them).
+    pub fn commit_with_changes(self) -> (DbFile, Result<(RSet, Vec<RowChange>), ReturnStatus>) {
+        // Changes cannot be committed without their undo entry.
+        let pushed = match &*self.undo_session.borrow() {
+            Some(s) => undo::push(&self.db_file.db_handle, s),
+            None => Ok(())
+        };
+
+        if let Err(e) = pushed {
+            let _ = self.q("ROLLBACK");
+            return (self.end(), Err(e));
+        }
+
         // @todo/medium In what ways can this fail? Should a fail prevent transfer of ownership and allow a re-try?
         let r = self.q("COMMIT");
+        let changes = self.changes.replace(vec![]);
 
         // Take ownership of self, drop it. Return ownership of `db_file` to calling scope.
         // Assert: Only one transaction per connection should be active.
         // Its not possible to have multiple isolated transactions per file connection in SQLite.
-        (self.db_file, r)
+        let mut f = self.end();
+
+        if r.is_ok() {
+            f.auto_checkpoint();
+        }
+
+        (f, r.map(|rset| (rset, changes)))
     }
 
     // Returns with "cannot rollback - no transaction is active" if already rolled back.
     pub fn rollback(self) -> (DbFile, Result<RSet, ReturnStatus>) {
         let r = self.q("ROLLBACK");
-        (self.db_file, r)
+        (self.end(), r)
+    }
+
+    // Rows changed so far in this tx.
+    pub fn get_changes(&self) -> Vec<RowChange> {
+        self.changes.borrow().clone()
+    }
+
+    fn end(self) -> DbFile {
+        let h = &self.db_file.db_handle;
+        h.set_update_hook(None);
+        h.set_rollback_hook(None);
+        self.session.replace(None);
+        self.db_file
     }
 
     // @todo/low What about `ROLLBACK TO SAVEPOINT` support?
@@ -510,7 +1199,7 @@ fn run_params(db_handle: &DbHandle, q: &str, p: &Params) -> Result<RSet, BindRun
 
 
 fn run_read_only(h: &DbHandle, q: &str) -> Result<RSet, ReadError> {
-    let s = h.new_stmt(&q)?;
+    let s = h.new_stmt_cached(&q)?;
 
     if !s.is_read_only {
         return Err(ReadError::QueryIsWrite);
@@ -520,7 +1209,7 @@ fn run_read_only(h: &DbHandle, q: &str) -> Result<RSet, ReadError> {
 }
 
 fn run_params_read_only(h: &DbHandle, q: &str, p: &Params) -> Result<RSet, ReadBindRunError> {
-    let s = h.new_stmt(&q)?;
+    let s = h.new_stmt_cached(&q)?;
 
     if !s.is_read_only {
         return Err(ReadBindRunError::QueryIsWrite);
@@ -540,7 +1229,7 @@ fn run_params_read_only(h: &DbHandle, q: &str, p: &Params) -> Result<RSet, ReadB
 
 
 fn run_write_only(h: &DbHandle, q: &str) -> Result<RSet, WriteError> {
-    let s = h.new_stmt(&q)?;
+    let s = h.new_stmt_cached(&q)?;
 
     if s.is_read_only {
         return Err(WriteError::QueryIsRead);
@@ -550,7 +1239,7 @@ fn run_write_only(h: &DbHandle, q: &str) -> Result<RSet, WriteError> {
 }
 
 fn run_params_write_only(h: &DbHandle, q: &str, p: &Params) -> Result<RSet, WriteBindRunError> {
-    let s = h.new_stmt(&q)?;
+    let s = h.new_stmt_cached(&q)?;
 
     if s.is_read_only {
         return Err(WriteBindRunError::QueryIsRead);
@@ -1,5 +1,8 @@
 use super::*;
 use std::collections::HashMap;
+use sqlite3ffi::aggregate::Aggregate;
+use sqlite3ffi::hooks::ChangeOp;
+use sqlite3ffi::session::ConflictAction;
 
 // Place outside src so that file writes do not trigger `cargo watch`.
 static TEST_OUTPUT_DIR: &'static str = "/tmp";
@@ -9,6 +12,33 @@ fn get_test_file() -> String {
     format!("{}/del-test-{:?}-{}.sqlite3", TEST_OUTPUT_DIR, now, get_unique_id())
 }
 
+// Compiles `fixtures/ext_fn.c` into a shared library; returns its path.
+// - `cc` is used for the compiler lookup only (it builds static libraries), so the target is set here as there is no build script env.
+fn get_test_extension() -> String {
+    let manifest_dir = env!("CARGO_MANIFEST_DIR");
+    let out = format!("{}/del-test-ext-{}.so", TEST_OUTPUT_DIR, get_unique_id());
+    let target = match std::env::consts::OS {
+        "macos" => format!("{}-apple-darwin", std::env::consts::ARCH),
+        _ => format!("{}-unknown-linux-gnu", std::env::consts::ARCH),
+    };
+
+    let status = cc::Build::new()
+        .target(&target)
+        .host(&target)
+        .opt_level(0)
+        .cargo_metadata(false)
+        .get_compiler()
+        .to_command()
+        .args(&["-shared", "-fPIC", "-o", &out])
+        .arg(format!("-I{}/../sqlite3ffi/c-code/sqlite3/3280000/all", manifest_dir))
+        .arg(format!("{}/fixtures/ext_fn.c", manifest_dir))
+        .status()
+        .expect("Ok");
+
+    assert!(status.success());
+    out
+}
+
 
 // @todo/low Test strings with many queries are ignored: "BEGIN; SELECT 1".
 
@@ -216,6 +246,508 @@ mod tests {
     }
 
 
+    // Assert: Read only connections can read a WAL file, but cannot convert a file to WAL.
+    #[test]
+    fn test_read_only_file() {
+        let file = get_test_file();
+        let c1 = DbFile::new(file.clone()).expect("Ok");
+        let c1 = create_table_a(c1).expect("Ok");
+
+        let c2 = DbFile::new_with_flags(file.clone(), &OpenFlags::read_only()).expect("Ok");
+        let rtx2 = c2.get_read_tx().expect("Ok");
+        assert_eq!(row_count_r(&rtx2), 2);
+
+
+        let file = get_test_file();
+        DbHandle::new(file.clone()).expect("Ok").run("CREATE TABLE t1(a)").expect("Ok");
+
+        match DbFile::new_with_flags(file.clone(), &OpenFlags::read_only()) {
+            Err(rs) => assert_eq!(rs.primary.id, PrimaryRC::SQLITE_READONLY),
+            Ok(_) => assert!(false)
+        }
+    }
+
+
+    // Assert: A file can be backed up while a read tx is open on it.
+    #[test]
+    fn test_backup_to() {
+        let file = get_test_file();
+        let c1 = create_table_a(DbFile::new(file.clone()).expect("Ok")).expect("Ok");
+        let rtx1 = DbFile::new(file.clone()).expect("Ok").get_read_tx().expect("Ok");
+
+        let dst = get_test_file();
+        let mut steps = 0;
+        c1.backup_to(dst.clone(), 1, |_| steps += 1).expect("Ok");
+        assert!(steps > 1);
+
+        let rtx2 = DbFile::new(dst.clone()).expect("Ok").get_read_tx().expect("Ok");
+        assert_eq!(row_count_r(&rtx2), row_count_r(&rtx1));
+    }
+
+
+    #[test]
+    fn test_serialize_deserialize() {
+        let file = get_test_file();
+        let c1 = create_table_a(DbFile::new(file.clone()).expect("Ok")).expect("Ok");
+        let img = c1.serialize().expect("Ok");
+
+        let dst = DbFile::new(get_test_file()).expect("Ok");
+        dst.deserialize(&img).expect("Ok");
+        assert!(dst.deserialize(b"not a database").is_err());
+
+        let rtx1 = DbFile::new(file.clone()).expect("Ok").get_read_tx().expect("Ok");
+        let rtx2 = dst.get_read_tx().expect("Ok");
+        assert_eq!(row_count_r(&rtx2), row_count_r(&rtx1));
+    }
+
+
+    // Assert: Registered functions are installed on connections opened afterwards.
+    #[test]
+    fn test_register_scalar_function() {
+        let file = get_test_file();
+        let before = DbFile::new(file.clone()).expect("Ok").get_read_tx().expect("Ok");
+
+        register_scalar_function(ScalarFunction::new("sma_add_one", 1, true, Box::new(|args| {
+            match args[0] {
+                Val::I64(i) => Ok(Val::I64(i + 1)),
+                _ => Err("Expected an integer.".to_string())
+            }
+        })));
+
+        assert!(before.q("SELECT sma_add_one(1)").is_err());
+
+        let rtx = DbFile::new(file.clone()).expect("Ok").get_read_tx().expect("Ok");
+        assert_eq!(rtx.q("SELECT sma_add_one(1)").expect("Ok").rows.data[0][0], Val::I64(2));
+    }
+
+
+    // Assert: Changes are returned on commit, and cleared by a rollback.
+    #[test]
+    fn test_write_tx_changes() {
+        let file = get_test_file();
+        let c1 = create_table_a(DbFile::new(file.clone()).expect("Ok")).expect("Ok");
+
+        let wtx1 = c1.get_write_tx().expect("Ok");
+        wtx1.q("UPDATE t1 SET b = 5 WHERE a = 3").expect("Ok");
+        assert_eq!(wtx1.get_changes().len(), 1);
+        let (c1, _) = wtx1.rollback();
+
+        let wtx2 = c1.get_write_tx().expect("Ok");
+        wtx2.q("INSERT INTO t1 (a, b) VALUES (5, 6)").expect("Ok");
+        let (_, res) = wtx2.commit_with_changes();
+        let (_, changes) = res.expect("Ok");
+
+        assert_eq!(changes, vec![RowChange { table: "t1".to_string(), op: ChangeOp::Insert, rowid: 3 }]);
+    }
+
+
+    // Assert: Rows from a statement or changeset that fails (and is undone by SQLite) are not returned.
+    #[test]
+    fn test_write_tx_changes_failed_statement() {
+        let src = create_table_a(DbFile::new(get_test_file()).expect("Ok")).expect("Ok");
+        let dst = create_table_a(DbFile::new(get_test_file()).expect("Ok")).expect("Ok");
+        let dst = ins_row(dst, 21, 0);
+
+        let wtx1 = src.get_write_tx().expect("Ok");
+        wtx1.start_session(&Some(vec!["t1".to_string()])).expect("Ok");
+        wtx1.q("INSERT INTO t1 (a, b) VALUES (20, 1), (21, 1)").expect("Ok");
+        let cs = wtx1.get_changeset(false).expect("Ok");
+        wtx1.commit().1.expect("Ok");
+
+        let wtx2 = dst.get_write_tx().expect("Ok");
+        assert!(wtx2.q("INSERT INTO t1 (a, b) VALUES (7, 8), (9, 10), (1, 0)").is_err());
+        assert_eq!(wtx2.get_changes().len(), 0);
+
+        let e = wtx2.exec_script("INSERT INTO t1 (a, b) VALUES (11, 12); INSERT INTO t1 (a, b) VALUES (13, 14), (3, 0);").unwrap_err();
+        assert_eq!(e.index, 1);
+        assert_eq!(wtx2.get_changes().len(), 1);
+
+        assert!(wtx2.apply_changeset(&cs, &mut |_| ConflictAction::Abort).is_err());
+
+        let (_, res) = wtx2.commit_with_changes();
+        let (_, changes) = res.expect("Ok");
+        assert_eq!(changes, vec![RowChange { table: "t1".to_string(), op: ChangeOp::Insert, rowid: 4 }]);
+    }
+
+
+    // Assert: A changeset recorded in a write tx on one file can be applied in a write tx on another.
+    #[test]
+    fn test_write_tx_changeset() {
+        let src = create_table_a(DbFile::new(get_test_file()).expect("Ok")).expect("Ok");
+        let dst = create_table_a(DbFile::new(get_test_file()).expect("Ok")).expect("Ok");
+
+        let wtx1 = src.get_write_tx().expect("Ok");
+        match wtx1.get_changeset(false) {
+            Err(SessionError::NotStarted) => {}
+            _ => assert!(false)
+        }
+
+        wtx1.start_session(&Some(vec!["t1".to_string()])).expect("Ok");
+        wtx1.q("INSERT INTO t1 (a, b) VALUES (5, 6)").expect("Ok");
+        wtx1.q("UPDATE t1 SET b = 7 WHERE a = 1").expect("Ok");
+        let cs = wtx1.get_changeset(false).expect("Ok");
+        let (_, res) = wtx1.commit();
+        res.expect("Ok");
+
+        let wtx2 = dst.get_write_tx().expect("Ok");
+        wtx2.apply_changeset(&cs, &mut |_| ConflictAction::Abort).expect("Ok");
+        let rset = wtx2.q("SELECT a, b FROM t1 ORDER BY a").expect("Ok");
+        wtx2.commit().1.expect("Ok");
+
+        assert_eq!(rset.rows.data, vec![
+            vec![Val::I64(1), Val::I64(7)],
+            vec![Val::I64(3), Val::I64(4)],
+            vec![Val::I64(5), Val::I64(6)],
+        ]);
+    }
+
+
+    // Assert: A session on all tables does not record the internal undo tables.
+    #[test]
+    fn test_write_tx_session_skips_undo_tables() {
+        let src = create_table_a(DbFile::new(get_test_file()).expect("Ok")).expect("Ok");
+        let dst = create_table_a(DbFile::new(get_test_file()).expect("Ok")).expect("Ok");
+
+        let wtx = dst.get_write_tx().expect("Ok");
+        wtx.enable_undo(&vec!["t1".to_string()]).expect("Ok");
+        let (dst, _) = wtx.commit();
+
+        let wtx = src.get_write_tx().expect("Ok");
+        wtx.enable_undo(&vec!["t1".to_string()]).expect("Ok");
+        let (src, _) = wtx.commit();
+
+        let wtx = src.get_write_tx().expect("Ok");
+        wtx.q("UPDATE t1 SET b = 10 WHERE a = 1").expect("Ok");
+        let (src, _) = wtx.commit();
+
+        let wtx1 = src.get_write_tx().expect("Ok");
+        wtx1.start_session(&None).expect("Ok");
+        assert!(wtx1.undo().expect("Ok").is_applied);
+        let cs = wtx1.get_changeset(false).expect("Ok");
+        wtx1.commit().1.expect("Ok");
+
+        let wtx2 = dst.get_write_tx().expect("Ok");
+        wtx2.q("UPDATE t1 SET b = 10 WHERE a = 1").expect("Ok");
+        wtx2.apply_changeset(&cs, &mut |_| ConflictAction::Abort).expect("Ok");
+
+        assert_eq!(wtx2.q("SELECT b FROM t1 WHERE a = 1").expect("Ok").rows.data, vec![vec![Val::I64(2)]]);
+        assert_eq!(wtx2.q("SELECT count(*) FROM _sma_undo_stack").expect("Ok").rows.data, vec![vec![Val::I64(0)]]);
+    }
+
+
+    // Assert: Committed write txs on opted in tables can be undone and redone, across connections.
+    #[test]
+    fn test_undo_redo() {
+        let file = get_test_file();
+        let c1 = create_table_a(DbFile::new(file.clone()).expect("Ok")).expect("Ok");
+        let get_b = |wtx: &WriteTx| wtx.q("SELECT b FROM t1 WHERE a = 1").expect("Ok").rows.data[0][0].clone();
+
+        let wtx1 = c1.get_write_tx().expect("Ok");
+        assert_eq!(wtx1.undo().expect("Ok").is_applied, false);
+        wtx1.enable_undo(&vec!["t1".to_string()]).expect("Ok");
+        let (mut c1, _) = wtx1.commit();
+
+        for b in vec![10, 20] {
+            let wtx = c1.get_write_tx().expect("Ok");
+            wtx.q(&format!("UPDATE t1 SET b = {} WHERE a = 1", b)).expect("Ok");
+            let (c, res) = wtx.commit();
+            res.expect("Ok");
+            c1 = c;
+        }
+
+        let wtx2 = DbFile::new(file.clone()).expect("Ok").get_write_tx().expect("Ok");
+        assert_eq!(wtx2.undo().expect("Ok"), UndoStep { is_applied: true, is_discarded: false, undo_len: 1, redo_len: 1 });
+        assert_eq!(get_b(&wtx2), Val::I64(10));
+
+        let (c2, res) = wtx2.commit_with_changes();
+        assert_eq!(res.expect("Ok").1.len(), 1);
+
+        let wtx3 = c2.get_write_tx().expect("Ok");
+        assert_eq!(wtx3.redo().expect("Ok"), UndoStep { is_applied: true, is_discarded: false, undo_len: 2, redo_len: 0 });
+        assert_eq!(get_b(&wtx3), Val::I64(20));
+        assert_eq!(wtx3.redo().expect("Ok").is_applied, false);
+        let (c2, _) = wtx3.commit();
+
+        // Assert: A new change clears the redo stack.
+        let wtx4 = c2.get_write_tx().expect("Ok");
+        wtx4.undo().expect("Ok");
+        wtx4.q("UPDATE t1 SET b = 30 WHERE a = 3").expect("Ok");
+        wtx4.commit().1.expect("Ok");
+
+        let wtx5 = c1.get_write_tx().expect("Ok");
+        assert_eq!(wtx5.redo().expect("Ok"), UndoStep { is_applied: false, is_discarded: false, undo_len: 2, redo_len: 0 });
+    }
+
+
+    // Assert: An undo entry that conflicts with a change made outside of the undo history is removed, so the next step can run.
+    #[test]
+    fn test_undo_conflict_discarded() {
+        let file = get_test_file();
+        let c1 = create_table_a(DbFile::new(file.clone()).expect("Ok")).expect("Ok");
+
+        let wtx1 = c1.get_write_tx().expect("Ok");
+        wtx1.enable_undo(&vec!["t1".to_string()]).expect("Ok");
+        let (c1, _) = wtx1.commit();
+
+        let wtx2 = c1.get_write_tx().expect("Ok");
+        wtx2.q("UPDATE t1 SET b = 10 WHERE a = 1").expect("Ok");
+        wtx2.q("UPDATE t1 SET b = 20 WHERE a = 3").expect("Ok");
+        let (c1, _) = wtx2.commit();
+
+        let wtx3 = c1.get_write_tx().expect("Ok");
+        wtx3.q("UPDATE t1 SET b = 30 WHERE a = 3").expect("Ok");
+        let (c1, _) = wtx3.commit();
+
+        // Not recorded, as it does not use a `WriteTx`.
+        DbHandle::new(file.clone()).expect("Ok").run("UPDATE t1 SET b = 40 WHERE a = 3").expect("Ok");
+
+        let wtx4 = c1.get_write_tx().expect("Ok");
+        assert_eq!(wtx4.undo().expect("Ok"), UndoStep { is_applied: false, is_discarded: true, undo_len: 1, redo_len: 0 });
+        assert_eq!(wtx4.q("SELECT b FROM t1 WHERE a = 3").expect("Ok").rows.data[0][0], Val::I64(40));
+        let (c1, _) = wtx4.commit();
+
+        let wtx5 = c1.get_write_tx().expect("Ok");
+        assert_eq!(wtx5.undo().expect("Ok").is_discarded, true);
+        assert_eq!(wtx5.undo().expect("Ok"), UndoStep { is_applied: false, is_discarded: false, undo_len: 0, redo_len: 0 });
+        wtx5.commit().1.expect("Ok");
+    }
+
+
+    // Assert: A timed out read leaves the tx active, a timed out write rolls it back.
+    #[test]
+    fn test_write_tx_timeout() {
+        let c1 = create_table_a(DbFile::new(get_test_file()).expect("Ok")).expect("Ok");
+        let forever = "WITH RECURSIVE c(x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM c) SELECT count(*) FROM c";
+
+        let wtx = c1.get_write_tx().expect("Ok");
+        wtx.q("INSERT INTO t1 (a, b) VALUES (5, 6)").expect("Ok");
+
+        let (r, is_timeout) = wtx.with_timeout(ms(20), |w| w.read(forever));
+        assert!(is_timeout && r.is_err());
+        assert!(wtx.is_active());
+
+        let (r, is_timeout) = wtx.with_timeout(ms(20), |w| w.q(&format!("INSERT INTO t1 (a) {}", forever)));
+        assert!(is_timeout && r.is_err());
+        assert_eq!(wtx.get_changes().len(), 0);
+        assert!(!wtx.is_active());
+        let (c1, _) = wtx.rollback();
+
+        let rtx = c1.get_read_tx().expect("Ok");
+        let rset = rtx.q("SELECT a FROM t1 ORDER BY a").expect("Ok");
+        assert_eq!(rset.rows.data, vec![vec![Val::I64(1)], vec![Val::I64(3)]]);
+    }
+
+
+    // Assert: The default policy checkpoints once the WAL reaches 1000 frames; an open read tx stops it from completing.
+    #[test]
+    fn test_auto_checkpoint() {
+        let file = get_test_file();
+        let c1 = create_table_a(DbFile::new(file.clone()).expect("Ok")).expect("Ok");
+
+        let rtx = DbFile::new(file.clone()).expect("Ok").get_read_tx().expect("Ok");
+        rtx.q("SELECT * FROM t1").expect("Ok");
+
+        let wtx = c1.get_write_tx().expect("Ok");
+        wtx.q("INSERT INTO t1 (a, b) VALUES (10, zeroblob(5000000))").expect("Ok");
+        let (mut c1, res) = wtx.commit();
+        res.expect("Ok");
+
+        let c = c1.take_auto_checkpoint().expect("Some");
+        assert!(c.wal_frames >= 1000 && !c.is_complete());
+        assert_eq!(c1.take_auto_checkpoint(), None);
+
+        rtx.commit().1.expect("Ok");
+        let c = c1.checkpoint(CheckpointMode::Truncate).expect("Ok");
+        assert!(c.is_complete());
+    }
+
+
+    // Assert: Without a policy, SQLites auto checkpoint runs (it is not reported).
+    #[test]
+    fn test_auto_checkpoint_sqlite() {
+        let file = get_test_file();
+        let config = DbFileConfig { checkpoint_policy: None, ..Default::default() };
+        let c1 = create_table_a(DbFile::new_with_config(file.clone(), &OpenFlags::default(), &config).expect("Ok")).expect("Ok");
+
+        let wtx = c1.get_write_tx().expect("Ok");
+        wtx.q("INSERT INTO t1 (a, b) VALUES (10, zeroblob(5000000))").expect("Ok");
+        let (mut c1, res) = wtx.commit();
+        res.expect("Ok");
+
+        assert_eq!(c1.take_auto_checkpoint(), None);
+
+        // Assert: The WAL was already copied by SQLite, so a passive checkpoint has nothing left to do.
+        let c = c1.checkpoint(CheckpointMode::Passive).expect("Ok");
+        assert!(c.wal_frames >= 1000 && c.checkpointed_frames == c.wal_frames);
+    }
+
+
+    // Assert: A files auth policy denies statements at prepare time, and the denied action can be read from the tx.
+    #[test]
+    fn test_auth_policy() {
+        let file = get_test_file();
+        let c1 = create_table_a(DbFile::new(file.clone()).expect("Ok")).expect("Ok");
+        drop(c1);
+
+        // Assert: The policy is keyed on the canonical path, so it can be set and opened with different names for the file.
+        let other_name = file.replacen("/tmp/", "/tmp/../tmp/", 1);
+        set_auth_policy(&other_name, Some(AuthPolicy { deny_ddl_tables: vec!["T1".to_string()], ..AuthPolicy::sandbox() }));
+        let wtx = DbFile::new(format!("file:{}", file)).expect("Ok").get_write_tx().expect("Ok");
+        assert!(wtx.q("DROP TABLE t1").is_err());
+        drop(wtx.rollback());
+
+        let wtx = DbFile::new(file.clone()).expect("Ok").get_write_tx().expect("Ok");
+
+        let e = wtx.q("DROP TABLE t1").expect_err("Err");
+        assert_eq!(e.primary.id, PrimaryRC::SQLITE_AUTH);
+        assert_eq!(wtx.take_auth_denied().expect("Some").action, AuthAction::DropTable { table: "t1".to_string() });
+
+        assert!(wtx.q("CREATE INDEX t1_b ON t1(b)").is_err());
+        assert!(wtx.q("ATTACH ':memory:' AS other").is_err());
+        assert!(wtx.q("SELECT load_extension('x')").is_err());
+        assert_eq!(wtx.take_auth_denied().expect("Some").action, AuthAction::Function { name: "load_extension".to_string() });
+        assert!(wtx.q("PRAGMA writable_schema = ON").is_err());
+
+        wtx.q("CREATE TABLE t2(a)").expect("Ok");
+        wtx.q("INSERT INTO t1 (a, b) VALUES (10, 11)").expect("Ok");
+        wtx.commit().1.expect("Ok");
+
+        // Assert: Other files, and connections opened after the policy is removed, are not affected.
+        let wtx = create_table_a(DbFile::new(get_test_file()).expect("Ok")).expect("Ok").get_write_tx().expect("Ok");
+        wtx.q("DROP TABLE t1").expect("Ok");
+
+        set_auth_policy(&file, None);
+        let wtx = DbFile::new(file.clone()).expect("Ok").get_write_tx().expect("Ok");
+        wtx.q("DROP TABLE t1").expect("Ok");
+    }
+
+
+    // Assert: Connections are opened with `DbConfig::secure()` by default.
+    #[test]
+    fn test_db_config() {
+        let c = create_table_a(DbFile::new(get_test_file()).expect("Ok")).expect("Ok");
+        let config = c.get_db_config().expect("Ok");
+        assert_eq!(config.defensive, Some(true));
+        assert_eq!(config.enable_load_extension, Some(false));
+
+        // Assert: `sqlite_master` cannot be written, even with `writable_schema`.
+        let wtx = c.get_write_tx().expect("Ok");
+        wtx.q("PRAGMA writable_schema = ON").expect("Ok");
+        assert!(wtx.q("DELETE FROM sqlite_master").is_err());
+    }
+
+
+    // Assert: Missing extension files are rejected when set, so connections still open.
+    #[test]
+    fn test_extensions_missing() {
+        let missing = Extension { path: "/tmp/del-missing-extension.so".to_string(), entry_point: None };
+        let mut config = DbFileConfig::default();
+        assert!(config.set_extensions(vec![missing]).is_err());
+        assert!(DbFile::new_with_config(get_test_file(), &OpenFlags::default(), &config).is_ok());
+    }
+
+
+    // Assert: An allowed extension is loaded into new connections, so its functions can be used in a tx.
+    #[test]
+    fn test_extensions_load() {
+        let ext = Extension { path: get_test_extension(), entry_point: None };
+        let mut config = DbFileConfig::default();
+        config.set_extensions(vec![ext.clone()]).expect("Ok");
+
+        let rtx = DbFile::new_with_config(get_test_file(), &OpenFlags::default(), &config).expect("Ok").get_read_tx().expect("Ok");
+        let rset = rtx.q("SELECT ext_fn()").expect("Ok");
+        assert_eq!(rset.rows.data[0][0], Val::I64(42));
+        rtx.commit().1.expect("Ok");
+
+        // Assert: Connections opened without it do not have the function.
+        let rtx = DbFile::new(get_test_file()).expect("Ok").get_read_tx().expect("Ok");
+        assert!(rtx.q("SELECT ext_fn()").is_err());
+
+        std::fs::remove_file(&ext.path).expect("Ok");
+    }
+
+
+    // Assert: The limits profile applies to connections opened with it.
+    #[test]
+    fn test_limits() {
+        let file = get_test_file();
+        let before = DbFile::new(file.clone()).expect("Ok");
+
+        let config = DbFileConfig { limits: Some(Limits { attached: Some(0), ..Default::default() }), ..Default::default() };
+        let after = DbFile::new_with_config(file.clone(), &OpenFlags::default(), &config).expect("Ok");
+
+        let attach = |c: DbFile| {
+            let wtx = c.get_write_tx().expect("Ok");
+            wtx.q("ATTACH ':memory:' AS other")
+        };
+
+        let e = attach(after).expect_err("Err");
+        assert_eq!(sqlite3ffi::limits::get_limit_exceeded(&e), Some(sqlite3ffi::limits::Limit::Attached));
+        assert!(attach(before).is_ok());
+    }
+
+
+    // Assert: With a threshold of 0 every statement is logged; connections opened without the log are not traced.
+    #[test]
+    fn test_slow_query_log() {
+        let file = get_test_file();
+        let c1 = create_table_a(DbFile::new(file.clone()).expect("Ok")).expect("Ok");
+
+        let config = DbFileConfig { slow_query_log: Some(SlowQueryLog { threshold_ms: 0, max_entries: 10000, rset_timing: true }), ..Default::default() };
+        let rtx = DbFile::new_with_config(file.clone(), &OpenFlags::default(), &config).expect("Ok").get_read_tx().expect("Ok");
+        let rset = rtx.q("SELECT * FROM t1 WHERE a > 1 ORDER BY b").expect("Ok");
+        assert!(rset.timing.is_some());
+        rtx.commit().1.expect("Ok");
+
+        let qs = get_slow_queries(Some(&file));
+        assert!(qs.iter().all(|q| q.file == file));
+
+        let q = qs.iter().find(|q| q.sql == "SELECT * FROM t1 WHERE a > 1 ORDER BY b").expect("Some");
+        assert_eq!(q.normalized_sql, Some("SELECT*FROM t1 WHERE a>?ORDER BY b;".to_string()));
+        assert_eq!(q.rows, rset.num_rows as u64);
+        assert_eq!(q.status.sorts, 1);
+
+        let n = qs.len();
+        let rtx = c1.get_read_tx().expect("Ok");
+        rtx.q("SELECT * FROM t1").expect("Ok");
+        assert_eq!(get_slow_queries(Some(&file)).len(), n);
+    }
+
+
+    // Assert: Built in collations are available on every connection.
+    #[test]
+    fn test_builtin_collations() {
+        let rtx = DbFile::new(get_test_file()).expect("Ok").get_read_tx().expect("Ok");
+        let rset = rtx.q("SELECT 'file2' < 'file10' COLLATE NATSORT, 'Ä' = 'ä' COLLATE CASEFOLD").expect("Ok");
+        assert_eq!(rset.rows.data[0], vec![Val::I64(1), Val::I64(1)]);
+    }
+
+
+    struct Count(i64);
+
+    impl Aggregate for Count {
+        fn step(&mut self, _args: &[Val]) -> Result<(), String> {
+            self.0 += 1;
+            Ok(())
+        }
+
+        fn value(&self) -> Result<Val, String> {
+            Ok(Val::I64(self.0))
+        }
+    }
+
+    #[test]
+    fn test_register_aggregate_function() {
+        register_aggregate_function(AggregateFunction::new("sma_count", 1, true, false, Box::new(|| Box::new(Count(0)))));
+
+        let file = get_test_file();
+        let c1 = create_table_a(DbFile::new(file.clone()).expect("Ok")).expect("Ok");
+        let rtx = c1.get_read_tx().expect("Ok");
+
+        assert_eq!(rtx.q("SELECT sma_count(a) FROM t1").expect("Ok").rows.data[0][0], Val::I64(row_count_r(&rtx)));
+    }
+
+
     // Assert: When using the read tx API, only accept a SQL string that is read.
     #[test]
     fn test_read_tx_no_writes() {
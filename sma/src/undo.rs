@@ -0,0 +1,197 @@
+// Multi level undo/redo across write txs, built on session changesets.
+// @see https://www.sqlite.org/sessionintro.html
+//
+// - Tables opt in with `WriteTx::enable_undo`; every later write tx records the changes to them in an undo session.
+// - On commit, the inverted changeset is pushed onto the undo stack (and the redo stack is cleared), in the same tx as the changes.
+// - `WriteTx::undo` applies the top of the undo stack and pushes its inverse onto the redo stack (and the reverse for `redo`).
+// - Both stacks are stored in the database file, so they survive restarts and are shared by every process using the file.
+//
+// Note: Only tables with a `PRIMARY KEY` can be undone (a session requirement).
+// @todo/low Limit the stack size; at the moment every undoable write tx is kept.
+
+use serde::{Deserialize, Serialize};
+
+use sqlite3ffi::{
+    db::{
+        DbHandle,
+        BindRunError,
+    },
+    stmt::{
+        RSet,
+        Val,
+        ErrorBindType,
+    },
+    session::{
+        Session,
+        ConflictAction,
+        invert_changeset,
+    },
+    err::ReturnStatus,
+    errmap::PrimaryRC,
+};
+
+
+// Internal tables are not reported as changes (see `RowChange`).
+static PREFIX: &str = "_sma_undo";
+
+static SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS _sma_undo_tables(name TEXT PRIMARY KEY) WITHOUT ROWID;
+    CREATE TABLE IF NOT EXISTS _sma_undo_stack(id INTEGER PRIMARY KEY, is_redo INTEGER NOT NULL, changeset BLOB NOT NULL);
+";
+
+
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Serialize, Deserialize)]
+#[derive(Clone)]
+pub struct UndoStep {
+    // `false` when the stack was empty, so nothing changed.
+    pub is_applied: bool,
+
+    // `true` when the top entry conflicted with the current rows and was removed without being applied (see `step`).
+    pub is_discarded: bool,
+
+    // Stack sizes after the step, E.g. for enabling undo/redo buttons.
+    pub undo_len: i64,
+    pub redo_len: i64,
+}
+
+
+pub(crate) fn is_internal_table(table: &str) -> bool {
+    table.starts_with(PREFIX)
+}
+
+pub(crate) fn enable(h: &DbHandle, tables: &Vec<String>) -> Result<(), ReturnStatus> {
+    h.exec_script(SCHEMA).map_err(|e| e.return_status)?;
+
+    for t in tables {
+        run_index(h, "INSERT OR IGNORE INTO _sma_undo_tables (name) VALUES (?)", vec![Val::String(t.clone())])?;
+    }
+
+    Ok(())
+}
+
+pub(crate) fn get_tables(h: &DbHandle) -> Result<Vec<String>, ReturnStatus> {
+    let exists = h.run("SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = '_sma_undo_tables'")?;
+
+    if exists.rows.data.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let rset = h.run("SELECT name FROM _sma_undo_tables ORDER BY name")?;
+
+    Ok(
+        rset.rows.data.into_iter().filter_map(|mut r| match r.remove(0) {
+            Val::String(s) => Some(s),
+            _ => None
+        }).collect()
+    )
+}
+
+// `None` when no tables have opted in.
+// Safety: the session does not borrow `h`; see `Session::new_unbound`.
+pub(crate) unsafe fn start_session(h: &DbHandle) -> Result<Option<Session<'static>>, ReturnStatus> {
+    let tables = get_tables(h)?;
+
+    if tables.is_empty() {
+        return Ok(None);
+    }
+
+    let s = Session::new_unbound(h, "main")?;
+    for t in tables.iter() {
+        s.attach(Some(t))?;
+    }
+
+    Ok(Some(s))
+}
+
+// Called just before `COMMIT`.
+pub(crate) fn push(h: &DbHandle, s: &Session) -> Result<(), ReturnStatus> {
+    let cs = s.changeset()?;
+
+    if cs.is_empty() {
+        return Ok(());
+    }
+
+    push_changeset(h, &invert_changeset(&cs)?, false)?;
+    h.run("DELETE FROM _sma_undo_stack WHERE is_redo = 1")?;
+    Ok(())
+}
+
+// Pops the top changeset from the undo (or redo) stack, applies it, and pushes its inverse onto the other stack.
+// - `s` is the write txs undo session; it is disabled while applying so the step itself is not recorded as a new undo entry.
+// - If a row was changed outside of the undo history (E.g. by another tool) since the changeset was recorded, the apply aborts and the entry is removed instead (`is_discarded`).
+//      - Otherwise it would stay on top of the stack, blocking every later step.
+pub(crate) fn step(h: &DbHandle, s: &Option<Session>, is_redo: bool) -> Result<UndoStep, ReturnStatus> {
+    if get_tables(h)?.is_empty() {
+        return Ok(UndoStep { is_applied: false, is_discarded: false, undo_len: 0, redo_len: 0 });
+    }
+
+    let top = run_index(h, "SELECT id, changeset FROM _sma_undo_stack WHERE is_redo = ? ORDER BY id DESC LIMIT 1", vec![Val::I64(is_redo as i64)])?;
+
+    let (id, cs) = match top.rows.data.into_iter().next() {
+        None => return get_step(h, false),
+        Some(mut r) => match (r.remove(0), r.remove(0)) {
+            (Val::I64(id), Val::Blob(cs)) => (id, cs),
+            x => {
+                let mut rs: ReturnStatus = PrimaryRC::SQLITE_CORRUPT.into();
+                rs.err_msg = Some(format!("Invalid undo stack row: {:?}", x));
+                return Err(rs);
+            }
+        }
+    };
+
+    if let Some(s) = s {
+        s.set_enabled(false);
+    }
+
+    let applied = h.apply_changeset(&cs, &mut |_| ConflictAction::Abort);
+
+    if let Some(s) = s {
+        s.set_enabled(true);
+    }
+
+    // The changeset is applied all or nothing, so on abort no row has changed.
+    if let Err(rs) = applied {
+        if rs.primary.id != PrimaryRC::SQLITE_ABORT {
+            return Err(rs);
+        }
+
+        run_index(h, "DELETE FROM _sma_undo_stack WHERE id = ?", vec![Val::I64(id)])?;
+        return Ok(UndoStep { is_discarded: true, ..get_step(h, false)? });
+    }
+
+    run_index(h, "DELETE FROM _sma_undo_stack WHERE id = ?", vec![Val::I64(id)])?;
+    push_changeset(h, &invert_changeset(&cs)?, !is_redo)?;
+    get_step(h, true)
+}
+
+
+fn push_changeset(h: &DbHandle, cs: &[u8], is_redo: bool) -> Result<(), ReturnStatus> {
+    run_index(h, "INSERT INTO _sma_undo_stack (is_redo, changeset) VALUES (?, ?)", vec![Val::I64(is_redo as i64), Val::Blob(cs.to_vec())])?;
+    Ok(())
+}
+
+fn get_step(h: &DbHandle, is_applied: bool) -> Result<UndoStep, ReturnStatus> {
+    let rset = h.run("SELECT sum(is_redo = 0), sum(is_redo = 1) FROM _sma_undo_stack")?;
+
+    let to_i64 = |v: &Val| match v {
+        Val::I64(i) => *i,
+        _ => 0
+    };
+
+    let r = &rset.rows.data[0];
+    Ok(UndoStep { is_applied, is_discarded: false, undo_len: to_i64(&r[0]), redo_len: to_i64(&r[1]) })
+}
+
+// The queries here are fixed and always bind every param, so only a `ReturnStatus` error is possible.
+fn run_index(h: &DbHandle, q: &str, vals: Vec<Val>) -> Result<RSet, ReturnStatus> {
+    match h.run_index(q, &vals) {
+        Ok(rset) => Ok(rset),
+        Err(BindRunError::ReturnStatus(rs)) => Err(rs),
+        Err(BindRunError::ErrorBind(eb)) => match eb.kind {
+            ErrorBindType::ReturnStatus(rs) => Err(rs),
+            _ => panic!("Undo query params do not match: {:?}", eb)
+        }
+    }
+}
@@ -15,6 +15,18 @@ fn get_sqlite3_amalgamation_dir(version: &str) -> String {
     format!("{}/c-code/sqlite3/{}/all", crate_with_build_script_dir, version)
 }
 
+// These change which functions `sqlite3.h` declares (E.g. `sqlite3_preupdate_hook`), so are also passed to bindgen.
+// Note: `build_already_ran` does not detect flag changes; run `cargo clean -p sqlite3ffi` after editing.
+static OPTIONAL_FLAGS: [&str; 5] = [
+    "SQLITE_ENABLE_UNLOCK_NOTIFY",
+    "SQLITE_ENABLE_PREUPDATE_HOOK",
+    "SQLITE_ENABLE_SESSION",
+    "SQLITE_ENABLE_DESERIALIZE",
+    // `sqlite3_busy_timeout` waits in the unix VFS on the `fcntl` lock itself, so a lock released by another process is taken within 1ms.
+    "SQLITE_ENABLE_SETLK_TIMEOUT",
+];
+
+
 fn add_flags(cfg: &mut cc::Build, flags: &[&str]) {
     for o in flags.iter() {
         cfg.flag(format!("-D{}", o).as_str());
@@ -40,6 +52,7 @@ fn add_sqlite3_flags(cfg: &mut cc::Build) {
         "SQLITE_ENABLE_JSON1",
         "SQLITE_ENABLE_LOAD_EXTENSION=1",
         "SQLITE_ENABLE_MEMORY_MANAGEMENT",
+        "SQLITE_ENABLE_NORMALIZE",
         "SQLITE_ENABLE_RTREE",
         "SQLITE_ENABLE_STAT2",
         "SQLITE_ENABLE_STAT4",
@@ -57,18 +70,11 @@ fn add_sqlite3_flags(cfg: &mut cc::Build) {
     //  2 = Full, (default).
 
 
-    let optional = [
-        "SQLITE_ENABLE_UNLOCK_NOTIFY",
-        "SQLITE_ENABLE_PREUPDATE_HOOK",
-        "SQLITE_ENABLE_SESSION",
-    ];
-
-
     // SQLITE_MAX_EXPR_DEPTH
     // SQLITE_MAX_VARIABLE_NUMBER
 
     add_flags(cfg, &base);
-    add_flags(cfg, &optional);
+    add_flags(cfg, &OPTIONAL_FLAGS);
 
     // @todo/low `.opt_level(2)`, `.static_crt(true)`?
 }
@@ -158,9 +164,15 @@ fn main() {
     compile_sqlite3(&sqlite_amalgamation_dir);
 
     // Generate bindings.
-    let bindings = bindgen::Builder::default()
+    let mut builder = bindgen::Builder::default()
         .header(format!("{}/sqlite3.h", sqlite_amalgamation_dir))
-        .rustfmt_bindings(true)
+        .rustfmt_bindings(true);
+
+    for o in OPTIONAL_FLAGS.iter() {
+        builder = builder.clang_arg(format!("-D{}", o));
+    }
+
+    let bindings = builder
         .generate()
         .expect("Unable to generate bindings");
 
@@ -0,0 +1,218 @@
+use std::ptr;
+use std::mem;
+use std::sync::Arc;
+use std::os::raw::{
+    c_int,
+    c_void,
+};
+
+use crate::cffi::{
+    // Objects
+    sqlite3_context,
+    sqlite3_value,
+
+    // Functions
+    sqlite3_create_function_v2,
+    sqlite3_create_window_function,
+    sqlite3_aggregate_context,
+    sqlite3_user_data,
+};
+
+use crate::fns::to_cstr;
+use crate::db::DbHandle;
+use crate::stmt::Val;
+use crate::function::{
+    get_args,
+    get_text_rep,
+    set_result,
+    catch_panic,
+};
+use crate::err::{
+    ReturnStatus,
+    to_return_status_cint_db_err,
+};
+
+
+// State for a single group (`GROUP BY`) or window partition.
+// @see https://www.sqlite.org/c3ref/create_function.html
+// @see https://www.sqlite.org/windowfunctions.html#user_defined_aggregate_window_functions
+//
+// - Aggregates implement `step` and `value`.
+// - Window functions also implement `inverse`, which removes the oldest row from the frame.
+// - `Err(msg)` from any method fails the statement with `msg`.
+pub trait Aggregate {
+    // Adds a row.
+    fn step(&mut self, args: &[Val]) -> Result<(), String>;
+
+    // Removes a row previously passed to `step` (window functions only).
+    fn inverse(&mut self, _args: &[Val]) -> Result<(), String> {
+        Err("Aggregate does not implement `inverse`.".to_string())
+    }
+
+    // Current result; called once per row for window functions.
+    fn value(&self) -> Result<Val, String>;
+
+    // Result for the group; the state is dropped afterwards.
+    // - Called on a fresh state when the group has no rows (E.g. `SELECT agg(a) FROM empty_table`).
+    fn finalize(self: Box<Self>) -> Result<Val, String> {
+        self.value()
+    }
+}
+
+
+// Creates the state for a new group.
+pub type AggregateFactory = dyn Fn() -> Box<dyn Aggregate> + Send + Sync;
+
+
+// A function definition that can be installed on many connections.
+#[derive(Clone)]
+pub struct AggregateFunction {
+    pub name: String,
+
+    // `-1` = any number of args.
+    pub n_args: i32,
+
+    pub deterministic: bool,
+
+    // `true` = registered via `sqlite3_create_window_function`; usable with `OVER (...)` and as a plain aggregate.
+    // `false` = plain aggregate only ("may not be used as a window function").
+    pub is_window: bool,
+
+    pub new_state: Arc<AggregateFactory>,
+}
+
+impl std::fmt::Debug for AggregateFunction {
+    fn fmt(&self, fm: &mut std::fmt::Formatter) -> std::fmt::Result {
+        fm.debug_struct("AggregateFunction")
+            .field("name", &self.name)
+            .field("n_args", &self.n_args)
+            .field("deterministic", &self.deterministic)
+            .field("is_window", &self.is_window)
+            .finish()
+    }
+}
+
+impl AggregateFunction {
+    pub fn new(name: &str, n_args: i32, deterministic: bool, is_window: bool, new_state: Box<AggregateFactory>) -> AggregateFunction {
+        AggregateFunction {
+            name: name.to_string(),
+            n_args,
+            deterministic,
+            is_window,
+            new_state: Arc::from(new_state),
+        }
+    }
+
+    // Note: Registering a function with the same name and `n_args` replaces the previous one.
+    pub fn install(&self, db: &DbHandle) -> Result<(), ReturnStatus> {
+        let name_c = to_cstr(&self.name);
+
+        // Owned by SQLite; freed by `destroy_factory` when the function is replaced or the connection closes.
+        let user_data = Box::into_raw(Box::new(self.new_state.clone())) as *mut c_void;
+
+        let r = unsafe {
+            if self.is_window {
+                sqlite3_create_window_function(
+                    db.db,
+                    name_c.as_ptr(),
+                    self.n_args as c_int,
+                    get_text_rep(self.deterministic),
+                    user_data,
+                    Some(call_step),
+                    Some(call_final),
+                    Some(call_value),
+                    Some(call_inverse),
+                    Some(destroy_factory),
+                )
+            } else {
+                sqlite3_create_function_v2(
+                    db.db,
+                    name_c.as_ptr(),
+                    self.n_args as c_int,
+                    get_text_rep(self.deterministic),
+                    user_data,
+                    None,
+                    Some(call_step),
+                    Some(call_final),
+                    Some(destroy_factory),
+                )
+            }
+        };
+
+        to_return_status_cint_db_err(&r, db.db)?;
+        Ok(())
+    }
+}
+
+
+type State = Box<dyn Aggregate>;
+
+fn new_state(ctx: *mut sqlite3_context) -> State {
+    let f = unsafe { &*(sqlite3_user_data(ctx) as *const Arc<AggregateFactory>) };
+    f()
+}
+
+// `sqlite3_aggregate_context` allocates zeroed memory per group that SQLite frees after `xFinal`.
+// - It holds a pointer to the Rust state (`null` until the first call for the group).
+fn get_state<'a>(ctx: *mut sqlite3_context) -> Result<&'a mut State, String> {
+    unsafe {
+        let slot = sqlite3_aggregate_context(ctx, mem::size_of::<*mut State>() as c_int) as *mut *mut State;
+
+        if slot.is_null() {
+            return Err("Could not allocate aggregate context.".to_string());
+        }
+
+        if (*slot).is_null() {
+            *slot = Box::into_raw(Box::new(new_state(ctx)));
+        }
+
+        Ok(&mut **slot)
+    }
+}
+
+// Moves the state out of the aggregate context so it is dropped after `finalize`.
+fn take_state(ctx: *mut sqlite3_context) -> State {
+    unsafe {
+        // `0` = do not allocate; `null` when `xStep` was never called for this group.
+        let slot = sqlite3_aggregate_context(ctx, 0) as *mut *mut State;
+
+        if slot.is_null() || (*slot).is_null() {
+            return new_state(ctx);
+        }
+
+        let state = Box::from_raw(*slot);
+        *slot = ptr::null_mut();
+        *state
+    }
+}
+
+
+unsafe extern "C" fn call_step(ctx: *mut sqlite3_context, argc: c_int, argv: *mut *mut sqlite3_value) {
+    let args = get_args(argc, argv);
+
+    if let Err(msg) = catch_panic(|| get_state(ctx)?.step(&args)) {
+        set_result(ctx, &Err(msg));
+    }
+}
+
+unsafe extern "C" fn call_inverse(ctx: *mut sqlite3_context, argc: c_int, argv: *mut *mut sqlite3_value) {
+    let args = get_args(argc, argv);
+
+    if let Err(msg) = catch_panic(|| get_state(ctx)?.inverse(&args)) {
+        set_result(ctx, &Err(msg));
+    }
+}
+
+unsafe extern "C" fn call_value(ctx: *mut sqlite3_context) {
+    let res = catch_panic(|| get_state(ctx)?.value());
+    set_result(ctx, &res);
+}
+
+unsafe extern "C" fn call_final(ctx: *mut sqlite3_context) {
+    let res = catch_panic(|| take_state(ctx).finalize());
+    set_result(ctx, &res);
+}
+
+unsafe extern "C" fn destroy_factory(user_data: *mut c_void) {
+    drop(Box::from_raw(user_data as *mut Arc<AggregateFactory>));
+}
@@ -0,0 +1,195 @@
+use serde::{Deserialize, Serialize};
+
+use crate::cffi::{
+    SQLITE_OK,
+    SQLITE_DENY,
+    SQLITE_IGNORE,
+    SQLITE_CREATE_INDEX,
+    SQLITE_CREATE_TABLE,
+    SQLITE_CREATE_TEMP_INDEX,
+    SQLITE_CREATE_TEMP_TABLE,
+    SQLITE_CREATE_TEMP_TRIGGER,
+    SQLITE_CREATE_TEMP_VIEW,
+    SQLITE_CREATE_TRIGGER,
+    SQLITE_CREATE_VIEW,
+    SQLITE_DELETE,
+    SQLITE_DROP_INDEX,
+    SQLITE_DROP_TABLE,
+    SQLITE_DROP_TEMP_INDEX,
+    SQLITE_DROP_TEMP_TABLE,
+    SQLITE_DROP_TEMP_TRIGGER,
+    SQLITE_DROP_TEMP_VIEW,
+    SQLITE_DROP_TRIGGER,
+    SQLITE_DROP_VIEW,
+    SQLITE_INSERT,
+    SQLITE_PRAGMA,
+    SQLITE_READ,
+    SQLITE_SELECT,
+    SQLITE_TRANSACTION,
+    SQLITE_UPDATE,
+    SQLITE_ATTACH,
+    SQLITE_DETACH,
+    SQLITE_ALTER_TABLE,
+    SQLITE_REINDEX,
+    SQLITE_ANALYZE,
+    SQLITE_CREATE_VTABLE,
+    SQLITE_DROP_VTABLE,
+    SQLITE_FUNCTION,
+    SQLITE_SAVEPOINT,
+    SQLITE_RECURSIVE,
+};
+
+use crate::errmap::PrimaryRC;
+use crate::err::ReturnStatus;
+
+
+// What a statement being prepared will do; one call per action (E.g. per column read).
+// @see https://www.sqlite.org/c3ref/c_alter_table.html
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Serialize, Deserialize)]
+#[derive(Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthAction {
+    CreateIndex { index: String, table: String },
+    CreateTable { table: String },
+    CreateTempIndex { index: String, table: String },
+    CreateTempTable { table: String },
+    CreateTempTrigger { trigger: String, table: String },
+    CreateTempView { view: String },
+    CreateTrigger { trigger: String, table: String },
+    CreateView { view: String },
+    Delete { table: String },
+    DropIndex { index: String, table: String },
+    DropTable { table: String },
+    DropTempIndex { index: String, table: String },
+    DropTempTable { table: String },
+    DropTempTrigger { trigger: String, table: String },
+    DropTempView { view: String },
+    DropTrigger { trigger: String, table: String },
+    DropView { view: String },
+    Insert { table: String },
+    // `arg` = the value being set (`PRAGMA name = arg`), or the argument (`PRAGMA name(arg)`).
+    Pragma { name: String, arg: Option<String> },
+    // `Ignore` reads the column as `NULL`.
+    Read { table: String, column: String },
+    Select,
+    // `BEGIN`, `COMMIT` or `ROLLBACK`.
+    Transaction { op: String },
+    Update { table: String, column: String },
+    // `file` is the file name as written, before URI handling.
+    Attach { file: String },
+    Detach { db_name: String },
+    AlterTable { db_name: String, table: String },
+    Reindex { index: String },
+    Analyze { table: String },
+    CreateVtable { table: String, module: String },
+    DropVtable { table: String, module: String },
+    Function { name: String },
+    // `op` = `BEGIN`, `RELEASE` or `ROLLBACK`.
+    Savepoint { op: String, name: String },
+    Recursive,
+}
+
+
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Serialize, Deserialize)]
+#[derive(Clone)]
+pub struct AuthContext {
+    pub action: AuthAction,
+
+    // E.g. `main`, `temp`; `None` when the action is not on a database object.
+    pub db_name: Option<String>,
+
+    // The inner most trigger or view that caused the action; `None` for the top level statement.
+    pub accessor: Option<String>,
+}
+
+
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Serialize, Deserialize)]
+#[derive(Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum Authorization {
+    Allow,
+
+    // Fails the prepare with `SQLITE_AUTH`.
+    Deny,
+
+    // Depends on the action: `Read` returns `NULL` for the column, `Delete` only disables the truncate optimization.
+    // - For most other actions the statement is prepared, but the action is silently skipped.
+    Ignore,
+}
+
+
+// Called while statements are prepared, not run; statements already prepared are re-prepared when it is set.
+// @see https://www.sqlite.org/c3ref/set_authorizer.html
+// - "The authorizer callback must not do anything that will modify the database connection that invoked the authorizer callback."
+pub type AuthorizerFn = dyn FnMut(&AuthContext) -> Authorization;
+
+
+pub(crate) fn to_auth_code(a: Authorization) -> u32 {
+    match a {
+        Authorization::Allow => SQLITE_OK,
+        Authorization::Deny => SQLITE_DENY,
+        Authorization::Ignore => SQLITE_IGNORE,
+    }
+}
+
+// `None` for codes added after SQLite 3.28 (the statement is then allowed).
+pub(crate) fn get_action(code: u32, a: Option<String>, b: Option<String>) -> Option<AuthAction> {
+    let s = |x: Option<String>| x.unwrap_or_default();
+
+    let action = match code {
+        SQLITE_CREATE_INDEX => AuthAction::CreateIndex { index: s(a), table: s(b) },
+        SQLITE_CREATE_TABLE => AuthAction::CreateTable { table: s(a) },
+        SQLITE_CREATE_TEMP_INDEX => AuthAction::CreateTempIndex { index: s(a), table: s(b) },
+        SQLITE_CREATE_TEMP_TABLE => AuthAction::CreateTempTable { table: s(a) },
+        SQLITE_CREATE_TEMP_TRIGGER => AuthAction::CreateTempTrigger { trigger: s(a), table: s(b) },
+        SQLITE_CREATE_TEMP_VIEW => AuthAction::CreateTempView { view: s(a) },
+        SQLITE_CREATE_TRIGGER => AuthAction::CreateTrigger { trigger: s(a), table: s(b) },
+        SQLITE_CREATE_VIEW => AuthAction::CreateView { view: s(a) },
+        SQLITE_DELETE => AuthAction::Delete { table: s(a) },
+        SQLITE_DROP_INDEX => AuthAction::DropIndex { index: s(a), table: s(b) },
+        SQLITE_DROP_TABLE => AuthAction::DropTable { table: s(a) },
+        SQLITE_DROP_TEMP_INDEX => AuthAction::DropTempIndex { index: s(a), table: s(b) },
+        SQLITE_DROP_TEMP_TABLE => AuthAction::DropTempTable { table: s(a) },
+        SQLITE_DROP_TEMP_TRIGGER => AuthAction::DropTempTrigger { trigger: s(a), table: s(b) },
+        SQLITE_DROP_TEMP_VIEW => AuthAction::DropTempView { view: s(a) },
+        SQLITE_DROP_TRIGGER => AuthAction::DropTrigger { trigger: s(a), table: s(b) },
+        SQLITE_DROP_VIEW => AuthAction::DropView { view: s(a) },
+        SQLITE_INSERT => AuthAction::Insert { table: s(a) },
+        SQLITE_PRAGMA => AuthAction::Pragma { name: s(a), arg: b },
+        SQLITE_READ => AuthAction::Read { table: s(a), column: s(b) },
+        SQLITE_SELECT => AuthAction::Select,
+        SQLITE_TRANSACTION => AuthAction::Transaction { op: s(a) },
+        SQLITE_UPDATE => AuthAction::Update { table: s(a), column: s(b) },
+        SQLITE_ATTACH => AuthAction::Attach { file: s(a) },
+        SQLITE_DETACH => AuthAction::Detach { db_name: s(a) },
+        SQLITE_ALTER_TABLE => AuthAction::AlterTable { db_name: s(a), table: s(b) },
+        SQLITE_REINDEX => AuthAction::Reindex { index: s(a) },
+        SQLITE_ANALYZE => AuthAction::Analyze { table: s(a) },
+        SQLITE_CREATE_VTABLE => AuthAction::CreateVtable { table: s(a), module: s(b) },
+        SQLITE_DROP_VTABLE => AuthAction::DropVtable { table: s(a), module: s(b) },
+        // The first argument is always NULL.
+        SQLITE_FUNCTION => AuthAction::Function { name: s(b) },
+        SQLITE_SAVEPOINT => AuthAction::Savepoint { op: s(a), name: s(b) },
+        SQLITE_RECURSIVE => AuthAction::Recursive,
+        _ => return None
+    };
+
+    Some(action)
+}
+
+
+// True if the error was caused by an authorizer returning `Deny`.
+// Note: Denied functions fail with `SQLITE_ERROR` ("not authorized to use function: x"), all other actions with `SQLITE_AUTH`.
+pub fn is_not_authorized(rs: &ReturnStatus) -> bool {
+    match rs.primary.id {
+        PrimaryRC::SQLITE_AUTH => true,
+        PrimaryRC::SQLITE_ERROR => rs.err_msg.as_ref().map_or(false, |m| m.starts_with("not authorized")),
+        _ => false
+    }
+}
@@ -0,0 +1,155 @@
+use std::thread;
+use std::time::{Duration, Instant};
+use std::os::raw::c_int;
+
+use serde::{Deserialize, Serialize};
+
+use crate::cffi::{
+    // Objects
+    sqlite3_backup,
+
+    // Functions
+    sqlite3_backup_init,
+    sqlite3_backup_step,
+    sqlite3_backup_finish,
+    sqlite3_backup_remaining,
+    sqlite3_backup_pagecount,
+    sqlite3_extended_errcode,
+};
+
+use crate::fns::to_cstr;
+use crate::db::DbHandle;
+use crate::errmap::PrimaryRC;
+use crate::err::{
+    ReturnStatus,
+    to_return_status_cint,
+    to_return_status_cint_db_err,
+};
+
+
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Serialize, Deserialize)]
+#[derive(Clone)]
+pub struct BackupProgress {
+    // Pages still to be copied, and total pages in the source (as of the last `step`).
+    pub remaining: u32,
+    pub page_count: u32,
+    pub is_done: bool,
+}
+
+
+// Online backup: copies the source database page by page while other connections keep using it.
+// @see https://www.sqlite.org/backup.html
+// @see https://www.sqlite.org/c3ref/backup_finish.html
+//
+// - Each `step` holds a read lock on the source and a write lock on the destination only for its duration.
+// - If the source is written to by a *different* connection between steps, the backup restarts from the first page.
+//      - Writes via the `src` connection are applied to the destination without restarting.
+// - The destination is overwritten (including its page size).
+//
+// Lifetimes: references both connections; neither can be closed while the backup exists.
+#[derive(Debug)]
+pub struct Backup<'a> {
+    src: &'a DbHandle,
+    dst: &'a DbHandle,
+    backup: *mut sqlite3_backup,
+}
+
+
+impl<'a> Backup<'a> {
+    // Copies "main" of `src` into "main" of `dst`.
+    pub fn new(src: &'a DbHandle, dst: &'a DbHandle) -> Result<Backup<'a>, ReturnStatus> {
+        let main_c = to_cstr("main");
+
+        let backup = unsafe {
+            sqlite3_backup_init(dst.db, main_c.as_ptr(), src.db, main_c.as_ptr())
+        };
+
+        // "If an error occurs within sqlite3_backup_init(D,N,S,M), then NULL is returned and an error code and error message are stored in the destination database connection D."
+        // E.g. `dst` has an open read transaction.
+        if backup.is_null() {
+            let code = unsafe { sqlite3_extended_errcode(dst.db) };
+            to_return_status_cint_db_err(&code, dst.db)?;
+            unreachable!("sqlite3_backup_init returned NULL without an error code.");
+        }
+
+        Ok(
+            Backup {
+                src,
+                dst,
+                backup,
+            }
+        )
+    }
+
+    // Copies up to `num_pages` pages (`-1` = all remaining pages).
+    // - `SQLITE_BUSY` and `SQLITE_LOCKED` errors can be retried by calling `step` again later.
+    pub fn step(&self, num_pages: i32) -> Result<BackupProgress, ReturnStatus> {
+        let r = unsafe { sqlite3_backup_step(self.backup, num_pages as c_int) };
+        let status = to_return_status_cint_db_err(&r, self.dst.db)?;
+
+        let mut p = self.progress();
+        p.is_done = status.primary.id == PrimaryRC::SQLITE_DONE;
+        Ok(p)
+    }
+
+    pub fn progress(&self) -> BackupProgress {
+        unsafe {
+            BackupProgress {
+                remaining: sqlite3_backup_remaining(self.backup) as u32,
+                page_count: sqlite3_backup_pagecount(self.backup) as u32,
+                is_done: false,
+            }
+        }
+    }
+
+    // Steps until done, calling `progress_fn` after every step.
+    // - `pause` = time to sleep between steps (and before retrying a `SQLITE_BUSY`/`SQLITE_LOCKED` step), allowing other connections to get locks.
+    // - `max_busy` = how long steps can keep failing with `SQLITE_BUSY`/`SQLITE_LOCKED` before that error is returned (E.g. another process holds a lock).
+    pub fn run<F>(&self, pages_per_step: i32, pause: Duration, max_busy: Duration, mut progress_fn: F) -> Result<(), ReturnStatus>
+        where F: FnMut(&BackupProgress) {
+        let mut busy_since: Option<Instant> = None;
+
+        loop {
+            match self.step(pages_per_step) {
+                Ok(p) => {
+                    busy_since = None;
+                    progress_fn(&p);
+
+                    if p.is_done {
+                        return Ok(());
+                    }
+                }
+                Err(rs) => {
+                    match rs.primary.id {
+                        PrimaryRC::SQLITE_BUSY | PrimaryRC::SQLITE_LOCKED => {
+                            let since = *busy_since.get_or_insert_with(Instant::now);
+                            if since.elapsed() >= max_busy {
+                                return Err(rs);
+                            }
+                        }
+                        _ => return Err(rs)
+                    }
+                }
+            }
+
+            thread::sleep(pause);
+        }
+    }
+}
+
+
+impl Drop for Backup<'_> {
+    // "The sqlite3_backup_finish() interfaces releases all resources associated with the sqlite3_backup object."
+    // - Called before completion = the destination is left unchanged.
+    fn drop(&mut self) {
+        let r = unsafe { sqlite3_backup_finish(self.backup) };
+        let finish = to_return_status_cint(&r);
+
+        // Returns the error of the last `step` (if any).
+        if !finish.is_ok {
+            dbg!(finish);
+        }
+    }
+}
@@ -0,0 +1,222 @@
+use std::io;
+use std::ptr;
+use std::os::raw::{
+    c_int,
+    c_void,
+};
+
+use crate::cffi::{
+    // Objects
+    sqlite3_blob,
+
+    // Functions
+    sqlite3_blob_open,
+    sqlite3_blob_reopen,
+    sqlite3_blob_close,
+    sqlite3_blob_bytes,
+    sqlite3_blob_read,
+    sqlite3_blob_write,
+};
+
+use crate::fns::to_cstr;
+use crate::db::DbHandle;
+use crate::errmap::PrimaryRC;
+use crate::err::{
+    ReturnStatus,
+    to_return_status_cint,
+    to_return_status_cint_db_err,
+};
+
+
+// Incremental I/O for a single BLOB cell; avoids copying the whole value into a `Val::Blob`.
+// @see https://www.sqlite.org/c3ref/blob_open.html
+//
+// - The size of a BLOB cannot be changed; use `zeroblob(N)` in an INSERT/UPDATE to reserve space, then write into it.
+// - "If the row that a BLOB handle points to is modified by an UPDATE, DELETE, or by ON CONFLICT side-effects then the BLOB handle is marked as expired"
+//      - Reads/writes on an expired handle return `SQLITE_ABORT`.
+// - Drop the handle before `COMMIT`; an open write handle counts as an active statement.
+//
+// Lifetimes: like `StmtHandle`, the handle references the `DbHandle` so it cannot outlive the connection.
+#[derive(Debug)]
+pub struct BlobHandle<'a> {
+    db: &'a DbHandle,
+    blob: *mut sqlite3_blob,
+    pub is_write: bool,
+
+    // Position used by the `io::Read`, `io::Write` and `io::Seek` impls.
+    pos: u32,
+}
+
+
+impl<'a> BlobHandle<'a> {
+    // `db_name` = "main", "temp" or an `ATTACH` name.
+    pub fn open(db: &'a DbHandle, db_name: &str, table: &str, column: &str, rowid: i64, is_write: bool) -> Result<BlobHandle<'a>, ReturnStatus> {
+        let db_c = to_cstr(db_name);
+        let table_c = to_cstr(table);
+        let column_c = to_cstr(column);
+
+        let mut blob: *mut sqlite3_blob = ptr::null_mut();
+
+        let r = unsafe {
+            sqlite3_blob_open(
+                db.db,
+                db_c.as_ptr(),
+                table_c.as_ptr(),
+                column_c.as_ptr(),
+                rowid,
+                is_write as c_int,
+                &mut blob,
+            )
+        };
+
+        // On error `blob` is set to null.
+        // E.g. SQLITE_ERROR "no such rowid: 1", "cannot open value of type integer".
+        to_return_status_cint_db_err(&r, db.db)?;
+
+        Ok(
+            BlobHandle {
+                db,
+                blob,
+                is_write,
+                pos: 0,
+            }
+        )
+    }
+
+    // Points the handle at the same column of a different row (faster than closing and opening).
+    // - "If an error occurs, the blob handle is set to expired".
+    pub fn reopen(&mut self, rowid: i64) -> Result<(), ReturnStatus> {
+        let r = unsafe { sqlite3_blob_reopen(self.blob, rowid) };
+        to_return_status_cint_db_err(&r, self.db.db)?;
+
+        self.pos = 0;
+        Ok(())
+    }
+
+    // Size in bytes.
+    pub fn len(&self) -> u32 {
+        unsafe { sqlite3_blob_bytes(self.blob) as u32 }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // Reads `len` bytes starting at `offset`.
+    // - `offset + len` past the end of the blob returns `SQLITE_ERROR` (no partial reads).
+    //      - Checked before the buffer is allocated, as `len` may come from a client.
+    pub fn read_at(&self, offset: u32, len: u32) -> Result<Vec<u8>, ReturnStatus> {
+        if offset as u64 + len as u64 > self.len() as u64 {
+            let mut rs: ReturnStatus = PrimaryRC::SQLITE_ERROR.into();
+            rs.err_msg = Some(format!("read of {} bytes at offset {} is past the end of the blob ({} bytes)", len, offset, self.len()));
+            return Err(rs);
+        }
+
+        let mut buf = vec![0; len as usize];
+        self.read_into(offset, &mut buf)?;
+        Ok(buf)
+    }
+
+    pub fn read_into(&self, offset: u32, buf: &mut [u8]) -> Result<(), ReturnStatus> {
+        let r = unsafe {
+            sqlite3_blob_read(
+                self.blob,
+                buf.as_mut_ptr() as *mut c_void,
+                buf.len() as c_int,
+                offset as c_int,
+            )
+        };
+
+        to_return_status_cint_db_err(&r, self.db.db)?;
+        Ok(())
+    }
+
+    // Writes `data` starting at `offset`.
+    // - `offset + data.len()` past the end of the blob returns `SQLITE_ERROR`.
+    // - Handles opened with `is_write=false` return `SQLITE_READONLY`.
+    pub fn write_at(&self, offset: u32, data: &[u8]) -> Result<(), ReturnStatus> {
+        let r = unsafe {
+            sqlite3_blob_write(
+                self.blob,
+                data.as_ptr() as *const c_void,
+                data.len() as c_int,
+                offset as c_int,
+            )
+        };
+
+        to_return_status_cint_db_err(&r, self.db.db)?;
+        Ok(())
+    }
+}
+
+
+impl Drop for BlobHandle<'_> {
+    // @see https://www.sqlite.org/c3ref/blob_close.html
+    // - "The BLOB handle is closed unconditionally. Even if this routine returns an error code, the handle is still closed."
+    fn drop(&mut self) {
+        let r = unsafe { sqlite3_blob_close(self.blob) };
+        let close = to_return_status_cint(&r);
+
+        if !close.is_ok {
+            dbg!(close);
+        }
+    }
+}
+
+
+fn to_io_err(rs: ReturnStatus) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, format!("{:?}", rs))
+}
+
+// Streaming access, E.g. `io::copy(&mut file, &mut blob)`.
+// - Reads stop at the end of the blob; writes past the end return an error as the blob cannot grow.
+impl io::Read for BlobHandle<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let remaining = self.len().saturating_sub(self.pos) as usize;
+        let n = remaining.min(buf.len());
+
+        if n == 0 {
+            return Ok(0);
+        }
+
+        self.read_into(self.pos, &mut buf[..n]).map_err(to_io_err)?;
+        self.pos += n as u32;
+        Ok(n)
+    }
+}
+
+impl io::Write for BlobHandle<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let remaining = self.len().saturating_sub(self.pos) as usize;
+        let n = remaining.min(buf.len());
+
+        if n == 0 && !buf.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::WriteZero, "Blob size cannot be increased."));
+        }
+
+        self.write_at(self.pos, &buf[..n]).map_err(to_io_err)?;
+        self.pos += n as u32;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl io::Seek for BlobHandle<'_> {
+    fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+        let new = match pos {
+            io::SeekFrom::Start(n) => n as i64,
+            io::SeekFrom::End(n) => self.len() as i64 + n,
+            io::SeekFrom::Current(n) => self.pos as i64 + n,
+        };
+
+        if new < 0 || new > self.len() as i64 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "Seek position is outside of the blob."));
+        }
+
+        self.pos = new as u32;
+        Ok(self.pos as u64)
+    }
+}
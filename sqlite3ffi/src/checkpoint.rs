@@ -0,0 +1,97 @@
+use std::ptr;
+use std::os::raw::c_int;
+
+use serde::{Deserialize, Serialize};
+
+use crate::cffi::{
+    // Functions
+    sqlite3_wal_checkpoint_v2,
+
+    // Constants
+    SQLITE_CHECKPOINT_PASSIVE,
+    SQLITE_CHECKPOINT_FULL,
+    SQLITE_CHECKPOINT_RESTART,
+    SQLITE_CHECKPOINT_TRUNCATE,
+};
+
+use crate::fns::to_cstr;
+use crate::db::DbHandle;
+use crate::errmap::PrimaryRC;
+use crate::err::{
+    ReturnStatus,
+    to_return_status_cint_db_err,
+};
+
+
+// @see https://www.sqlite.org/c3ref/wal_checkpoint_v2.html
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Serialize, Deserialize)]
+#[derive(Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckpointMode {
+    // Checkpoint as many frames as possible without waiting for readers or writers (the same as SQLites auto checkpoint).
+    Passive,
+    // Waits (with the busy handler) for the writer and for readers of older snapshots, then checkpoints every frame.
+    Full,
+    // `Full`, then also waits for every reader to finish with the WAL, so the next writer restarts it from the beginning.
+    Restart,
+    // `Restart`, then truncates the WAL file to zero bytes.
+    Truncate,
+}
+
+impl Default for CheckpointMode {
+    fn default() -> Self {
+        CheckpointMode::Passive
+    }
+}
+
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Serialize, Deserialize)]
+#[derive(Clone)]
+pub struct Checkpoint {
+    // Frames in the WAL, and how many of them are now in the database file. `-1` when the file is not in WAL mode.
+    pub wal_frames: i32,
+    pub checkpointed_frames: i32,
+
+    // `true` when a `Full`/`Restart`/`Truncate` could not get the locks it waits for (`SQLITE_BUSY`); the frame counts are still set.
+    pub is_busy: bool,
+}
+
+impl Checkpoint {
+    // `false` when a reader (or the writer) prevented some frames from being checkpointed.
+    pub fn is_complete(&self) -> bool {
+        !self.is_busy && self.checkpointed_frames >= self.wal_frames
+    }
+}
+
+
+fn to_c_mode(mode: &CheckpointMode) -> c_int {
+    let m = match mode {
+        CheckpointMode::Passive => SQLITE_CHECKPOINT_PASSIVE,
+        CheckpointMode::Full => SQLITE_CHECKPOINT_FULL,
+        CheckpointMode::Restart => SQLITE_CHECKPOINT_RESTART,
+        CheckpointMode::Truncate => SQLITE_CHECKPOINT_TRUNCATE,
+    };
+
+    m as c_int
+}
+
+// Note: Fails with `SQLITE_LOCKED` when called inside a transaction on the same connection.
+pub fn checkpoint(db: &DbHandle, db_name: Option<&str>, mode: CheckpointMode) -> Result<Checkpoint, ReturnStatus> {
+    let db_c = db_name.map(to_cstr);
+    let db_ptr = db_c.as_ref().map_or(ptr::null(), |d| d.as_ptr());
+    let mut wal_frames: c_int = 0;
+    let mut checkpointed_frames: c_int = 0;
+
+    let r = unsafe { sqlite3_wal_checkpoint_v2(db.db, db_ptr, to_c_mode(&mode), &mut wal_frames, &mut checkpointed_frames) };
+
+    let is_busy = match to_return_status_cint_db_err(&r, db.db) {
+        Ok(_) => false,
+        Err(ref rs) if rs.primary.id == PrimaryRC::SQLITE_BUSY => true,
+        Err(rs) => return Err(rs)
+    };
+
+    Ok(Checkpoint { wal_frames, checkpointed_frames, is_busy })
+}
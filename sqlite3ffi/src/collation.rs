@@ -0,0 +1,171 @@
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::iter::Peekable;
+use std::str::Chars;
+use std::slice;
+use std::os::raw::{
+    c_int,
+    c_void,
+};
+
+use crate::cffi::{
+    // Functions
+    sqlite3_create_collation_v2,
+
+    // Constants
+    SQLITE_UTF8,
+};
+
+use crate::fns::to_cstr;
+use crate::db::DbHandle;
+use crate::function::catch_panic;
+use crate::err::{
+    ReturnStatus,
+    to_return_status_cint_db_err,
+};
+
+
+// Used with `ORDER BY name COLLATE <name>`, or `name TEXT COLLATE <name>` in a `CREATE TABLE`.
+// @see https://www.sqlite.org/c3ref/create_collation.html
+// @see https://www.sqlite.org/datatype3.html#collation
+//
+// - Must be consistent: "If A==B then B==A", "If A<B then B>A" etc., as indexes are ordered with it.
+//      - An index created with a collation cannot be used (or is corrupt) on connections where the collation is missing or different.
+pub type CollationFn = dyn Fn(&str, &str) -> Ordering + Send + Sync;
+
+
+// `file2` < `file10`.
+// Note: Not `NATURAL`, as that is a keyword (`NATURAL JOIN`).
+pub const NATSORT: &str = "NATSORT";
+
+// Like `NOCASE`, but for all of Unicode instead of only ASCII: `Ä` = `ä`.
+pub const CASEFOLD: &str = "CASEFOLD";
+
+
+pub fn create_collation(db: &DbHandle, name: &str, f: Box<CollationFn>) -> Result<(), ReturnStatus> {
+    let name_c = to_cstr(name);
+    let user_data = Box::into_raw(Box::new(f));
+
+    let r = unsafe {
+        sqlite3_create_collation_v2(
+            db.db,
+            name_c.as_ptr(),
+            SQLITE_UTF8 as c_int,
+            user_data as *mut c_void,
+            Some(call_compare),
+            Some(destroy_compare),
+        )
+    };
+
+    let res = to_return_status_cint_db_err(&r, db.db);
+
+    // "The xDestroy callback is not called if the sqlite3_create_collation_v2() function fails."
+    if res.is_err() {
+        unsafe { destroy_compare(user_data as *mut c_void) };
+    }
+
+    res?;
+    Ok(())
+}
+
+// Registers `NATSORT` and `CASEFOLD`.
+pub fn create_builtin_collations(db: &DbHandle) -> Result<(), ReturnStatus> {
+    create_collation(db, NATSORT, Box::new(natural_cmp))?;
+    create_collation(db, CASEFOLD, Box::new(casefold_cmp))?;
+    Ok(())
+}
+
+
+unsafe extern "C" fn call_compare(user_data: *mut c_void, n_a: c_int, a: *const c_void, n_b: c_int, b: *const c_void) -> c_int {
+    let f = &*(user_data as *const Box<CollationFn>);
+
+    let a = to_str(a, n_a);
+    let b = to_str(b, n_b);
+
+    // Collations cannot return an error; a panic compares as equal.
+    match catch_panic(|| Ok(f(&a, &b))) {
+        Ok(Ordering::Less) => -1,
+        Ok(Ordering::Greater) => 1,
+        Ok(Ordering::Equal) | Err(_) => 0,
+    }
+}
+
+// Note: Not null terminated; SQLite converts the text to UTF-8 (`SQLITE_UTF8`) before calling.
+unsafe fn to_str<'a>(p: *const c_void, n: c_int) -> Cow<'a, str> {
+    if p.is_null() || n <= 0 {
+        return Cow::Borrowed("");
+    }
+
+    String::from_utf8_lossy(slice::from_raw_parts(p as *const u8, n as usize))
+}
+
+unsafe extern "C" fn destroy_compare(user_data: *mut c_void) {
+    drop(Box::from_raw(user_data as *mut Box<CollationFn>));
+}
+
+
+// Compares runs of ASCII digits by their numeric value, everything else char by char.
+// - Leading zeros are ignored unless the values are otherwise equal (`01` > `1`).
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a = a.chars().peekable();
+    let mut b = b.chars().peekable();
+
+    loop {
+        match (a.peek(), b.peek()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let x_num = take_digits(&mut a);
+                let y_num = take_digits(&mut b);
+
+                let o = cmp_digits(&x_num, &y_num);
+                if o != Ordering::Equal {
+                    return o;
+                }
+            }
+            (Some(x), Some(y)) => {
+                let o = x.cmp(y);
+                if o != Ordering::Equal {
+                    return o;
+                }
+
+                a.next();
+                b.next();
+            }
+        }
+    }
+}
+
+fn take_digits(c: &mut Peekable<Chars>) -> String {
+    let mut s = String::new();
+
+    while let Some(d) = c.peek() {
+        if !d.is_ascii_digit() {
+            break;
+        }
+        s.push(*d);
+        c.next();
+    }
+
+    s
+}
+
+// Compares without parsing, so any number of digits can be compared.
+fn cmp_digits(a: &str, b: &str) -> Ordering {
+    let a_trim = a.trim_start_matches('0');
+    let b_trim = b.trim_start_matches('0');
+
+    a_trim.len().cmp(&b_trim.len())
+        .then_with(|| a_trim.cmp(b_trim))
+        .then_with(|| a.len().cmp(&b.len()))
+}
+
+
+// @todo/low Full Unicode case folding (E.g. `ß` = `ss`) needs the `CaseFolding.txt` tables; this uses Rusts `to_lowercase`.
+pub fn casefold_cmp(a: &str, b: &str) -> Ordering {
+    let a = a.chars().flat_map(char::to_lowercase);
+    let b = b.chars().flat_map(char::to_lowercase);
+
+    a.cmp(b)
+}
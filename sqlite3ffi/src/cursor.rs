@@ -0,0 +1,256 @@
+use std::marker::PhantomData;
+use std::os::raw::c_int;
+use std::slice;
+use std::str;
+
+use crate::cffi::{
+    // Objects
+    sqlite3_stmt,
+
+    // Functions
+    sqlite3_step,
+    sqlite3_reset,
+    sqlite3_column_type,
+    sqlite3_column_text,
+    sqlite3_column_blob,
+    sqlite3_column_bytes,
+
+    // Constants
+    SQLITE_TEXT,
+    SQLITE_BLOB,
+};
+
+use crate::errmap::PrimaryRC;
+use crate::err::{
+    ReturnStatus,
+    to_return_status_cint_db_err,
+};
+
+use crate::trace::reset_stmt_status;
+use crate::stmt::{
+    StmtHandle,
+    ColName,
+    Val,
+    get_headers,
+    get_num_cols,
+    get_row,
+    get_cell,
+    get_changes,
+    is_iud,
+};
+
+
+// Steps a `stmt` one row at a time instead of collecting every row into an `RSet`.
+// - Memory use is bounded by a single row.
+// - The caller can stop at any point; dropping the cursor resets the `stmt` (releasing any read lock held by an unfinished `SELECT`).
+//
+// `Iterator` returns owned rows (`Vec<Val>`), `next_view` returns a view that borrows SQLite's memory for the current row.
+#[derive(Debug)]
+pub struct Cursor<'a> {
+    s: &'a StmtHandle<'a>,
+    is_iud: bool,
+    is_done: bool,
+    num_rows: u32,
+    rows_changed: Option<u64>,
+}
+
+
+impl<'a> Cursor<'a> {
+    pub fn new(s: &'a StmtHandle<'a>) -> Cursor<'a> {
+        reset_stmt_status(s.stmt);
+
+        Cursor {
+            s,
+            is_iud: is_iud(s.stmt),
+            is_done: false,
+            num_rows: 0,
+            rows_changed: None,
+        }
+    }
+
+    // Available before the first row; `storage_class` is always `None` as the rows are not collected.
+    pub fn col_names(&self) -> Vec<ColName> {
+        get_headers(self.s.stmt)
+    }
+
+    pub fn is_iud(&self) -> bool {
+        self.is_iud
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.is_done
+    }
+
+    // Number of rows returned so far.
+    pub fn num_rows(&self) -> u32 {
+        self.num_rows
+    }
+
+    // `Some` once the cursor is done and the query was an (INSERT|UPDATE|DELETE).
+    pub fn rows_changed(&self) -> Option<u64> {
+        self.rows_changed
+    }
+
+    // Like `next`, but the row is read directly from the `stmt` instead of being copied.
+    // - The view is valid until the next call to `next_view`/`next` (enforced by the `&mut self` borrow).
+    pub fn next_view(&mut self) -> Option<Result<RowView, ReturnStatus>> {
+        match self.step()? {
+            Ok(()) => Some(Ok(RowView {
+                stmt: self.s.stmt,
+                _cursor: PhantomData,
+            })),
+            Err(e) => Some(Err(e))
+        }
+    }
+
+    // `None` = no more rows.
+    fn step(&mut self) -> Option<Result<(), ReturnStatus>> {
+        if self.is_done {
+            return None;
+        }
+
+        let StmtHandle { db, stmt, .. } = self.s;
+
+        let r = unsafe { sqlite3_step(*stmt) };
+        let step = to_return_status_cint_db_err(&r, db.db);
+
+        match step {
+            Err(status) => {
+                self.is_done = true;
+
+                match status.primary.id {
+                    PrimaryRC::SQLITE_BUSY => {
+                        // @todo/important If "COMMIT" or outside of transaction, retry `step()`, else "ROLLBACK"
+                        // @see https://www.sqlite.org/c3ref/step.html
+//                        dbg!(("sqlite3_step: ", &status));
+
+                        Some(Err(status))
+                    }
+                    _ => {
+                        // SQLITE_ERROR etc
+
+                        // Errors: (FULL, IOERR, BUSY, NOMEM)
+                        // @todo/low "It is recommended that applications respond to the errors listed above by explicitly issuing a ROLLBACK command"
+                        // @see https://www.sqlite.org/lang_transaction.html
+                        Some(Err(status))
+                    }
+                }
+            }
+            Ok(status) => {
+                match status.primary.id {
+                    PrimaryRC::SQLITE_ROW => {
+                        self.num_rows += 1;
+                        Some(Ok(()))
+                    }
+
+                    PrimaryRC::SQLITE_DONE => {
+                        self.is_done = true;
+
+                        // `sqlite3_reset`
+                        // - Resets the VM so the `sqlite3_step` can be called on the `stmt` again.
+                        // - Will return last error code (but `sqlite3_step` error should be the same).
+                        // - Keeps bindings (call `sqlite3_clear_bindings` to clear them).
+                        // - `sqlite3_step` automatically calls this.
+                        unsafe {
+                            sqlite3_reset(*stmt);
+                        }
+
+                        // @see https://www.sqlite.org/pragma.html#pragma_data_version (determine if file has changed).
+                        if self.is_iud {
+                            self.rows_changed = Some(get_changes(db.db));
+                        }
+
+                        None
+                    }
+                    _ => {
+                        // Only SQLITE_OK remaining. Create enum of non-error values so compiler can verify `match`?
+                        panic!("sqlite3_step Ok values are not exhaustive. {:?}", status)
+                    }
+                }
+            }
+        }
+    }
+}
+
+
+impl Iterator for Cursor<'_> {
+    type Item = Result<Vec<Val>, ReturnStatus>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.step()? {
+            Ok(()) => Some(Ok(get_row(self.s.stmt))),
+            Err(e) => Some(Err(e))
+        }
+    }
+}
+
+
+impl Drop for Cursor<'_> {
+    fn drop(&mut self) {
+        // Stopped early: reset so the `stmt` does not keep its read transaction open.
+        if !self.is_done {
+            unsafe {
+                sqlite3_reset(self.s.stmt);
+            }
+        }
+    }
+}
+
+
+// A borrowed view of the current row.
+// @see https://www.sqlite.org/c3ref/column_blob.html
+// - "pointers returned are valid until a type conversion occurs as described above, or until sqlite3_step() or sqlite3_reset() or sqlite3_finalize() is called."
+#[derive(Debug)]
+pub struct RowView<'c> {
+    stmt: *mut sqlite3_stmt,
+    _cursor: PhantomData<&'c mut Cursor<'c>>,
+}
+
+impl RowView<'_> {
+    pub fn num_cols(&self) -> u32 {
+        get_num_cols(self.stmt)
+    }
+
+    // Copies a single cell.
+    pub fn get(&self, i: u32) -> Val {
+        get_cell(self.stmt, i)
+    }
+
+    // `None` if the cell is not `TEXT` (no type conversion is done so other borrowed pointers stay valid).
+    pub fn get_text(&self, i: u32) -> Option<&str> {
+        let n_c = i as c_int;
+
+        unsafe {
+            if sqlite3_column_type(self.stmt, n_c) as u32 != SQLITE_TEXT {
+                return None;
+            }
+
+            let ptr = sqlite3_column_text(self.stmt, n_c);
+            let len = sqlite3_column_bytes(self.stmt, n_c) as usize;
+            str::from_utf8(slice::from_raw_parts(ptr, len)).ok()
+        }
+    }
+
+    // `None` if the cell is not a `BLOB`.
+    pub fn get_blob(&self, i: u32) -> Option<&[u8]> {
+        let n_c = i as c_int;
+
+        unsafe {
+            if sqlite3_column_type(self.stmt, n_c) as u32 != SQLITE_BLOB {
+                return None;
+            }
+
+            let len = sqlite3_column_bytes(self.stmt, n_c) as usize;
+            if len == 0 {
+                return Some(&[]);
+            }
+
+            let ptr = sqlite3_column_blob(self.stmt, n_c) as *const u8;
+            Some(slice::from_raw_parts(ptr, len))
+        }
+    }
+
+    pub fn to_vec(&self) -> Vec<Val> {
+        get_row(self.stmt)
+    }
+}
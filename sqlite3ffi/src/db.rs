@@ -1,6 +1,10 @@
 extern crate chrono;
 
 use std::mem::MaybeUninit;
+use std::cell::{RefCell, Cell};
+use std::rc::Rc;
+use std::ptr;
+use std::time::Duration;
 
 use std::sync::Once;
 
@@ -11,10 +15,13 @@ use crate::cffi::{
     sqlite3,
 
     // Functions
-    sqlite3_open,
+    sqlite3_open_v2,
     sqlite3_close,
     sqlite3_extended_result_codes,
     sqlite3_threadsafe,
+    sqlite3_db_filename,
+    sqlite3_get_autocommit,
+    sqlite3_busy_timeout,
 };
 
 
@@ -22,6 +29,59 @@ use crate::errmap;
 use crate::err;
 use crate::fns;
 use crate::config;
+use crate::stmt_cache;
+use crate::script;
+use crate::open;
+use crate::blob::BlobHandle;
+use crate::function::{
+    ScalarFn,
+    ScalarFunction,
+};
+use crate::collation;
+use crate::hooks;
+use crate::hooks::{
+    Hooks,
+    UpdateHookFn,
+    CommitHookFn,
+    RollbackHookFn,
+    PreUpdateHookFn,
+    ProgressFn,
+    WalHookFn,
+};
+use crate::trace::TraceFn;
+use crate::auth::{
+    AuthorizerFn,
+    AuthContext,
+};
+use crate::limits;
+use crate::db_config;
+use crate::extension;
+use crate::db_config::{
+    DbConfig,
+    DbConfigFlag,
+};
+use crate::limits::{
+    Limit,
+    Limits,
+};
+use crate::collation::CollationFn;
+use crate::session;
+use crate::session::{
+    Session,
+    ConflictFn,
+};
+use crate::checkpoint;
+use crate::checkpoint::{
+    Checkpoint,
+    CheckpointMode,
+};
+use crate::serialize;
+use crate::explain;
+use crate::explain::QueryPlan;
+use crate::aggregate::{
+    AggregateFactory,
+    AggregateFunction,
+};
 
 
 use config::set_error_callback;
@@ -31,13 +91,22 @@ use errmap::PrimaryRow;
 use err::{
     ReturnStatus,
     to_return_status_cint,
+    to_return_status_cint_err,
 };
 use crate::stmt::{
     StmtHandle,
     RSet,
     KeyVal,
     IndexVal,
-    ErrorBind
+    ErrorBind,
+    finalize,
+};
+use script::ScriptError;
+use open::OpenFlags;
+use stmt_cache::{
+    StmtCache,
+    StmtCacheStats,
+    DEFAULT_STMT_CACHE_CAPACITY,
 };
 
 
@@ -45,6 +114,18 @@ use crate::stmt::{
 pub struct DbHandle {
     pub db: *mut sqlite3,
     pub file: String,
+
+    // `RefCell` as statements are taken from and returned to the cache via `&DbHandle` (queries do not need a `&mut`).
+    pub(crate) stmt_cache: RefCell<StmtCache>,
+
+    // Update/commit/rollback/pre-update hook closures; dropped after the connection closes.
+    pub(crate) hooks: RefCell<Hooks>,
+
+    // Set `RSet.timing` for each run.
+    pub(crate) rset_timing: Cell<bool>,
+
+    // Number of `Session`s that exist on this connection; they use the pre-update hook.
+    pub(crate) sessions: Rc<Cell<usize>>,
 }
 
 impl Drop for DbHandle {
@@ -56,6 +137,11 @@ impl Drop for DbHandle {
         let db = self.db;
         assert!(!(db.is_null()), "Drop called but db handle was null.");
 
+        // Cached statements are outstanding resources that would cause `sqlite3_close` to fail with `SQLITE_BUSY`.
+        for c in self.stmt_cache.borrow_mut().clear() {
+            finalize(c.stmt);
+        }
+
         let r = unsafe { sqlite3_close(db) };
         let close = to_return_status_cint(&r);
 
@@ -96,11 +182,17 @@ impl DbHandle {
     @todo/low Map SQL read/writes to Rusts ownership semantics. (You need a mut to write, and a & to read). Mutate "changes" on write?
     */
     pub fn new(file: String) -> Result<DbHandle, ReturnStatus> {
+        DbHandle::new_with_flags(file, &OpenFlags::default())
+    }
+
+    // @see https://www.sqlite.org/c3ref/open.html
+    pub fn new_with_flags(file: String, flags: &OpenFlags) -> Result<DbHandle, ReturnStatus> {
         // @todo/low Call on init, not on first db handle request.
         set_error_cb_once();
         assert_is_threadsafe();
 
         let c_file = to_cstr(file.as_str());
+        let c_vfs = flags.vfs.as_ref().map(|v| to_cstr(v));
 
         // Question: *mut *mut = coerce a pointer to a pointer? How does a pointer to a pointer FFI work?
         // Question: Should the `MaybeUninit` var be held onto after the memory is initialised?
@@ -109,7 +201,12 @@ impl DbHandle {
 
 
         let r = unsafe {
-            sqlite3_open(c_file.as_ptr(), &mut db)
+            sqlite3_open_v2(
+                c_file.as_ptr(),
+                &mut db,
+                flags.to_c_flags() as ::std::os::raw::c_int,
+                c_vfs.as_ref().map_or(ptr::null(), |v| v.as_ptr()),
+            )
         };
 
 
@@ -125,6 +222,10 @@ impl DbHandle {
             DbHandle {
                 file,
                 db,
+                stmt_cache: RefCell::new(StmtCache::new(DEFAULT_STMT_CACHE_CAPACITY)),
+                hooks: RefCell::new(Hooks::default()),
+                rset_timing: Cell::new(false),
+                sessions: Rc::new(Cell::new(0)),
             }
         )
     }
@@ -135,24 +236,224 @@ impl DbHandle {
         StmtHandle::new(&self, q)
     }
 
+    // Uses the statement cache; the `stmt` is kept prepared for the next query with the same SQL string.
+    pub fn new_stmt_cached(&self, q: &str) -> Result<StmtHandle, ReturnStatus> {
+        StmtHandle::new_cached(&self, q)
+    }
+
     // @todo/low only allow access to run, ignore statements?
     pub fn run(&self, q: &str) -> Result<RSet, ReturnStatus> {
-        let s = &self.new_stmt(q)?;
+        let s = &self.new_stmt_cached(q)?;
         s.run()
     }
 
     pub fn run_kv(&self, q: &str, kv: &KeyVal) -> Result<RSet, BindRunError> {
-        let s = &self.new_stmt(&q)?;
+        let s = &self.new_stmt_cached(&q)?;
         &s.bind_kv(&kv)?;
         Ok(s.run()?)
     }
 
     pub fn run_index(&self, q: &str, vals: &IndexVal) -> Result<RSet, BindRunError> {
-        let s = &self.new_stmt(q)?;
+        let s = &self.new_stmt_cached(q)?;
         &s.bind_index(&vals)?;
         Ok(s.run()?)
     }
 
+    // Runs every statement in `q` (`run` only runs the first statement of a string).
+    pub fn exec_script(&self, q: &str) -> Result<Vec<RSet>, ScriptError> {
+        script::exec_script(&self, q)
+    }
+
+    pub fn exec_script_each(&self, q: &str, before_each: &mut dyn FnMut(usize)) -> Result<Vec<RSet>, ScriptError> {
+        script::exec_script_each(&self, q, before_each)
+    }
+
+    pub fn is_read_only_script(&self, q: &str) -> Result<bool, ScriptError> {
+        script::is_read_only_script(&self, q)
+    }
+
+    // Incremental read/write of a single BLOB cell (`is_write=false` = read only).
+    pub fn open_blob(&self, table: &str, column: &str, rowid: i64, is_write: bool) -> Result<BlobHandle, ReturnStatus> {
+        BlobHandle::open(&self, "main", table, column, rowid, is_write)
+    }
+
+    // Makes a Rust closure callable from SQL on this connection, E.g. `SELECT name(?)`.
+    // - Panics inside `f` are caught and returned to SQL as an error.
+    pub fn create_scalar_function(&self, name: &str, n_args: i32, deterministic: bool, f: Box<ScalarFn>) -> Result<(), ReturnStatus> {
+        ScalarFunction::new(name, n_args, deterministic, f).install(&self)
+    }
+
+    // `new_state` is called once per group; see `aggregate::Aggregate`.
+    pub fn create_aggregate_function(&self, name: &str, n_args: i32, deterministic: bool, is_window: bool, new_state: Box<AggregateFactory>) -> Result<(), ReturnStatus> {
+        AggregateFunction::new(name, n_args, deterministic, is_window, new_state).install(&self)
+    }
+
+    // Makes `COLLATE name` available on this connection.
+    pub fn create_collation(&self, name: &str, f: Box<CollationFn>) -> Result<(), ReturnStatus> {
+        collation::create_collation(&self, name, f)
+    }
+
+    // `None` removes the hook. See `hooks` for when each is called.
+    pub fn set_update_hook(&self, f: Option<Box<UpdateHookFn>>) {
+        hooks::set_update_hook(&self, f)
+    }
+
+    pub fn set_commit_hook(&self, f: Option<Box<CommitHookFn>>) {
+        hooks::set_commit_hook(&self, f)
+    }
+
+    pub fn set_rollback_hook(&self, f: Option<Box<RollbackHookFn>>) {
+        hooks::set_rollback_hook(&self, f)
+    }
+
+    // Fails with `SQLITE_MISUSE` while a `Session` exists, as replacing the hook would silently stop it recording.
+    pub fn set_preupdate_hook(&self, f: Option<Box<PreUpdateHookFn>>) -> Result<(), ReturnStatus> {
+        hooks::set_preupdate_hook(&self, f)
+    }
+
+    pub fn set_wal_hook(&self, f: Option<Box<WalHookFn>>) {
+        hooks::set_wal_hook(&self, f)
+    }
+
+    // Copies WAL frames back into the database file. `db_name` = `None` for every attached database.
+    pub fn checkpoint(&self, db_name: Option<&str>, mode: CheckpointMode) -> Result<Checkpoint, ReturnStatus> {
+        checkpoint::checkpoint(&self, db_name, mode)
+    }
+
+    pub fn explain_query_plan(&self, q: &str) -> Result<QueryPlan, ReturnStatus> {
+        explain::explain_query_plan(&self, q)
+    }
+
+    // Returns the previous value.
+    pub fn set_limit(&self, l: Limit, v: i32) -> i32 {
+        limits::set_limit(&self, l, v)
+    }
+
+    pub fn get_limit(&self, l: Limit) -> i32 {
+        limits::get_limit(&self, l)
+    }
+
+    pub fn set_limits(&self, l: &Limits) {
+        limits::set_limits(&self, l)
+    }
+
+    pub fn get_limits(&self) -> Limits {
+        limits::get_limits(&self)
+    }
+
+    // Returns the value after the call.
+    pub fn set_db_config_flag(&self, f: DbConfigFlag, v: bool) -> Result<bool, ReturnStatus> {
+        db_config::set_db_config_flag(&self, f, v)
+    }
+
+    pub fn get_db_config_flag(&self, f: DbConfigFlag) -> Result<bool, ReturnStatus> {
+        db_config::get_db_config_flag(&self, f)
+    }
+
+    pub fn set_db_config(&self, c: &DbConfig) -> Result<(), ReturnStatus> {
+        db_config::set_db_config(&self, c)
+    }
+
+    pub fn get_db_config(&self) -> Result<DbConfig, ReturnStatus> {
+        db_config::get_db_config(&self)
+    }
+
+    // `path` must be in the process wide allowlist (see `extension::set_extension_allowlist`).
+    pub fn load_extension(&self, path: &str, entry_point: Option<&str>) -> Result<(), ReturnStatus> {
+        extension::load_extension(&self, path, entry_point)
+    }
+
+    // `db_name` = `None` for "main".
+    pub fn serialize(&self, db_name: Option<&str>) -> Result<Vec<u8>, ReturnStatus> {
+        serialize::serialize(&self, db_name)
+    }
+
+    // Note: `db_name` becomes an in-memory database; the file is not written to.
+    pub fn deserialize(&self, db_name: Option<&str>, data: &[u8]) -> Result<(), ReturnStatus> {
+        serialize::deserialize(&self, db_name, data)
+    }
+
+    // Reports each statement run (including those inside scripts and triggers); `None` removes it.
+    pub fn set_trace(&self, f: Option<Box<TraceFn>>) {
+        hooks::set_trace(&self, f)
+    }
+
+    // Allows or denies each action of a statement while it is prepared; denied statements fail with `SQLITE_AUTH`.
+    pub fn set_authorizer(&self, f: Option<Box<AuthorizerFn>>) {
+        hooks::set_authorizer(&self, f)
+    }
+
+    // The action that made the last prepare fail (see `auth::is_not_authorized`); cleared by this call.
+    pub fn take_auth_denied(&self) -> Option<AuthContext> {
+        hooks::take_auth_denied(&self)
+    }
+
+    pub fn set_rset_timing(&self, on: bool) {
+        self.rset_timing.set(on)
+    }
+
+    pub fn set_progress_handler(&self, n_ops: i32, f: Option<Box<ProgressFn>>) {
+        hooks::set_progress_handler(&self, n_ops, f)
+    }
+
+    // `(f(), is_timeout)`; statements still running after `timeout` fail with `SQLITE_INTERRUPT`.
+    pub fn with_timeout<T, F>(&self, timeout: Duration, f: F) -> (T, bool) where F: FnOnce() -> T {
+        hooks::with_timeout(&self, timeout, f)
+    }
+
+    // `false` while a transaction is open. E.g. `true` after SQLite rolls back a transaction on its own (`SQLITE_FULL`, an interrupted write).
+    pub fn is_autocommit(&self) -> bool {
+        unsafe { sqlite3_get_autocommit(self.db) != 0 }
+    }
+
+    // Statements that need a lock another connection holds wait up to `t` for it, instead of returning `SQLITE_BUSY` at once. `0` = do not wait (the default).
+    // @see https://www.sqlite.org/c3ref/busy_timeout.html
+    // - With `SQLITE_ENABLE_SETLK_TIMEOUT` the unix VFS retries the `fcntl` lock every 1ms, so a lock released by another process is taken almost at once.
+    // Note: Blocks the calling thread.
+    // Note: In 3.28 a lock held by another connection in the *same process* is not an `fcntl` lock, so it returns `SQLITE_BUSY` without waiting.
+    pub fn set_busy_timeout(&self, t: Duration) -> Result<(), ReturnStatus> {
+        let r = unsafe { sqlite3_busy_timeout(self.db, t.as_millis() as ::std::os::raw::c_int) };
+        to_return_status_cint_err(&r)?;
+        Ok(())
+    }
+
+    pub fn create_session(&self) -> Result<Session, ReturnStatus> {
+        Session::new(&self, "main")
+    }
+
+    pub fn apply_changeset(&self, changeset: &[u8], on_conflict: &mut ConflictFn) -> Result<(), ReturnStatus> {
+        session::apply_changeset(&self, changeset, on_conflict)
+    }
+
+    // Max number of prepared statements to keep. `0` disables the cache.
+    // - Statements that no longer fit are finalized.
+    pub fn set_stmt_cache_capacity(&self, capacity: usize) {
+        for c in self.stmt_cache.borrow_mut().set_capacity(capacity) {
+            finalize(c.stmt);
+        }
+    }
+
+    pub fn get_stmt_cache_stats(&self) -> StmtCacheStats {
+        self.stmt_cache.borrow().stats()
+    }
+
+    // The absolute path SQLite opened (after URI parsing); `None` for in-memory and temp databases.
+    // - `db_name` = `None` for "main".
+    // @see https://www.sqlite.org/c3ref/db_filename.html
+    pub fn get_db_filename(&self, db_name: Option<&str>) -> Option<String> {
+        let db_c = to_cstr(db_name.unwrap_or("main"));
+
+        unsafe {
+            let f = sqlite3_db_filename(self.db, db_c.as_ptr());
+            if f.is_null() {
+                return None;
+            }
+
+            let s = std::ffi::CStr::from_ptr(f).to_string_lossy().into_owned();
+            if s.is_empty() { None } else { Some(s) }
+        }
+    }
+
 }
 
 static START: Once = Once::new();
@@ -0,0 +1,195 @@
+use std::os::raw::c_int;
+
+use serde::{Deserialize, Serialize};
+
+use crate::cffi::{
+    // Functions
+    sqlite3_db_config,
+
+    // Constants
+    SQLITE_DBCONFIG_ENABLE_FKEY,
+    SQLITE_DBCONFIG_ENABLE_TRIGGER,
+    SQLITE_DBCONFIG_ENABLE_FTS3_TOKENIZER,
+    SQLITE_DBCONFIG_ENABLE_LOAD_EXTENSION,
+    SQLITE_DBCONFIG_NO_CKPT_ON_CLOSE,
+    SQLITE_DBCONFIG_ENABLE_QPSG,
+    SQLITE_DBCONFIG_TRIGGER_EQP,
+    SQLITE_DBCONFIG_DEFENSIVE,
+    SQLITE_DBCONFIG_WRITABLE_SCHEMA,
+};
+
+use crate::db::DbHandle;
+use crate::err::{
+    ReturnStatus,
+    to_return_status_cint_db_err,
+};
+
+
+// Per connection on/off options.
+// @see https://www.sqlite.org/c3ref/c_dbconfig_defensive.html
+// @todo/low This crate bundles 3.28; add `LEGACY_ALTER_TABLE`, `DQS_DML`, `DQS_DDL` (3.29) and `TRUSTED_SCHEMA` (3.31, stops triggers and views calling functions with side effects) when it is upgraded.
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Serialize, Deserialize)]
+#[derive(Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum DbConfigFlag {
+    // Foreign key constraints; same as `PRAGMA foreign_keys`.
+    EnableFkey,
+    EnableTrigger,
+    // The two argument version of `fts3_tokenizer()`, which can call a function pointer read from SQL.
+    EnableFts3Tokenizer,
+    // The C API only; `load_extension()` in SQL is controlled by `sqlite3_enable_load_extension`.
+    EnableLoadExtension,
+    NoCkptOnClose,
+    // Query planner stability guarantee.
+    EnableQpsg,
+    // `EXPLAIN QUERY PLAN` includes the triggers a statement runs.
+    TriggerEqp,
+    // Disables SQL that can corrupt the file (E.g. `PRAGMA writable_schema`, `PRAGMA journal_mode=OFF`, writes to shadow tables).
+    Defensive,
+    WritableSchema,
+}
+
+impl DbConfigFlag {
+    fn get_code(&self) -> c_int {
+        let c = match self {
+            DbConfigFlag::EnableFkey => SQLITE_DBCONFIG_ENABLE_FKEY,
+            DbConfigFlag::EnableTrigger => SQLITE_DBCONFIG_ENABLE_TRIGGER,
+            DbConfigFlag::EnableFts3Tokenizer => SQLITE_DBCONFIG_ENABLE_FTS3_TOKENIZER,
+            DbConfigFlag::EnableLoadExtension => SQLITE_DBCONFIG_ENABLE_LOAD_EXTENSION,
+            DbConfigFlag::NoCkptOnClose => SQLITE_DBCONFIG_NO_CKPT_ON_CLOSE,
+            DbConfigFlag::EnableQpsg => SQLITE_DBCONFIG_ENABLE_QPSG,
+            DbConfigFlag::TriggerEqp => SQLITE_DBCONFIG_TRIGGER_EQP,
+            DbConfigFlag::Defensive => SQLITE_DBCONFIG_DEFENSIVE,
+            DbConfigFlag::WritableSchema => SQLITE_DBCONFIG_WRITABLE_SCHEMA,
+        };
+        c as c_int
+    }
+}
+
+const ALL_FLAGS: [DbConfigFlag; 9] = [
+    DbConfigFlag::EnableFkey,
+    DbConfigFlag::EnableTrigger,
+    DbConfigFlag::EnableFts3Tokenizer,
+    DbConfigFlag::EnableLoadExtension,
+    DbConfigFlag::NoCkptOnClose,
+    DbConfigFlag::EnableQpsg,
+    DbConfigFlag::TriggerEqp,
+    DbConfigFlag::Defensive,
+    DbConfigFlag::WritableSchema,
+];
+
+
+// A connection config; `None` leaves the connections current value.
+// E.g. `DbConfig::secure().set(DbConfigFlag::EnableFkey, true)`
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Serialize, Deserialize)]
+#[derive(Clone)]
+#[derive(Default)]
+#[serde(default)]
+pub struct DbConfig {
+    pub enable_fkey: Option<bool>,
+    pub enable_trigger: Option<bool>,
+    pub enable_fts3_tokenizer: Option<bool>,
+    pub enable_load_extension: Option<bool>,
+    pub no_ckpt_on_close: Option<bool>,
+    pub enable_qpsg: Option<bool>,
+    pub trigger_eqp: Option<bool>,
+    pub defensive: Option<bool>,
+    pub writable_schema: Option<bool>,
+}
+
+impl DbConfig {
+    // Turns off features that let SQL corrupt the file or run native code; leaves options that change query results (E.g. `enable_fkey`) as they are.
+    // @see https://www.sqlite.org/security.html
+    pub fn secure() -> DbConfig {
+        DbConfig {
+            defensive: Some(true),
+            writable_schema: Some(false),
+            enable_load_extension: Some(false),
+            enable_fts3_tokenizer: Some(false),
+            ..Default::default()
+        }
+    }
+
+    pub fn set(mut self, f: DbConfigFlag, v: bool) -> DbConfig {
+        *self.get_mut(f) = Some(v);
+        self
+    }
+
+    pub fn get(&self, f: DbConfigFlag) -> Option<bool> {
+        match f {
+            DbConfigFlag::EnableFkey => self.enable_fkey,
+            DbConfigFlag::EnableTrigger => self.enable_trigger,
+            DbConfigFlag::EnableFts3Tokenizer => self.enable_fts3_tokenizer,
+            DbConfigFlag::EnableLoadExtension => self.enable_load_extension,
+            DbConfigFlag::NoCkptOnClose => self.no_ckpt_on_close,
+            DbConfigFlag::EnableQpsg => self.enable_qpsg,
+            DbConfigFlag::TriggerEqp => self.trigger_eqp,
+            DbConfigFlag::Defensive => self.defensive,
+            DbConfigFlag::WritableSchema => self.writable_schema,
+        }
+    }
+
+    fn get_mut(&mut self, f: DbConfigFlag) -> &mut Option<bool> {
+        match f {
+            DbConfigFlag::EnableFkey => &mut self.enable_fkey,
+            DbConfigFlag::EnableTrigger => &mut self.enable_trigger,
+            DbConfigFlag::EnableFts3Tokenizer => &mut self.enable_fts3_tokenizer,
+            DbConfigFlag::EnableLoadExtension => &mut self.enable_load_extension,
+            DbConfigFlag::NoCkptOnClose => &mut self.no_ckpt_on_close,
+            DbConfigFlag::EnableQpsg => &mut self.enable_qpsg,
+            DbConfigFlag::TriggerEqp => &mut self.trigger_eqp,
+            DbConfigFlag::Defensive => &mut self.defensive,
+            DbConfigFlag::WritableSchema => &mut self.writable_schema,
+        }
+    }
+}
+
+
+// `v` = `None` only reads the value; returns the value after the call.
+fn db_config_flag(db: &DbHandle, f: DbConfigFlag, v: Option<bool>) -> Result<bool, ReturnStatus> {
+    let v_c: c_int = v.map_or(-1, |v| v as c_int);
+    let mut out: c_int = 0;
+
+    let r = unsafe { sqlite3_db_config(db.db, f.get_code(), v_c, &mut out as *mut c_int) };
+    to_return_status_cint_db_err(&r, db.db)?;
+
+    Ok(out != 0)
+}
+
+pub fn set_db_config_flag(db: &DbHandle, f: DbConfigFlag, v: bool) -> Result<bool, ReturnStatus> {
+    db_config_flag(db, f, Some(v))
+}
+
+pub fn get_db_config_flag(db: &DbHandle, f: DbConfigFlag) -> Result<bool, ReturnStatus> {
+    db_config_flag(db, f, None)
+}
+
+// Stops at the first flag that fails; the flags before it stay set.
+// - `sqlite3_db_config` only fails for an option this SQLite build does not know, so the error names the flag.
+pub fn set_db_config(db: &DbHandle, c: &DbConfig) -> Result<(), ReturnStatus> {
+    for f in ALL_FLAGS.iter() {
+        if let Some(v) = c.get(*f) {
+            set_db_config_flag(db, *f, v).map_err(|mut rs| {
+                rs.err_msg = Some(format!("could not set db config flag {:?}", f));
+                rs
+            })?;
+        }
+    }
+
+    Ok(())
+}
+
+// The effective config of the connection (all `Some`).
+pub fn get_db_config(db: &DbHandle) -> Result<DbConfig, ReturnStatus> {
+    let mut c = DbConfig::default();
+
+    for f in ALL_FLAGS.iter() {
+        *c.get_mut(*f) = Some(get_db_config_flag(db, *f)?);
+    }
+
+    Ok(c)
+}
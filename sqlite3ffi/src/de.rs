@@ -0,0 +1,238 @@
+use std::fmt;
+use std::marker::PhantomData;
+use std::vec::IntoIter;
+
+use serde::{Deserialize, Serialize};
+use serde::de::{
+    self,
+    DeserializeOwned,
+    DeserializeSeed,
+    IntoDeserializer,
+    MapAccess,
+    SeqAccess,
+    Visitor,
+};
+use serde::de::value::SeqDeserializer;
+use serde::forward_to_deserialize_any;
+
+use crate::stmt::{
+    RSet,
+    ColName,
+    Val,
+};
+
+
+// Reads result rows into Rust types with `serde`.
+// - Structs and maps are keyed by column name (`col AS x` = `x`); columns without a field are ignored, `Option` fields for missing columns are `None`.
+// - Tuples and `Vec`s read the columns in order.
+//
+// Conversions:
+// - `bool` from `INTEGER` (0 = false).
+// - `Vec<u8>` from `BLOB`.
+// - Unit enum variants from `TEXT`.
+// - `chrono::DateTime`/`NaiveDateTime` from RFC 3339 `TEXT`; use `#[serde(with = "chrono::serde::ts_seconds")]` for unix time `INTEGER`s.
+// - `uuid::Uuid` from hyphenated `TEXT`, or a 16 byte `BLOB`.
+
+
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Serialize, Deserialize)]
+#[derive(Clone)]
+pub struct DeError {
+    pub msg: String,
+}
+
+impl fmt::Display for DeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.msg)
+    }
+}
+
+impl std::error::Error for DeError {}
+
+impl de::Error for DeError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        DeError { msg: msg.to_string() }
+    }
+}
+
+
+pub fn from_row<'a, T: Deserialize<'a>>(col_names: &'a [ColName], row: &'a [Val]) -> Result<T, DeError> {
+    T::deserialize(RowDeserializer { col_names, row })
+}
+
+
+// Owns the rows of an `RSet`, yielding one `T` per row.
+pub struct RowIter<T> {
+    col_names: Vec<ColName>,
+    rows: IntoIter<Vec<Val>>,
+    _t: PhantomData<T>,
+}
+
+impl<T: DeserializeOwned> Iterator for RowIter<T> {
+    type Item = Result<T, DeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let row = self.rows.next()?;
+        Some(from_row(&self.col_names, &row))
+    }
+}
+
+impl RSet {
+    // E.g. `for p in rset.into_iter::<Person>() {}`
+    pub fn into_iter<T: DeserializeOwned>(self) -> RowIter<T> {
+        RowIter {
+            col_names: self.col_names,
+            rows: self.rows.data.into_iter(),
+            _t: PhantomData,
+        }
+    }
+}
+
+
+struct RowDeserializer<'a> {
+    col_names: &'a [ColName],
+    row: &'a [Val],
+}
+
+impl<'de> de::Deserializer<'de> for RowDeserializer<'de> {
+    type Error = DeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        visitor.visit_map(RowMap { col_names: self.col_names, row: self.row, i: 0 })
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(self, _name: &'static str, _fields: &'static [&'static str], visitor: V) -> Result<V::Value, DeError> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        visitor.visit_seq(RowSeq { row: self.row, i: 0 })
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, DeError> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(self, _name: &'static str, _len: usize, visitor: V) -> Result<V::Value, DeError> {
+        self.deserialize_seq(visitor)
+    }
+
+    // E.g. `struct Row(Person)`.
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, DeError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct enum identifier ignored_any
+    }
+}
+
+
+struct RowMap<'a> {
+    col_names: &'a [ColName],
+    row: &'a [Val],
+    i: usize,
+}
+
+impl<'de> MapAccess<'de> for RowMap<'de> {
+    type Error = DeError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, DeError> {
+        match self.col_names.get(self.i) {
+            Some(c) if self.i < self.row.len() => seed.deserialize(c.name.as_str().into_deserializer()).map(Some),
+            _ => Ok(None)
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, DeError> {
+        let v = &self.row[self.i];
+        self.i += 1;
+        seed.deserialize(ValDeserializer(v))
+    }
+}
+
+
+struct RowSeq<'a> {
+    row: &'a [Val],
+    i: usize,
+}
+
+impl<'de> SeqAccess<'de> for RowSeq<'de> {
+    type Error = DeError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>, DeError> {
+        match self.row.get(self.i) {
+            Some(v) => {
+                self.i += 1;
+                seed.deserialize(ValDeserializer(v)).map(Some)
+            }
+            None => Ok(None)
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.row.len() - self.i)
+    }
+}
+
+
+pub struct ValDeserializer<'a>(pub &'a Val);
+
+impl<'de> de::Deserializer<'de> for ValDeserializer<'de> {
+    type Error = DeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        match self.0 {
+            Val::I64(i) => visitor.visit_i64(*i),
+            Val::F64(f) => visitor.visit_f64(*f),
+            Val::String(s) => visitor.visit_borrowed_str(s),
+            Val::Blob(b) => visitor.visit_borrowed_bytes(b),
+            Val::Null => visitor.visit_unit(),
+        }
+    }
+
+    // SQLite has no boolean type, `TRUE`/`FALSE` are stored as 1/0.
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        match self.0 {
+            Val::I64(i) => visitor.visit_bool(*i != 0),
+            _ => self.deserialize_any(visitor)
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        match self.0 {
+            Val::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self)
+        }
+    }
+
+    // `Vec<u8>` is read as a sequence.
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        match self.0 {
+            Val::Blob(b) => visitor.visit_seq(SeqDeserializer::new(b.iter().cloned())),
+            _ => self.deserialize_any(visitor)
+        }
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(self, _name: &'static str, _variants: &'static [&'static str], visitor: V) -> Result<V::Value, DeError> {
+        match self.0 {
+            Val::String(s) => visitor.visit_enum(s.as_str().into_deserializer()),
+            _ => self.deserialize_any(visitor)
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, DeError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    forward_to_deserialize_any! {
+        i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct tuple tuple_struct map struct identifier ignored_any
+    }
+}
@@ -0,0 +1,86 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::db::DbHandle;
+use crate::stmt::Val;
+use crate::err::ReturnStatus;
+
+
+// `EXPLAIN QUERY PLAN` rows as a tree.
+// @see https://www.sqlite.org/eqp.html
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Serialize, Deserialize)]
+#[derive(Clone)]
+pub struct QueryPlan {
+    // Top level nodes (`parent` = 0).
+    pub nodes: Vec<PlanNode>,
+
+    // Any node in the tree `is_full_scan`.
+    pub has_full_scan: bool,
+}
+
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Serialize, Deserialize)]
+#[derive(Clone)]
+pub struct PlanNode {
+    pub id: i64,
+    pub parent: i64,
+
+    // E.g. `SEARCH TABLE t1 USING INDEX i1 (a=?)`
+    pub detail: String,
+
+    // Reads every row of a table without an index (`SCAN TABLE t1`).
+    // - Not set for `SCAN TABLE t1 USING (COVERING) INDEX i1`, subqueries or virtual tables.
+    pub is_full_scan: bool,
+
+    pub children: Vec<PlanNode>,
+}
+
+
+// Note: The query is prepared, not run; placeholders do not need to be bound.
+pub fn explain_query_plan(db: &DbHandle, q: &str) -> Result<QueryPlan, ReturnStatus> {
+    let rset = db.run(&format!("EXPLAIN QUERY PLAN {}", q))?;
+
+    // Columns: id, parent, notused, detail.
+    let mut children: HashMap<i64, Vec<(i64, String)>> = HashMap::new();
+    for row in rset.rows.data.iter() {
+        match (&row[0], &row[1], &row[3]) {
+            (Val::I64(id), Val::I64(parent), Val::String(detail)) => {
+                children.entry(*parent).or_insert_with(Vec::new).push((*id, detail.clone()));
+            }
+            _ => unreachable!("EXPLAIN QUERY PLAN rows are (int, int, int, text).")
+        }
+    }
+
+    let nodes = to_nodes(&children, 0);
+    let has_full_scan = nodes.iter().any(has_full_scan);
+
+    Ok(QueryPlan { nodes, has_full_scan })
+}
+
+fn to_nodes(children: &HashMap<i64, Vec<(i64, String)>>, parent: i64) -> Vec<PlanNode> {
+    let rows = match children.get(&parent) {
+        Some(rows) => rows,
+        None => return vec![]
+    };
+
+    rows.iter().map(|(id, detail)| PlanNode {
+        id: *id,
+        parent,
+        detail: detail.clone(),
+        is_full_scan: is_full_scan(detail),
+        children: to_nodes(children, *id),
+    }).collect()
+}
+
+// Note: Matches the 3.28 format; SQLite 3.36+ omits `TABLE` (`SCAN t1`).
+fn is_full_scan(detail: &str) -> bool {
+    detail.starts_with("SCAN TABLE ") && !detail.contains(" INDEX")
+}
+
+fn has_full_scan(n: &PlanNode) -> bool {
+    n.is_full_scan || n.children.iter().any(has_full_scan)
+}
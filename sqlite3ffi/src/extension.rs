@@ -0,0 +1,111 @@
+use std::ptr;
+use std::fs;
+use std::io;
+use std::ffi::CStr;
+use std::path::PathBuf;
+use std::sync::RwLock;
+use std::os::raw::{
+    c_void,
+    c_char,
+};
+
+use crate::cffi::{
+    // Functions
+    sqlite3_load_extension,
+    sqlite3_enable_load_extension,
+    sqlite3_free,
+};
+
+use crate::fns::to_cstr;
+use crate::db::DbHandle;
+use crate::db_config::DbConfigFlag;
+use crate::errmap::PrimaryRC;
+use crate::err::{
+    ReturnStatus,
+    to_return_status_cint,
+};
+
+
+// Canonical paths; empty = no extension can be loaded.
+static ALLOWLIST: RwLock<Vec<PathBuf>> = RwLock::new(vec![]);
+
+// Process wide; replaces the allowlist. Fails if a path does not exist.
+// - Paths are compared after resolving symlinks and `..`, so each file must exist when this is called.
+pub fn set_extension_allowlist(paths: &[String]) -> Result<(), io::Error> {
+    let canonical = paths.iter().map(fs::canonicalize).collect::<Result<Vec<PathBuf>, io::Error>>()?;
+    *ALLOWLIST.write().unwrap() = canonical;
+    Ok(())
+}
+
+// Like `set_extension_allowlist`, but keeps the paths already allowed (E.g. by another runtime in the process).
+pub fn allow_extensions(paths: &[String]) -> Result<(), io::Error> {
+    let canonical = paths.iter().map(fs::canonicalize).collect::<Result<Vec<PathBuf>, io::Error>>()?;
+
+    let mut allowed = ALLOWLIST.write().unwrap();
+    for p in canonical {
+        if !allowed.contains(&p) {
+            allowed.push(p);
+        }
+    }
+
+    Ok(())
+}
+
+fn get_allowed(path: &str) -> Option<PathBuf> {
+    let p = fs::canonicalize(path).ok()?;
+
+    if ALLOWLIST.read().unwrap().contains(&p) {
+        return Some(p);
+    }
+
+    None
+}
+
+
+// Loads a native extension into this connection; `entry_point` = `None` uses SQLites default (`sqlite3_extension_init` or one derived from the file name).
+// @see https://www.sqlite.org/c3ref/load_extension.html
+// - Paths not in the allowlist fail with `SQLITE_AUTH`.
+// - Loading is only enabled for the duration of the call, so SQL cannot call `load_extension()`.
+//      - `DbConfigFlag::EnableLoadExtension` (the C API only) is restored afterwards.
+pub fn load_extension(db: &DbHandle, path: &str, entry_point: Option<&str>) -> Result<(), ReturnStatus> {
+    let allowed = match get_allowed(path) {
+        Some(p) => p,
+        None => {
+            let mut rs: ReturnStatus = PrimaryRC::SQLITE_AUTH.into();
+            rs.err_msg = Some(format!("not authorized to load extension: {}", path));
+            return Err(rs);
+        }
+    };
+
+    // Note: The canonical path is passed, so SQLite does not try other file names (E.g. with a `.so` suffix).
+    let path_c = to_cstr(&allowed.to_string_lossy());
+    let entry_c = entry_point.map(to_cstr);
+    let entry_ptr = entry_c.as_ref().map_or(ptr::null(), |e| e.as_ptr());
+
+    let prev_c_api = db.get_db_config_flag(DbConfigFlag::EnableLoadExtension)?;
+    let mut err_msg: *mut c_char = ptr::null_mut();
+
+    let r = unsafe {
+        sqlite3_enable_load_extension(db.db, 1);
+        let r = sqlite3_load_extension(db.db, path_c.as_ptr(), entry_ptr, &mut err_msg);
+        sqlite3_enable_load_extension(db.db, 0);
+        r
+    };
+
+    if prev_c_api {
+        db.set_db_config_flag(DbConfigFlag::EnableLoadExtension, true)?;
+    }
+
+    let mut rs = to_return_status_cint(&r);
+    if rs.is_err {
+        if !err_msg.is_null() {
+            unsafe {
+                rs.err_msg = Some(CStr::from_ptr(err_msg).to_string_lossy().into_owned());
+                sqlite3_free(err_msg as *mut c_void);
+            }
+        }
+        return Err(rs);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,239 @@
+use std::sync::Arc;
+use std::panic::{
+    catch_unwind,
+    AssertUnwindSafe,
+};
+use std::os::raw::{
+    c_int,
+    c_char,
+    c_void,
+};
+
+use crate::cffi::{
+    // Objects
+    sqlite3_context,
+    sqlite3_value,
+
+    // Types
+    sqlite3_int64,
+
+    // Functions
+    sqlite3_create_function_v2,
+    sqlite3_user_data,
+    sqlite3_value_type,
+    sqlite3_value_int64,
+    sqlite3_value_double,
+    sqlite3_value_text,
+    sqlite3_value_blob,
+    sqlite3_value_bytes,
+    sqlite3_result_int64,
+    sqlite3_result_double,
+    sqlite3_result_text,
+    sqlite3_result_blob,
+    sqlite3_result_null,
+    sqlite3_result_error,
+
+    // Constants
+    SQLITE_UTF8,
+    SQLITE_DETERMINISTIC,
+
+    // Functions that return constants.
+    SQLITE_TRANSIENT,
+};
+
+use crate::fns::to_cstr;
+use crate::db::DbHandle;
+use crate::stmt::{
+    Val,
+    Type,
+    get_cell_type,
+    to_owned_vec,
+};
+use crate::err::{
+    ReturnStatus,
+    to_return_status_cint_db_err,
+};
+
+
+// `Err(msg)` is returned to SQL as an error (the statement fails with `SQLITE_ERROR` and `msg`).
+// - `Send + Sync` as the same function can be installed on connections owned by different threads.
+pub type ScalarFn = dyn Fn(&[Val]) -> Result<Val, String> + Send + Sync;
+
+
+// A function definition that can be installed on many connections.
+// @see https://www.sqlite.org/c3ref/create_function.html
+#[derive(Clone)]
+pub struct ScalarFunction {
+    pub name: String,
+
+    // `-1` = any number of args.
+    pub n_args: i32,
+
+    // "A deterministic function always gives the same answer when its inputs are the same."
+    // - Allows SQLite to factor calls out of loops, and use the function in indexes and `CHECK` constraints.
+    pub deterministic: bool,
+
+    pub f: Arc<ScalarFn>,
+}
+
+impl std::fmt::Debug for ScalarFunction {
+    fn fmt(&self, fm: &mut std::fmt::Formatter) -> std::fmt::Result {
+        fm.debug_struct("ScalarFunction")
+            .field("name", &self.name)
+            .field("n_args", &self.n_args)
+            .field("deterministic", &self.deterministic)
+            .finish()
+    }
+}
+
+impl ScalarFunction {
+    pub fn new(name: &str, n_args: i32, deterministic: bool, f: Box<ScalarFn>) -> ScalarFunction {
+        ScalarFunction {
+            name: name.to_string(),
+            n_args,
+            deterministic,
+            f: Arc::from(f),
+        }
+    }
+
+    // Note: Registering a function with the same name and `n_args` replaces the previous one.
+    pub fn install(&self, db: &DbHandle) -> Result<(), ReturnStatus> {
+        let name_c = to_cstr(&self.name);
+
+        // Owned by SQLite; freed by `destroy_scalar` when the function is replaced or the connection closes.
+        let user_data = Box::into_raw(Box::new(self.f.clone()));
+
+        let r = unsafe {
+            sqlite3_create_function_v2(
+                db.db,
+                name_c.as_ptr(),
+                self.n_args as c_int,
+                get_text_rep(self.deterministic),
+                user_data as *mut c_void,
+                Some(call_scalar),
+                None,
+                None,
+                Some(destroy_scalar),
+            )
+        };
+
+        // "The destructor is also invoked if the call to sqlite3_create_function_v2() fails."
+        to_return_status_cint_db_err(&r, db.db)?;
+        Ok(())
+    }
+}
+
+
+pub(crate) fn get_text_rep(deterministic: bool) -> c_int {
+    let mut flags = SQLITE_UTF8;
+
+    if deterministic {
+        flags |= SQLITE_DETERMINISTIC;
+    }
+
+    flags as c_int
+}
+
+
+unsafe extern "C" fn call_scalar(ctx: *mut sqlite3_context, argc: c_int, argv: *mut *mut sqlite3_value) {
+    let f = &*(sqlite3_user_data(ctx) as *const Arc<ScalarFn>);
+    let args = get_args(argc, argv);
+
+    let res = catch_panic(|| f(&args));
+    set_result(ctx, &res);
+}
+
+unsafe extern "C" fn destroy_scalar(user_data: *mut c_void) {
+    drop(Box::from_raw(user_data as *mut Arc<ScalarFn>));
+}
+
+
+// Unwinding across the FFI boundary is undefined behavior; a panic is returned to SQL as an error instead.
+pub(crate) fn catch_panic<T, F>(f: F) -> Result<T, String>
+    where F: FnOnce() -> Result<T, String> {
+    match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(r) => r,
+        Err(_) => Err("Rust function panicked.".to_string())
+    }
+}
+
+
+pub(crate) fn get_args(argc: c_int, argv: *mut *mut sqlite3_value) -> Vec<Val> {
+    (0..argc as isize).map(|i| get_value(unsafe { *argv.offset(i) })).collect()
+}
+
+// Like `stmt::get_cell`, but for a function argument (or any other protected `sqlite3_value`).
+// @see https://www.sqlite.org/c3ref/value_blob.html
+pub(crate) fn get_value(v: *mut sqlite3_value) -> Val {
+    let t = get_cell_type(unsafe {
+        sqlite3_value_type(v) as u32
+    });
+
+    match t {
+        Type::SQLITE_INTEGER => Val::I64(unsafe { sqlite3_value_int64(v) } as i64),
+        Type::SQLITE_FLOAT => Val::F64(unsafe { sqlite3_value_double(v) }),
+        Type::SQLITE_NULL => Val::Null,
+        Type::SQLITE_TEXT => {
+            // Note: Call `_text` before `_bytes` so the byte count is for the UTF-8 conversion.
+            let (ptr, num_bytes) = unsafe {
+                (
+                    sqlite3_value_text(v) as *const u8,
+                    sqlite3_value_bytes(v) as u32
+                )
+            };
+
+            // Null when SQLite could not allocate the UTF-8 conversion.
+            if ptr.is_null() {
+                return Val::String(String::new());
+            }
+
+            Val::String(String::from_utf8_lossy(&to_owned_vec(ptr, &num_bytes)).into_owned())
+        }
+        Type::SQLITE_BLOB => {
+            let (ptr, num_bytes) = unsafe {
+                (
+                    sqlite3_value_blob(v) as *const u8,
+                    sqlite3_value_bytes(v) as u32
+                )
+            };
+
+            // A zero length blob can be a null pointer.
+            if ptr.is_null() {
+                return Val::Blob(vec![]);
+            }
+
+            Val::Blob(to_owned_vec(ptr, &num_bytes))
+        }
+    }
+}
+
+
+pub(crate) fn set_result(ctx: *mut sqlite3_context, res: &Result<Val, String>) {
+    unsafe {
+        match res {
+            Ok(Val::I64(v)) => sqlite3_result_int64(ctx, *v as sqlite3_int64),
+            Ok(Val::F64(v)) => sqlite3_result_double(ctx, *v),
+            Ok(Val::String(v)) => {
+                sqlite3_result_text(
+                    ctx,
+                    v.as_ptr() as *const c_char,
+                    v.len() as c_int,
+                    SQLITE_TRANSIENT(),
+                )
+            }
+            Ok(Val::Null) => sqlite3_result_null(ctx),
+            Ok(Val::Blob(v)) => {
+                sqlite3_result_blob(
+                    ctx,
+                    v.as_ptr() as *const c_void,
+                    v.len() as c_int,
+                    SQLITE_TRANSIENT(),
+                )
+            }
+            Err(msg) => {
+                // SQLite copies the message.
+                sqlite3_result_error(ctx, msg.as_ptr() as *const c_char, msg.len() as c_int)
+            }
+        }
+    }
+}
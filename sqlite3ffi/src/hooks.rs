@@ -0,0 +1,490 @@
+use std::ptr;
+use std::rc::Rc;
+use std::cell::Cell;
+use std::time::{Duration, Instant};
+use std::ffi::CStr;
+use std::collections::HashMap;
+use std::os::raw::{
+    c_int,
+    c_uint,
+    c_char,
+    c_void,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::cffi::{
+    // Objects
+    sqlite3,
+    sqlite3_value,
+    sqlite3_stmt,
+
+    // Types
+    sqlite3_int64,
+
+    // Functions
+    sqlite3_update_hook,
+    sqlite3_commit_hook,
+    sqlite3_rollback_hook,
+    sqlite3_preupdate_hook,
+    sqlite3_preupdate_old,
+    sqlite3_preupdate_new,
+    sqlite3_preupdate_count,
+    sqlite3_preupdate_depth,
+    sqlite3_progress_handler,
+    sqlite3_wal_hook,
+    sqlite3_trace_v2,
+    sqlite3_set_authorizer,
+
+    // Constants
+    SQLITE_INSERT,
+    SQLITE_UPDATE,
+    SQLITE_DELETE,
+    SQLITE_TRACE_STMT,
+    SQLITE_TRACE_PROFILE,
+    SQLITE_TRACE_ROW,
+};
+
+use crate::db::DbHandle;
+use crate::stmt::Val;
+use crate::function::{
+    catch_panic,
+    get_value,
+};
+use crate::err::{
+    ReturnStatus,
+    to_return_status_cint,
+};
+use crate::errmap::PrimaryRC;
+use crate::trace::{
+    TraceEvent,
+    TraceFn,
+    get_profile,
+};
+use crate::auth::{
+    AuthContext,
+    AuthorizerFn,
+    Authorization,
+    get_action,
+    to_auth_code,
+};
+
+
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Serialize, Deserialize)]
+#[derive(Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeOp {
+    Insert,
+    Update,
+    Delete,
+}
+
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Serialize, Deserialize)]
+#[derive(Clone)]
+pub struct RowChange {
+    pub table: String,
+    pub op: ChangeOp,
+    pub rowid: i64,
+}
+
+
+// The full row before and after a change.
+// - Column values are in table column order (`PRAGMA table_info`), including an `INTEGER PRIMARY KEY` column (a rowid alias, so its value is the rowid).
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Serialize, Deserialize)]
+#[derive(Clone)]
+pub struct PreUpdate {
+    pub db_name: String,
+    pub table: String,
+    pub op: ChangeOp,
+
+    // Rowid before (update, delete) and after (insert, update).
+    // - Undefined for `WITHOUT ROWID` tables; use the primary key columns in `old`/`new` instead.
+    pub old_rowid: Option<i64>,
+    pub new_rowid: Option<i64>,
+
+    // `None` for inserts.
+    pub old: Option<Vec<Val>>,
+
+    // `None` for deletes.
+    pub new: Option<Vec<Val>>,
+
+    // `0` = direct change, `1` = change made by a trigger, `2` = by a trigger fired by a trigger etc.
+    pub depth: i32,
+}
+
+impl PreUpdate {
+    // Indexes of the columns whose value differs between `old` and `new` (updates only).
+    pub fn changed_columns(&self) -> Vec<usize> {
+        match (&self.old, &self.new) {
+            (Some(old), Some(new)) => {
+                old.iter().zip(new.iter()).enumerate()
+                    .filter(|(_, (a, b))| a != b)
+                    .map(|(i, _)| i)
+                    .collect()
+            }
+            _ => vec![]
+        }
+    }
+}
+
+
+// Called once per changed row, *during* the statement.
+// @see https://www.sqlite.org/c3ref/update_hook.html
+// - Not called for `WITHOUT ROWID` tables, internal tables (E.g. `sqlite_master`), or the `DELETE FROM t` truncate optimization.
+// - Rows changed by a statement or savepoint that is later rolled back are still reported; the caller must drop them (E.g. when the statement returns an error).
+// - "The update hook implementation must not do anything that will modify the database connection that invoked the update hook."
+pub type UpdateHookFn = dyn FnMut(&RowChange);
+
+// Called before a transaction commits; return `true` to turn the `COMMIT` into a `ROLLBACK`.
+// @see https://www.sqlite.org/c3ref/commit_hook.html
+pub type CommitHookFn = dyn FnMut() -> bool;
+
+// Called when a transaction is rolled back (explicitly, or automatically after an error).
+// - Not called when the connection closes with an open transaction.
+pub type RollbackHookFn = dyn FnMut();
+
+
+// Called before each row change, with the old and new values of every column.
+// @see https://www.sqlite.org/c3ref/preupdate_count.html
+// - Unlike the update hook, also called for `WITHOUT ROWID` tables.
+// - Requires the `SQLITE_ENABLE_PREUPDATE_HOOK` compile flag (disables the `DELETE FROM t` truncate optimization).
+// - A `session::Session` also uses this hook; only one can be set per connection.
+pub type PreUpdateHookFn = dyn FnMut(&PreUpdate);
+
+// Called periodically while a statement runs; return `true` to interrupt it (the statement returns `SQLITE_INTERRUPT`).
+// @see https://www.sqlite.org/c3ref/progress_handler.html
+// - "If the interrupted SQL statement is an INSERT, UPDATE, or DELETE that is inside an explicit transaction, then the entire transaction will be rolled back automatically."
+//      - An interrupted read only statement does not end the transaction.
+pub type ProgressFn = dyn FnMut() -> bool;
+
+// Called after each commit in WAL mode with `(db_name, wal_frames)`; `wal_frames` = frames (pages) in the WAL file, including ones already checkpointed.
+// @see https://www.sqlite.org/c3ref/wal_hook.html
+// - Replaces SQLites auto checkpoint (`sqlite3_wal_autocheckpoint`), which is implemented with this hook; nothing is checkpointed automatically while it is set.
+pub type WalHookFn = dyn FnMut(&str, i32);
+
+// Virtual machine instructions between progress handler calls; low enough to check a deadline every few ms.
+static PROGRESS_N_OPS: i32 = 1000;
+
+
+// Closures owned by the `DbHandle`; SQLite only holds pointers to them.
+// - Double boxed so the pointer passed to SQLite is thin and does not move.
+#[derive(Default)]
+pub(crate) struct Hooks {
+    update: Option<Box<Box<UpdateHookFn>>>,
+    commit: Option<Box<Box<CommitHookFn>>>,
+    rollback: Option<Box<Box<RollbackHookFn>>>,
+    preupdate: Option<Box<Box<PreUpdateHookFn>>>,
+    progress: Option<Box<Box<ProgressFn>>>,
+    wal: Option<Box<Box<WalHookFn>>>,
+    trace: Option<Box<Trace>>,
+    auth: Option<Box<Auth>>,
+}
+
+// `rows` = `SQLITE_TRACE_ROW` events per running `stmt`, reported (and removed) with its profile.
+struct Trace {
+    f: Box<TraceFn>,
+    rows: HashMap<usize, u64>,
+}
+
+// `last_denied` = the action that made the last prepare fail with `SQLITE_AUTH`.
+struct Auth {
+    f: Box<AuthorizerFn>,
+    last_denied: Option<AuthContext>,
+}
+
+impl std::fmt::Debug for Hooks {
+    fn fmt(&self, fm: &mut std::fmt::Formatter) -> std::fmt::Result {
+        fm.debug_struct("Hooks")
+            .field("update", &self.update.is_some())
+            .field("commit", &self.commit.is_some())
+            .field("rollback", &self.rollback.is_some())
+            .field("preupdate", &self.preupdate.is_some())
+            .field("progress", &self.progress.is_some())
+            .field("wal", &self.wal.is_some())
+            .field("trace", &self.trace.is_some())
+            .field("auth", &self.auth.is_some())
+            .finish()
+    }
+}
+
+
+fn to_user_data<T: ?Sized>(f: &mut Option<Box<Box<T>>>) -> *mut c_void {
+    match f {
+        Some(b) => &mut **b as *mut Box<T> as *mut c_void,
+        None => ptr::null_mut()
+    }
+}
+
+// `None` removes the hook.
+// Note: Must not be called from inside the hook being replaced.
+pub(crate) fn set_update_hook(db: &DbHandle, f: Option<Box<UpdateHookFn>>) {
+    let mut f = f.map(Box::new);
+    let cb = f.as_ref().map(|_| call_update as unsafe extern "C" fn(_, _, _, _, _));
+
+    unsafe { sqlite3_update_hook(db.db, cb, to_user_data(&mut f)) };
+
+    // The previous closure is dropped after SQLite stops referencing it.
+    db.hooks.borrow_mut().update = f;
+}
+
+pub(crate) fn set_commit_hook(db: &DbHandle, f: Option<Box<CommitHookFn>>) {
+    let mut f = f.map(Box::new);
+    let cb = f.as_ref().map(|_| call_commit as unsafe extern "C" fn(_) -> _);
+
+    unsafe { sqlite3_commit_hook(db.db, cb, to_user_data(&mut f)) };
+    db.hooks.borrow_mut().commit = f;
+}
+
+pub(crate) fn set_rollback_hook(db: &DbHandle, f: Option<Box<RollbackHookFn>>) {
+    let mut f = f.map(Box::new);
+    let cb = f.as_ref().map(|_| call_rollback as unsafe extern "C" fn(_));
+
+    unsafe { sqlite3_rollback_hook(db.db, cb, to_user_data(&mut f)) };
+    db.hooks.borrow_mut().rollback = f;
+}
+
+
+pub(crate) fn set_preupdate_hook(db: &DbHandle, f: Option<Box<PreUpdateHookFn>>) -> Result<(), ReturnStatus> {
+    if db.sessions.get() > 0 {
+        let mut rs: ReturnStatus = PrimaryRC::SQLITE_MISUSE.into();
+        rs.err_msg = Some("cannot set the pre-update hook while a session exists".to_string());
+        return Err(rs);
+    }
+
+    let mut f = f.map(Box::new);
+    let cb = f.as_ref().map(|_| call_preupdate as unsafe extern "C" fn(_, _, _, _, _, _, _));
+
+    unsafe { sqlite3_preupdate_hook(db.db, cb, to_user_data(&mut f)) };
+    db.hooks.borrow_mut().preupdate = f;
+    Ok(())
+}
+
+pub(crate) fn set_wal_hook(db: &DbHandle, f: Option<Box<WalHookFn>>) {
+    let mut f = f.map(Box::new);
+    let cb = f.as_ref().map(|_| call_wal as unsafe extern "C" fn(_, _, _, _) -> _);
+
+    unsafe { sqlite3_wal_hook(db.db, cb, to_user_data(&mut f)) };
+    db.hooks.borrow_mut().wal = f;
+}
+
+pub(crate) fn set_trace(db: &DbHandle, f: Option<Box<TraceFn>>) {
+    let mut t = f.map(|f| Box::new(Trace { f, rows: HashMap::new() }));
+    let cb = t.as_ref().map(|_| call_trace as unsafe extern "C" fn(_, _, _, _) -> _);
+    let user_data = t.as_mut().map_or(ptr::null_mut(), |b| &mut **b as *mut Trace as *mut c_void);
+
+    unsafe { sqlite3_trace_v2(db.db, SQLITE_TRACE_STMT | SQLITE_TRACE_PROFILE | SQLITE_TRACE_ROW, cb, user_data) };
+    db.hooks.borrow_mut().trace = t;
+}
+
+// Note: Expires every prepared statement (including cached ones), so they are re-checked on their next run.
+pub(crate) fn set_authorizer(db: &DbHandle, f: Option<Box<AuthorizerFn>>) {
+    let mut a = f.map(|f| Box::new(Auth { f, last_denied: None }));
+    let cb = a.as_ref().map(|_| call_auth as unsafe extern "C" fn(_, _, _, _, _, _) -> _);
+    let user_data = a.as_mut().map_or(ptr::null_mut(), |b| &mut **b as *mut Auth as *mut c_void);
+
+    unsafe { sqlite3_set_authorizer(db.db, cb, user_data) };
+    db.hooks.borrow_mut().auth = a;
+}
+
+pub(crate) fn take_auth_denied(db: &DbHandle) -> Option<AuthContext> {
+    db.hooks.borrow_mut().auth.as_mut().and_then(|a| a.last_denied.take())
+}
+
+// `n_ops` = virtual machine instructions between calls.
+pub(crate) fn set_progress_handler(db: &DbHandle, n_ops: i32, f: Option<Box<ProgressFn>>) {
+    let mut f = f.map(Box::new);
+    let cb = f.as_ref().map(|_| call_progress as unsafe extern "C" fn(_) -> _);
+
+    unsafe { sqlite3_progress_handler(db.db, n_ops, cb, to_user_data(&mut f)) };
+    db.hooks.borrow_mut().progress = f;
+}
+
+// Interrupts any statement still running `timeout` after `f` starts. Returns `true` if a statement was interrupted.
+// Note: Replaces (then removes) any progress handler already set.
+pub(crate) fn with_timeout<T, F>(db: &DbHandle, timeout: Duration, f: F) -> (T, bool) where F: FnOnce() -> T {
+    let deadline = Instant::now() + timeout;
+    let is_timeout = Rc::new(Cell::new(false));
+    let is_timeout_2 = is_timeout.clone();
+
+    set_progress_handler(db, PROGRESS_N_OPS, Some(Box::new(move || {
+        let past = Instant::now() >= deadline;
+        if past {
+            is_timeout_2.set(true);
+        }
+        past
+    })));
+
+    let r = f();
+    set_progress_handler(db, 0, None);
+
+    (r, is_timeout.get())
+}
+
+
+pub(crate) fn get_op(op: c_int) -> ChangeOp {
+    match op as u32 {
+        SQLITE_INSERT => ChangeOp::Insert,
+        SQLITE_UPDATE => ChangeOp::Update,
+        SQLITE_DELETE => ChangeOp::Delete,
+        _ => panic!("Unknown update hook op, code={}", op)
+    }
+}
+
+unsafe extern "C" fn call_update(user_data: *mut c_void, op: c_int, _db_name: *const c_char, table: *const c_char, rowid: sqlite3_int64) {
+    let f = &mut *(user_data as *mut Box<UpdateHookFn>);
+
+    let _ = catch_panic(|| {
+        let change = RowChange {
+            table: CStr::from_ptr(table).to_string_lossy().into_owned(),
+            op: get_op(op),
+            rowid: rowid as i64,
+        };
+
+        Ok(f(&change))
+    });
+}
+
+type PreUpdateValueFn = unsafe extern "C" fn(*mut sqlite3, c_int, *mut *mut sqlite3_value) -> c_int;
+
+// Note: Only valid inside the pre-update callback.
+fn get_preupdate_row(db: *mut sqlite3, get: PreUpdateValueFn) -> Vec<Val> {
+    let n = unsafe { sqlite3_preupdate_count(db) };
+
+    (0..n).map(|i| {
+        let mut v: *mut sqlite3_value = ptr::null_mut();
+        let r = unsafe { get(db, i, &mut v) };
+        let rs = to_return_status_cint(&r);
+
+        assert!(rs.is_ok, "Could not read pre-update value: {:?}", rs);
+        get_value(v)
+    }).collect()
+}
+
+unsafe extern "C" fn call_preupdate(user_data: *mut c_void, db: *mut sqlite3, op: c_int, db_name: *const c_char, table: *const c_char, old_rowid: sqlite3_int64, new_rowid: sqlite3_int64) {
+    let f = &mut *(user_data as *mut Box<PreUpdateHookFn>);
+
+    let _ = catch_panic(|| {
+        let op = get_op(op);
+
+        let (old, new) = match op {
+            ChangeOp::Insert => (None, Some(get_preupdate_row(db, sqlite3_preupdate_new))),
+            ChangeOp::Update => (Some(get_preupdate_row(db, sqlite3_preupdate_old)), Some(get_preupdate_row(db, sqlite3_preupdate_new))),
+            ChangeOp::Delete => (Some(get_preupdate_row(db, sqlite3_preupdate_old)), None),
+        };
+
+        let pu = PreUpdate {
+            db_name: CStr::from_ptr(db_name).to_string_lossy().into_owned(),
+            table: CStr::from_ptr(table).to_string_lossy().into_owned(),
+            old_rowid: old.as_ref().map(|_| old_rowid as i64),
+            new_rowid: new.as_ref().map(|_| new_rowid as i64),
+            op,
+            old,
+            new,
+            depth: sqlite3_preupdate_depth(db) as i32,
+        };
+
+        Ok(f(&pu))
+    });
+}
+
+unsafe extern "C" fn call_commit(user_data: *mut c_void) -> c_int {
+    let f = &mut *(user_data as *mut Box<CommitHookFn>);
+
+    // A panic rolls back, as the hook could not approve the commit.
+    match catch_panic(|| Ok(f())) {
+        Ok(false) => 0,
+        Ok(true) | Err(_) => 1,
+    }
+}
+
+unsafe extern "C" fn call_progress(user_data: *mut c_void) -> c_int {
+    let f = &mut *(user_data as *mut Box<ProgressFn>);
+
+    // A panic interrupts, as the statement may otherwise never end.
+    match catch_panic(|| Ok(f())) {
+        Ok(false) => 0,
+        Ok(true) | Err(_) => 1,
+    }
+}
+
+unsafe extern "C" fn call_wal(user_data: *mut c_void, _db: *mut sqlite3, db_name: *const c_char, wal_frames: c_int) -> c_int {
+    let f = &mut *(user_data as *mut Box<WalHookFn>);
+    let db_name = CStr::from_ptr(db_name).to_string_lossy();
+
+    // The commit has already happened; a non `SQLITE_OK` return would only be reported as the `COMMIT` error.
+    let _ = catch_panic(|| Ok(f(&db_name, wal_frames)));
+    0
+}
+
+unsafe extern "C" fn call_rollback(user_data: *mut c_void) {
+    let f = &mut *(user_data as *mut Box<RollbackHookFn>);
+    let _ = catch_panic(|| Ok(f()));
+}
+
+// `p` = the `stmt`, `x` = expanded SQL (`STMT`) or elapsed nanoseconds (`PROFILE`).
+unsafe extern "C" fn call_trace(event: c_uint, user_data: *mut c_void, p: *mut c_void, x: *mut c_void) -> c_int {
+    let t = &mut *(user_data as *mut Trace);
+    let stmt = p as *mut sqlite3_stmt;
+
+    let _ = catch_panic(|| {
+        match event {
+            SQLITE_TRACE_STMT => {
+                let sql = CStr::from_ptr(x as *const c_char).to_string_lossy().into_owned();
+                (t.f)(&TraceEvent::Stmt { sql });
+            }
+            SQLITE_TRACE_ROW => {
+                *t.rows.entry(stmt as usize).or_insert(0) += 1;
+            }
+            SQLITE_TRACE_PROFILE => {
+                let rows = t.rows.remove(&(stmt as usize)).unwrap_or(0);
+                let profile = get_profile(stmt, *(x as *const i64), rows);
+                (t.f)(&TraceEvent::Profile(profile));
+            }
+            _ => {}
+        }
+        Ok(())
+    });
+
+    // "The return value from the callback is currently ignored".
+    0
+}
+
+unsafe fn to_opt_string(s: *const c_char) -> Option<String> {
+    if s.is_null() {
+        return None;
+    }
+    Some(CStr::from_ptr(s).to_string_lossy().into_owned())
+}
+
+unsafe extern "C" fn call_auth(user_data: *mut c_void, code: c_int, a: *const c_char, b: *const c_char, db_name: *const c_char, accessor: *const c_char) -> c_int {
+    let auth = &mut *(user_data as *mut Auth);
+
+    let action = match get_action(code as u32, to_opt_string(a), to_opt_string(b)) {
+        Some(action) => action,
+        None => return 0
+    };
+
+    let ctx = AuthContext {
+        action,
+        db_name: to_opt_string(db_name),
+        accessor: to_opt_string(accessor),
+    };
+
+    // A panic denies, as the callback could not approve the action.
+    let r = match catch_panic(|| Ok((auth.f)(&ctx))) {
+        Ok(r) => r,
+        Err(_) => Authorization::Deny
+    };
+
+    if r == Authorization::Deny {
+        auth.last_denied = Some(ctx);
+    }
+
+    to_auth_code(r) as c_int
+}
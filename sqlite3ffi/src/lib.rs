@@ -9,9 +9,53 @@ extern crate chrono;
 use chrono::{DateTime, Utc};
 
 
+#[cfg(test)]
+mod test_utils;
 #[cfg(test)]
 mod test_placeholders;
 mod test_transactions;
+#[cfg(test)]
+mod test_stmt_cache;
+#[cfg(test)]
+mod test_cursor;
+#[cfg(test)]
+mod test_script;
+#[cfg(test)]
+mod test_open;
+#[cfg(test)]
+mod test_blob;
+#[cfg(test)]
+mod test_backup;
+#[cfg(test)]
+mod test_function;
+#[cfg(test)]
+mod test_aggregate;
+#[cfg(test)]
+mod test_collation;
+#[cfg(test)]
+mod test_hooks;
+#[cfg(test)]
+mod test_session;
+#[cfg(test)]
+mod test_checkpoint;
+#[cfg(test)]
+mod test_serialize;
+#[cfg(test)]
+mod test_col_names;
+#[cfg(test)]
+mod test_serde;
+#[cfg(test)]
+mod test_explain;
+#[cfg(test)]
+mod test_trace;
+#[cfg(test)]
+mod test_limits;
+#[cfg(test)]
+mod test_auth;
+#[cfg(test)]
+mod test_db_config;
+#[cfg(test)]
+mod test_extension;
 
 // @todo/low Possible features:
 // - @see https://www.sqlite.org/c3ref/progress_handler.html (Used for percent complete indicator of long queries).
@@ -27,7 +71,28 @@ mod fns;
 mod placeholder;
 mod config;
 pub mod db;
+pub mod open;
 pub mod stmt;
+pub mod stmt_cache;
+pub mod cursor;
+pub mod script;
+pub mod blob;
+pub mod backup;
+pub mod function;
+pub mod aggregate;
+pub mod collation;
+pub mod hooks;
+pub mod session;
+pub mod checkpoint;
+pub mod serialize;
+pub mod de;
+pub mod ser;
+pub mod explain;
+pub mod trace;
+pub mod limits;
+pub mod auth;
+pub mod db_config;
+pub mod extension;
 
 
 use errmap::{
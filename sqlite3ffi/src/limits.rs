@@ -0,0 +1,219 @@
+use std::os::raw::c_int;
+
+use serde::{Deserialize, Serialize};
+
+use crate::cffi::{
+    // Functions
+    sqlite3_limit,
+    sqlite3_soft_heap_limit64,
+
+    // Constants
+    SQLITE_LIMIT_LENGTH,
+    SQLITE_LIMIT_SQL_LENGTH,
+    SQLITE_LIMIT_COLUMN,
+    SQLITE_LIMIT_EXPR_DEPTH,
+    SQLITE_LIMIT_COMPOUND_SELECT,
+    SQLITE_LIMIT_VDBE_OP,
+    SQLITE_LIMIT_FUNCTION_ARG,
+    SQLITE_LIMIT_ATTACHED,
+    SQLITE_LIMIT_LIKE_PATTERN_LENGTH,
+    SQLITE_LIMIT_VARIABLE_NUMBER,
+    SQLITE_LIMIT_TRIGGER_DEPTH,
+    SQLITE_LIMIT_WORKER_THREADS,
+};
+
+use crate::db::DbHandle;
+use crate::errmap::PrimaryRC;
+use crate::err::ReturnStatus;
+
+
+// Per connection run time limits; can only be lowered below the compile time maximums.
+// @see https://www.sqlite.org/c3ref/c_limit_attached.html
+// @see https://www.sqlite.org/limits.html
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Serialize, Deserialize)]
+#[derive(Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum Limit {
+    // Bytes in a string or blob, or a row.
+    Length,
+    // Bytes in a statement; exceeding it is reported as `Length` by `get_limit_exceeded`.
+    SqlLength,
+    // Columns in a table, index, view or result set; terms in `GROUP BY`, `ORDER BY` etc.
+    Column,
+    ExprDepth,
+    CompoundSelect,
+    // Instructions in a prepared statement; exceeding it fails with `SQLITE_NOMEM`, so it is not detected by `get_limit_exceeded`.
+    VdbeOp,
+    FunctionArg,
+    Attached,
+    LikePatternLength,
+    // Largest `?NNN`.
+    VariableNumber,
+    TriggerDepth,
+    WorkerThreads,
+}
+
+impl Limit {
+    fn get_code(&self) -> c_int {
+        let c = match self {
+            Limit::Length => SQLITE_LIMIT_LENGTH,
+            Limit::SqlLength => SQLITE_LIMIT_SQL_LENGTH,
+            Limit::Column => SQLITE_LIMIT_COLUMN,
+            Limit::ExprDepth => SQLITE_LIMIT_EXPR_DEPTH,
+            Limit::CompoundSelect => SQLITE_LIMIT_COMPOUND_SELECT,
+            Limit::VdbeOp => SQLITE_LIMIT_VDBE_OP,
+            Limit::FunctionArg => SQLITE_LIMIT_FUNCTION_ARG,
+            Limit::Attached => SQLITE_LIMIT_ATTACHED,
+            Limit::LikePatternLength => SQLITE_LIMIT_LIKE_PATTERN_LENGTH,
+            Limit::VariableNumber => SQLITE_LIMIT_VARIABLE_NUMBER,
+            Limit::TriggerDepth => SQLITE_LIMIT_TRIGGER_DEPTH,
+            Limit::WorkerThreads => SQLITE_LIMIT_WORKER_THREADS,
+        };
+        c as c_int
+    }
+}
+
+
+// A limits profile; `None` leaves the connections current value.
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Serialize, Deserialize)]
+#[derive(Clone)]
+#[derive(Default)]
+#[serde(default)]
+pub struct Limits {
+    pub length: Option<i32>,
+    pub sql_length: Option<i32>,
+    pub column: Option<i32>,
+    pub expr_depth: Option<i32>,
+    pub compound_select: Option<i32>,
+    pub vdbe_op: Option<i32>,
+    pub function_arg: Option<i32>,
+    pub attached: Option<i32>,
+    pub like_pattern_length: Option<i32>,
+    pub variable_number: Option<i32>,
+    pub trigger_depth: Option<i32>,
+    pub worker_threads: Option<i32>,
+}
+
+impl Limits {
+    // For SQL from an untrusted source.
+    // @see https://www.sqlite.org/security.html ("Defense Against The Dark Arts")
+    // Note: `variable_number` is higher than the docs suggest, as key based placeholders each use one.
+    pub fn untrusted() -> Limits {
+        Limits {
+            length: Some(1000000),
+            sql_length: Some(100000),
+            column: Some(100),
+            expr_depth: Some(10),
+            compound_select: Some(3),
+            vdbe_op: Some(25000),
+            function_arg: Some(8),
+            attached: Some(0),
+            like_pattern_length: Some(50),
+            variable_number: Some(100),
+            trigger_depth: Some(10),
+            worker_threads: None,
+        }
+    }
+
+    fn get_all(&self) -> Vec<(Limit, Option<i32>)> {
+        vec![
+            (Limit::Length, self.length),
+            (Limit::SqlLength, self.sql_length),
+            (Limit::Column, self.column),
+            (Limit::ExprDepth, self.expr_depth),
+            (Limit::CompoundSelect, self.compound_select),
+            (Limit::VdbeOp, self.vdbe_op),
+            (Limit::FunctionArg, self.function_arg),
+            (Limit::Attached, self.attached),
+            (Limit::LikePatternLength, self.like_pattern_length),
+            (Limit::VariableNumber, self.variable_number),
+            (Limit::TriggerDepth, self.trigger_depth),
+            (Limit::WorkerThreads, self.worker_threads),
+        ]
+    }
+}
+
+
+// Values larger than the compile time maximum are truncated to it; returns the previous value.
+pub fn set_limit(db: &DbHandle, l: Limit, v: i32) -> i32 {
+    unsafe { sqlite3_limit(db.db, l.get_code(), v.max(0)) as i32 }
+}
+
+pub fn get_limit(db: &DbHandle, l: Limit) -> i32 {
+    unsafe { sqlite3_limit(db.db, l.get_code(), -1) as i32 }
+}
+
+pub fn set_limits(db: &DbHandle, limits: &Limits) {
+    for (l, v) in limits.get_all() {
+        if let Some(v) = v {
+            set_limit(db, l, v);
+        }
+    }
+}
+
+// Every limit of the connection (all `Some`).
+pub fn get_limits(db: &DbHandle) -> Limits {
+    let g = |l| Some(get_limit(db, l));
+
+    Limits {
+        length: g(Limit::Length),
+        sql_length: g(Limit::SqlLength),
+        column: g(Limit::Column),
+        expr_depth: g(Limit::ExprDepth),
+        compound_select: g(Limit::CompoundSelect),
+        vdbe_op: g(Limit::VdbeOp),
+        function_arg: g(Limit::FunctionArg),
+        attached: g(Limit::Attached),
+        like_pattern_length: g(Limit::LikePatternLength),
+        variable_number: g(Limit::VariableNumber),
+        trigger_depth: g(Limit::TriggerDepth),
+        worker_threads: g(Limit::WorkerThreads),
+    }
+}
+
+
+// The limit that caused an error, from its code and message.
+// Note: SQLite has no distinct return code per limit; this matches the 3.28 messages.
+pub fn get_limit_exceeded(rs: &ReturnStatus) -> Option<Limit> {
+    let msg = rs.err_msg.as_ref().map_or("", |m| m.as_str());
+
+    match rs.primary.id {
+        // Also returned for statements longer than `SqlLength`; SQLite uses the same code and message for both.
+        PrimaryRC::SQLITE_TOOBIG => Some(Limit::Length),
+        PrimaryRC::SQLITE_ERROR => {
+            if msg.starts_with("too many columns") {
+                Some(Limit::Column)
+            } else if msg.starts_with("Expression tree is too large") {
+                Some(Limit::ExprDepth)
+            } else if msg.starts_with("too many terms in compound SELECT") {
+                Some(Limit::CompoundSelect)
+            } else if msg.starts_with("too many arguments on function") {
+                Some(Limit::FunctionArg)
+            } else if msg.starts_with("too many attached databases") {
+                Some(Limit::Attached)
+            } else if msg.starts_with("LIKE or GLOB pattern too complex") {
+                Some(Limit::LikePatternLength)
+            } else if msg.starts_with("too many SQL variables") || msg.starts_with("variable number must be between") {
+                Some(Limit::VariableNumber)
+            } else if msg.starts_with("too many levels of trigger recursion") {
+                Some(Limit::TriggerDepth)
+            } else {
+                None
+            }
+        }
+        _ => None
+    }
+}
+
+
+// Process wide; SQLite frees cached pages to try to keep its heap below `n` bytes, but allocations do not fail when it is exceeded. `0` = no limit.
+// Returns the previous limit (a negative `n` only reads it).
+// @see https://www.sqlite.org/c3ref/hard_heap_limit64.html
+// @todo/low `sqlite3_hard_heap_limit64` (allocations fail with `SQLITE_NOMEM` past it) needs SQLite 3.31; this crate bundles 3.28.
+pub fn set_soft_heap_limit(n: i64) -> i64 {
+    unsafe { sqlite3_soft_heap_limit64(n) as i64 }
+}
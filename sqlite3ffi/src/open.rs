@@ -0,0 +1,109 @@
+use serde::{Deserialize, Serialize};
+
+use crate::cffi::{
+    // Constants
+    SQLITE_OPEN_READONLY,
+    SQLITE_OPEN_READWRITE,
+    SQLITE_OPEN_CREATE,
+    SQLITE_OPEN_URI,
+    SQLITE_OPEN_NOMUTEX,
+    SQLITE_OPEN_FULLMUTEX,
+};
+
+
+// @see https://www.sqlite.org/c3ref/open.html
+// - `sqlite3_open_v2` requires exactly one of the three modes below.
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Serialize, Deserialize)]
+#[derive(Clone)]
+pub enum OpenMode {
+    // "The database is opened in read-only mode. If the database does not already exist, an error is returned."
+    #[serde(rename = "read_only")]
+    ReadOnly,
+
+    // Does not create the file; a missing file is an error instead of a new empty database.
+    #[serde(rename = "read_write")]
+    ReadWrite,
+
+    // Same as `sqlite3_open`.
+    #[serde(rename = "read_write_create")]
+    ReadWriteCreate,
+}
+
+// Overrides the threading mode set at compile/start time for a single connection.
+// @see https://www.sqlite.org/threadsafe.html
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Serialize, Deserialize)]
+#[derive(Clone)]
+pub enum Threading {
+    // Multi-thread: the connection must not be used by two threads at the same time.
+    #[serde(rename = "no_mutex")]
+    NoMutex,
+
+    // Serialized.
+    #[serde(rename = "full_mutex")]
+    FullMutex,
+}
+
+
+// `Default` = the same flags as `sqlite3_open`.
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Serialize, Deserialize)]
+#[derive(Clone)]
+#[serde(default)]
+pub struct OpenFlags {
+    pub mode: OpenMode,
+
+    // Interpret the file name as a URI (E.g. `file:data.db?mode=ro&cache=private`).
+    // @see https://www.sqlite.org/uri.html
+    pub uri: bool,
+
+    // `None` = use the process wide threading mode.
+    pub threading: Option<Threading>,
+
+    // Name of a registered `sqlite3_vfs`, `None` = default VFS.
+    pub vfs: Option<String>,
+}
+
+impl Default for OpenFlags {
+    fn default() -> OpenFlags {
+        OpenFlags {
+            mode: OpenMode::ReadWriteCreate,
+            uri: false,
+            threading: None,
+            vfs: None,
+        }
+    }
+}
+
+impl OpenFlags {
+    pub fn read_only() -> OpenFlags {
+        OpenFlags {
+            mode: OpenMode::ReadOnly,
+            ..Default::default()
+        }
+    }
+
+    pub fn to_c_flags(&self) -> u32 {
+        let mut f = match self.mode {
+            OpenMode::ReadOnly => SQLITE_OPEN_READONLY,
+            OpenMode::ReadWrite => SQLITE_OPEN_READWRITE,
+            OpenMode::ReadWriteCreate => SQLITE_OPEN_READWRITE | SQLITE_OPEN_CREATE,
+        };
+
+        if self.uri {
+            f |= SQLITE_OPEN_URI;
+        }
+
+        match self.threading {
+            Some(Threading::NoMutex) => f |= SQLITE_OPEN_NOMUTEX,
+            Some(Threading::FullMutex) => f |= SQLITE_OPEN_FULLMUTEX,
+            None => {}
+        }
+
+        f
+    }
+}
@@ -51,6 +51,20 @@ impl PlaceholderMeta {
 
 }
 
+impl Default for PlaceholderMeta {
+    fn default() -> PlaceholderMeta {
+        PlaceholderMeta {
+            max_index: 0,
+            names: HashMap::new(),
+            keys: HashMap::new(),
+            keys_normal: HashMap::new(),
+            contains_integer_key: false,
+
+            types_used: PlaceholderTypes::None,
+        }
+    }
+}
+
 
 // @see https://www.sqlite.org/c3ref/bind_blob.html
 // @see https://www.sqlite.org/lang_expr.html#varparam
@@ -0,0 +1,113 @@
+use serde::{Deserialize, Serialize};
+
+use crate::fns::to_cstr;
+use crate::err::ReturnStatus;
+use crate::db::DbHandle;
+use crate::stmt::{
+    StmtHandle,
+    RSet,
+};
+
+
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Serialize, Deserialize)]
+#[derive(Clone)]
+pub struct ScriptError {
+    // Position of the failed statement in the script (starting at 0).
+    // - Every statement before it has already run; in a transaction the caller decides to commit or rollback.
+    pub index: usize,
+
+    // Byte offset of the failed statement in the script string.
+    // - Leading whitespace and empty statements (`;`) are skipped, but a comment before the statement is counted as part of it.
+    pub offset: usize,
+
+    pub return_status: ReturnStatus,
+}
+
+
+// Prepares and runs each statement of `q` in order, returning one `RSet` per statement.
+// - Stops at the first statement that fails to prepare or run.
+// - Statements are not cached (scripts are typically run once, E.g. schema setup).
+//
+// Note: `sqlite3_exec` is not used as it converts every value to text and does not return `sqlite3_changes`.
+// @see https://www.sqlite.org/c3ref/exec.html
+pub fn exec_script(db: &DbHandle, q: &str) -> Result<Vec<RSet>, ScriptError> {
+    exec_script_each(db, q, &mut |_| {})
+}
+
+// Same as `exec_script`; `before_each` is called with the index of each statement just before it runs.
+pub fn exec_script_each(db: &DbHandle, q: &str, before_each: &mut dyn FnMut(usize)) -> Result<Vec<RSet>, ScriptError> {
+    let mut rsets = vec![];
+
+    each_stmt(db, q, &mut |index, s| {
+        before_each(index);
+        rsets.push(s.run()?);
+        Ok(true)
+    })?;
+
+    Ok(rsets)
+}
+
+// `true` when every statement in `q` is read only (see `StmtHandle::is_read_only`); no statement is run.
+// - Stops preparing at the first write, as the statements after it may depend on it (E.g. `CREATE TABLE` then `INSERT`).
+pub fn is_read_only_script(db: &DbHandle, q: &str) -> Result<bool, ScriptError> {
+    let mut is_read_only = true;
+
+    each_stmt(db, q, &mut |_, s| {
+        is_read_only = s.is_read_only;
+        Ok(is_read_only)
+    })?;
+
+    Ok(is_read_only)
+}
+
+
+// Prepares each statement of `q` in order and passes it to `f`, which returns `Ok(false)` to stop.
+fn each_stmt(db: &DbHandle, q: &str, f: &mut dyn FnMut(usize, StmtHandle) -> Result<bool, ReturnStatus>) -> Result<(), ScriptError> {
+    let q_c = to_cstr(q);
+    let bytes = q_c.to_bytes();
+
+    let mut index = 0;
+    let mut offset = 0;
+
+    while offset < bytes.len() {
+        let start = skip_whitespace(bytes, offset);
+
+        let err = |return_status| ScriptError {
+            index,
+            offset: start,
+            return_status,
+        };
+
+        let (s, next) = match StmtHandle::new_at(db, &q_c, offset) {
+            Ok(x) => x,
+            Err(rs) => return Err(err(rs))
+        };
+
+        // `None` = empty statement (E.g. `;;`) or only comments remain.
+        if let Some(s) = s {
+            match f(index, s) {
+                Ok(true) => {}
+                Ok(false) => break,
+                Err(rs) => return Err(err(rs))
+            }
+            index += 1;
+        }
+
+        if next <= offset {
+            break;
+        }
+        offset = next;
+    }
+
+    Ok(())
+}
+
+
+fn skip_whitespace(bytes: &[u8], offset: usize) -> usize {
+    match bytes[offset..].iter().position(|b| !(b.is_ascii_whitespace() || *b == b';')) {
+        Some(i) => offset + i,
+        None => bytes.len()
+    }
+}
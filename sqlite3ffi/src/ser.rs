@@ -0,0 +1,296 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use serde::ser::{
+    self,
+    Impossible,
+    SerializeMap,
+    SerializeSeq,
+    SerializeStruct,
+};
+
+use crate::stmt::{
+    KeyVal,
+    Val,
+};
+
+
+// Writes a struct (or map) as key-based placeholder data, for `bind_kv`/`run_kv`.
+// - Field names are the placeholder keys (`:name`, `$name` or `@name` = `name`).
+// - The reverse of `de`: `bool` to `INTEGER`, `Vec<u8>` to `BLOB`, unit enum variants to `TEXT`, `None` to `NULL`.
+// - `chrono` dates serialize to RFC 3339 `TEXT`, `uuid::Uuid` to hyphenated `TEXT`.
+// - Nested structs, maps and sequences (other than bytes) are not supported; store them as JSON `TEXT` with `serde_json::to_string`.
+
+
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Serialize, Deserialize)]
+#[derive(Clone)]
+pub struct SerError {
+    pub msg: String,
+}
+
+impl fmt::Display for SerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.msg)
+    }
+}
+
+impl std::error::Error for SerError {}
+
+impl ser::Error for SerError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        SerError { msg: msg.to_string() }
+    }
+}
+
+fn err<T>(msg: &str) -> Result<T, SerError> {
+    Err(SerError { msg: msg.to_string() })
+}
+
+
+pub fn to_key_val<T: Serialize + ?Sized>(v: &T) -> Result<KeyVal, SerError> {
+    v.serialize(KeyValSerializer)
+}
+
+pub fn to_val<T: Serialize + ?Sized>(v: &T) -> Result<Val, SerError> {
+    v.serialize(ValSerializer)
+}
+
+
+struct KeyValSerializer;
+
+pub struct KeyValMap {
+    data: HashMap<String, Val>,
+    next_key: Option<String>,
+}
+
+static ERR_NOT_KEY_VAL: &str = "Only structs and maps with string keys can be placeholder data.";
+
+impl ser::Serializer for KeyValSerializer {
+    type Ok = KeyVal;
+    type Error = SerError;
+
+    type SerializeSeq = Impossible<KeyVal, SerError>;
+    type SerializeTuple = Impossible<KeyVal, SerError>;
+    type SerializeTupleStruct = Impossible<KeyVal, SerError>;
+    type SerializeTupleVariant = Impossible<KeyVal, SerError>;
+    type SerializeMap = KeyValMap;
+    type SerializeStruct = KeyValMap;
+    type SerializeStructVariant = Impossible<KeyVal, SerError>;
+
+    fn serialize_map(self, len: Option<usize>) -> Result<KeyValMap, SerError> {
+        Ok(KeyValMap { data: HashMap::with_capacity(len.unwrap_or(0)), next_key: None })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<KeyValMap, SerError> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, _name: &'static str, v: &T) -> Result<KeyVal, SerError> {
+        v.serialize(self)
+    }
+
+    fn serialize_bool(self, _v: bool) -> Result<KeyVal, SerError> { err(ERR_NOT_KEY_VAL) }
+    fn serialize_i8(self, _v: i8) -> Result<KeyVal, SerError> { err(ERR_NOT_KEY_VAL) }
+    fn serialize_i16(self, _v: i16) -> Result<KeyVal, SerError> { err(ERR_NOT_KEY_VAL) }
+    fn serialize_i32(self, _v: i32) -> Result<KeyVal, SerError> { err(ERR_NOT_KEY_VAL) }
+    fn serialize_i64(self, _v: i64) -> Result<KeyVal, SerError> { err(ERR_NOT_KEY_VAL) }
+    fn serialize_u8(self, _v: u8) -> Result<KeyVal, SerError> { err(ERR_NOT_KEY_VAL) }
+    fn serialize_u16(self, _v: u16) -> Result<KeyVal, SerError> { err(ERR_NOT_KEY_VAL) }
+    fn serialize_u32(self, _v: u32) -> Result<KeyVal, SerError> { err(ERR_NOT_KEY_VAL) }
+    fn serialize_u64(self, _v: u64) -> Result<KeyVal, SerError> { err(ERR_NOT_KEY_VAL) }
+    fn serialize_f32(self, _v: f32) -> Result<KeyVal, SerError> { err(ERR_NOT_KEY_VAL) }
+    fn serialize_f64(self, _v: f64) -> Result<KeyVal, SerError> { err(ERR_NOT_KEY_VAL) }
+    fn serialize_char(self, _v: char) -> Result<KeyVal, SerError> { err(ERR_NOT_KEY_VAL) }
+    fn serialize_str(self, _v: &str) -> Result<KeyVal, SerError> { err(ERR_NOT_KEY_VAL) }
+    fn serialize_bytes(self, _v: &[u8]) -> Result<KeyVal, SerError> { err(ERR_NOT_KEY_VAL) }
+    fn serialize_none(self) -> Result<KeyVal, SerError> { err(ERR_NOT_KEY_VAL) }
+    fn serialize_some<T: Serialize + ?Sized>(self, _v: &T) -> Result<KeyVal, SerError> { err(ERR_NOT_KEY_VAL) }
+    fn serialize_unit(self) -> Result<KeyVal, SerError> { err(ERR_NOT_KEY_VAL) }
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<KeyVal, SerError> { err(ERR_NOT_KEY_VAL) }
+    fn serialize_unit_variant(self, _name: &'static str, _i: u32, _variant: &'static str) -> Result<KeyVal, SerError> { err(ERR_NOT_KEY_VAL) }
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(self, _name: &'static str, _i: u32, _variant: &'static str, _v: &T) -> Result<KeyVal, SerError> { err(ERR_NOT_KEY_VAL) }
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, SerError> { err(ERR_NOT_KEY_VAL) }
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, SerError> { err(ERR_NOT_KEY_VAL) }
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeTupleStruct, SerError> { err(ERR_NOT_KEY_VAL) }
+    fn serialize_tuple_variant(self, _name: &'static str, _i: u32, _variant: &'static str, _len: usize) -> Result<Self::SerializeTupleVariant, SerError> { err(ERR_NOT_KEY_VAL) }
+    fn serialize_struct_variant(self, _name: &'static str, _i: u32, _variant: &'static str, _len: usize) -> Result<Self::SerializeStructVariant, SerError> { err(ERR_NOT_KEY_VAL) }
+}
+
+impl SerializeStruct for KeyValMap {
+    type Ok = KeyVal;
+    type Error = SerError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, v: &T) -> Result<(), SerError> {
+        self.data.insert(key.to_string(), to_val(v)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<KeyVal, SerError> {
+        Ok(KeyVal { data: self.data })
+    }
+}
+
+impl SerializeMap for KeyValMap {
+    type Ok = KeyVal;
+    type Error = SerError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), SerError> {
+        match to_val(key)? {
+            Val::String(k) => {
+                self.next_key = Some(k);
+                Ok(())
+            }
+            _ => err(ERR_NOT_KEY_VAL)
+        }
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, v: &T) -> Result<(), SerError> {
+        let k = self.next_key.take().expect("serialize_key is called before serialize_value.");
+        self.data.insert(k, to_val(v)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<KeyVal, SerError> {
+        Ok(KeyVal { data: self.data })
+    }
+}
+
+
+struct ValSerializer;
+
+// `Vec<u8>` (and `[u8; N]`) serialize as a sequence of `u8`.
+// - Elements go through `ByteSerializer`, so a sequence of any other type (E.g. `Vec<i64>`) is an error, not a BLOB.
+// Note: An empty sequence cannot be told apart; it is an empty BLOB.
+pub struct BytesSeq {
+    bytes: Vec<u8>,
+}
+
+static ERR_NESTED: &str = "Nested structs, maps and sequences cannot be placeholder values; serialize them to a JSON string first.";
+static ERR_NOT_BYTES: &str = "Only sequences of `u8` can be placeholder values (as a BLOB); serialize other sequences to a JSON string first.";
+
+impl ser::Serializer for ValSerializer {
+    type Ok = Val;
+    type Error = SerError;
+
+    type SerializeSeq = BytesSeq;
+    type SerializeTuple = Impossible<Val, SerError>;
+    type SerializeTupleStruct = Impossible<Val, SerError>;
+    type SerializeTupleVariant = Impossible<Val, SerError>;
+    type SerializeMap = Impossible<Val, SerError>;
+    type SerializeStruct = Impossible<Val, SerError>;
+    type SerializeStructVariant = Impossible<Val, SerError>;
+
+    fn serialize_bool(self, v: bool) -> Result<Val, SerError> {
+        Ok(Val::I64(v as i64))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Val, SerError> { self.serialize_i64(v as i64) }
+    fn serialize_i16(self, v: i16) -> Result<Val, SerError> { self.serialize_i64(v as i64) }
+    fn serialize_i32(self, v: i32) -> Result<Val, SerError> { self.serialize_i64(v as i64) }
+    fn serialize_i64(self, v: i64) -> Result<Val, SerError> { Ok(Val::I64(v)) }
+    fn serialize_u8(self, v: u8) -> Result<Val, SerError> { self.serialize_i64(v as i64) }
+    fn serialize_u16(self, v: u16) -> Result<Val, SerError> { self.serialize_i64(v as i64) }
+    fn serialize_u32(self, v: u32) -> Result<Val, SerError> { self.serialize_i64(v as i64) }
+
+    fn serialize_u64(self, v: u64) -> Result<Val, SerError> {
+        match i64::try_from(v) {
+            Ok(i) => Ok(Val::I64(i)),
+            Err(_) => err("u64 is larger than SQLite's largest INTEGER (i64::MAX).")
+        }
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Val, SerError> { self.serialize_f64(v as f64) }
+    fn serialize_f64(self, v: f64) -> Result<Val, SerError> { Ok(Val::F64(v)) }
+    fn serialize_char(self, v: char) -> Result<Val, SerError> { self.serialize_str(&v.to_string()) }
+    fn serialize_str(self, v: &str) -> Result<Val, SerError> { Ok(Val::String(v.to_string())) }
+    fn serialize_bytes(self, v: &[u8]) -> Result<Val, SerError> { Ok(Val::Blob(v.to_vec())) }
+    fn serialize_none(self) -> Result<Val, SerError> { Ok(Val::Null) }
+    fn serialize_some<T: Serialize + ?Sized>(self, v: &T) -> Result<Val, SerError> { v.serialize(self) }
+    fn serialize_unit(self) -> Result<Val, SerError> { Ok(Val::Null) }
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Val, SerError> { Ok(Val::Null) }
+
+    fn serialize_unit_variant(self, _name: &'static str, _i: u32, variant: &'static str) -> Result<Val, SerError> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, _name: &'static str, v: &T) -> Result<Val, SerError> {
+        v.serialize(self)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<BytesSeq, SerError> {
+        Ok(BytesSeq { bytes: Vec::with_capacity(len.unwrap_or(0)) })
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(self, _name: &'static str, _i: u32, _variant: &'static str, _v: &T) -> Result<Val, SerError> { err(ERR_NESTED) }
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, SerError> { err(ERR_NESTED) }
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeTupleStruct, SerError> { err(ERR_NESTED) }
+    fn serialize_tuple_variant(self, _name: &'static str, _i: u32, _variant: &'static str, _len: usize) -> Result<Self::SerializeTupleVariant, SerError> { err(ERR_NESTED) }
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, SerError> { err(ERR_NESTED) }
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct, SerError> { err(ERR_NESTED) }
+    fn serialize_struct_variant(self, _name: &'static str, _i: u32, _variant: &'static str, _len: usize) -> Result<Self::SerializeStructVariant, SerError> { err(ERR_NESTED) }
+}
+
+impl SerializeSeq for BytesSeq {
+    type Ok = Val;
+    type Error = SerError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, v: &T) -> Result<(), SerError> {
+        self.bytes.push(v.serialize(ByteSerializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Val, SerError> {
+        Ok(Val::Blob(self.bytes))
+    }
+}
+
+
+// One element of a `BytesSeq`; only `u8` is accepted.
+struct ByteSerializer;
+
+impl ser::Serializer for ByteSerializer {
+    type Ok = u8;
+    type Error = SerError;
+
+    type SerializeSeq = Impossible<u8, SerError>;
+    type SerializeTuple = Impossible<u8, SerError>;
+    type SerializeTupleStruct = Impossible<u8, SerError>;
+    type SerializeTupleVariant = Impossible<u8, SerError>;
+    type SerializeMap = Impossible<u8, SerError>;
+    type SerializeStruct = Impossible<u8, SerError>;
+    type SerializeStructVariant = Impossible<u8, SerError>;
+
+    fn serialize_u8(self, v: u8) -> Result<u8, SerError> { Ok(v) }
+
+    fn serialize_bool(self, _v: bool) -> Result<u8, SerError> { err(ERR_NOT_BYTES) }
+    fn serialize_i8(self, _v: i8) -> Result<u8, SerError> { err(ERR_NOT_BYTES) }
+    fn serialize_i16(self, _v: i16) -> Result<u8, SerError> { err(ERR_NOT_BYTES) }
+    fn serialize_i32(self, _v: i32) -> Result<u8, SerError> { err(ERR_NOT_BYTES) }
+    fn serialize_i64(self, _v: i64) -> Result<u8, SerError> { err(ERR_NOT_BYTES) }
+    fn serialize_u16(self, _v: u16) -> Result<u8, SerError> { err(ERR_NOT_BYTES) }
+    fn serialize_u32(self, _v: u32) -> Result<u8, SerError> { err(ERR_NOT_BYTES) }
+    fn serialize_u64(self, _v: u64) -> Result<u8, SerError> { err(ERR_NOT_BYTES) }
+    fn serialize_f32(self, _v: f32) -> Result<u8, SerError> { err(ERR_NOT_BYTES) }
+    fn serialize_f64(self, _v: f64) -> Result<u8, SerError> { err(ERR_NOT_BYTES) }
+    fn serialize_char(self, _v: char) -> Result<u8, SerError> { err(ERR_NOT_BYTES) }
+    fn serialize_str(self, _v: &str) -> Result<u8, SerError> { err(ERR_NOT_BYTES) }
+    fn serialize_bytes(self, _v: &[u8]) -> Result<u8, SerError> { err(ERR_NOT_BYTES) }
+    fn serialize_none(self) -> Result<u8, SerError> { err(ERR_NOT_BYTES) }
+    fn serialize_some<T: Serialize + ?Sized>(self, _v: &T) -> Result<u8, SerError> { err(ERR_NOT_BYTES) }
+    fn serialize_unit(self) -> Result<u8, SerError> { err(ERR_NOT_BYTES) }
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<u8, SerError> { err(ERR_NOT_BYTES) }
+    fn serialize_unit_variant(self, _name: &'static str, _i: u32, _variant: &'static str) -> Result<u8, SerError> { err(ERR_NOT_BYTES) }
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, _name: &'static str, _v: &T) -> Result<u8, SerError> { err(ERR_NOT_BYTES) }
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(self, _name: &'static str, _i: u32, _variant: &'static str, _v: &T) -> Result<u8, SerError> { err(ERR_NOT_BYTES) }
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, SerError> { err(ERR_NOT_BYTES) }
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, SerError> { err(ERR_NOT_BYTES) }
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeTupleStruct, SerError> { err(ERR_NOT_BYTES) }
+    fn serialize_tuple_variant(self, _name: &'static str, _i: u32, _variant: &'static str, _len: usize) -> Result<Self::SerializeTupleVariant, SerError> { err(ERR_NOT_BYTES) }
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, SerError> { err(ERR_NOT_BYTES) }
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct, SerError> { err(ERR_NOT_BYTES) }
+    fn serialize_struct_variant(self, _name: &'static str, _i: u32, _variant: &'static str, _len: usize) -> Result<Self::SerializeStructVariant, SerError> { err(ERR_NOT_BYTES) }
+}
@@ -0,0 +1,96 @@
+use std::ptr;
+use std::slice;
+use std::os::raw::{c_int, c_void};
+
+use crate::cffi::{
+    // Functions
+    sqlite3_serialize,
+    sqlite3_deserialize,
+    sqlite3_malloc64,
+    sqlite3_free,
+
+    // Constants
+    SQLITE_ERROR,
+    SQLITE_NOMEM,
+    SQLITE_DESERIALIZE_FREEONCLOSE,
+    SQLITE_DESERIALIZE_RESIZEABLE,
+};
+
+use crate::fns::to_cstr;
+use crate::db::DbHandle;
+use crate::err::{
+    ReturnStatus,
+    to_return_status_cint,
+    to_return_status_cint_db_err,
+};
+
+
+// Database images: the same bytes as the database file (without the WAL).
+// @see https://www.sqlite.org/c3ref/serialize.html
+// @see https://www.sqlite.org/c3ref/deserialize.html
+//
+// Note: Needs `SQLITE_ENABLE_DESERIALIZE` (SQLite 3.28 does not enable it by default).
+
+
+// Copies the database (as of a read of its current snapshot) to a byte vector.
+// - Inside a write tx, uncommitted writes on this connection are included.
+pub fn serialize(db: &DbHandle, db_name: Option<&str>) -> Result<Vec<u8>, ReturnStatus> {
+    let db_c = db_name.map(to_cstr);
+    let db_ptr = db_c.as_ref().map_or(ptr::null(), |d| d.as_ptr());
+    let mut size: i64 = 0;
+
+    let p = unsafe { sqlite3_serialize(db.db, db_ptr, &mut size, 0) };
+
+    if p.is_null() {
+        return match size {
+            // A database with no pages (E.g. a new file), `sqlite3_malloc64(0)` returns NULL.
+            0 => Ok(vec![]),
+            // Unknown `db_name`.
+            s if s < 0 => Err(to_return_status_cint(&(SQLITE_ERROR as c_int))),
+            _ => Err(to_return_status_cint(&(SQLITE_NOMEM as c_int)))
+        };
+    }
+
+    let bytes = unsafe {
+        let v = slice::from_raw_parts(p, size as usize).to_vec();
+        sqlite3_free(p as *mut c_void);
+        v
+    };
+
+    Ok(bytes)
+}
+
+
+// Disconnects `db_name` from its file, and re-opens it as an in-memory database with a copy of `data`.
+// - The file is not changed; use `Backup` from this connection to write the image to a file.
+// - Fails with `SQLITE_BUSY` when the connection has a read tx or backup open.
+pub fn deserialize(db: &DbHandle, db_name: Option<&str>, data: &[u8]) -> Result<(), ReturnStatus> {
+    let db_c = db_name.map(to_cstr);
+    let db_ptr = db_c.as_ref().map_or(ptr::null(), |d| d.as_ptr());
+    let size = data.len();
+
+    // SQLite frees this with `sqlite3_free` (`FREEONCLOSE`), and may grow it with `sqlite3_realloc64` (`RESIZEABLE`).
+    let buf = unsafe { sqlite3_malloc64(size as u64) as *mut u8 };
+    if buf.is_null() && size > 0 {
+        return Err(to_return_status_cint(&(SQLITE_NOMEM as c_int)));
+    }
+
+    unsafe {
+        if size > 0 {
+            ptr::copy_nonoverlapping(data.as_ptr(), buf, size);
+        }
+
+        // Issue: In-memory databases cannot open a WAL, so an image of a WAL mode file fails to read with `SQLITE_CANTOPEN`.
+        // Fix: Set the header's read/write versions (bytes 18, 19) to "legacy" (1). A `Backup` into a WAL mode file sets them back to 2.
+        if size >= 20 && *buf.add(18) == 2 {
+            *buf.add(18) = 1;
+            *buf.add(19) = 1;
+        }
+    }
+
+    let flags = SQLITE_DESERIALIZE_FREEONCLOSE | SQLITE_DESERIALIZE_RESIZEABLE;
+    let r = unsafe { sqlite3_deserialize(db.db, db_ptr, buf, size as i64, size as i64, flags) };
+
+    to_return_status_cint_db_err(&r, db.db)?;
+    Ok(())
+}
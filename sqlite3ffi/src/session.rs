@@ -0,0 +1,335 @@
+use std::ptr;
+use std::rc::Rc;
+use std::cell::Cell;
+use std::marker::PhantomData;
+use std::ffi::CStr;
+use std::os::raw::{
+    c_int,
+    c_char,
+    c_void,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::cffi::{
+    // Objects
+    sqlite3_session,
+    sqlite3_changeset_iter,
+
+    // Functions
+    sqlite3session_create,
+    sqlite3session_delete,
+    sqlite3session_attach,
+    sqlite3session_table_filter,
+    sqlite3session_changeset,
+    sqlite3session_patchset,
+    sqlite3session_isempty,
+    sqlite3session_enable,
+    sqlite3changeset_op,
+    sqlite3changeset_apply,
+    sqlite3changeset_invert,
+    sqlite3_free,
+
+    // Constants
+    SQLITE_CHANGESET_DATA,
+    SQLITE_CHANGESET_NOTFOUND,
+    SQLITE_CHANGESET_CONFLICT,
+    SQLITE_CHANGESET_CONSTRAINT,
+    SQLITE_CHANGESET_FOREIGN_KEY,
+    SQLITE_CHANGESET_OMIT,
+    SQLITE_CHANGESET_REPLACE,
+    SQLITE_CHANGESET_ABORT,
+};
+
+use crate::fns::to_cstr;
+use crate::db::DbHandle;
+use crate::stmt::to_owned_vec;
+use crate::hooks::{
+    ChangeOp,
+    get_op,
+};
+use crate::function::catch_panic;
+use crate::err::{
+    ReturnStatus,
+    to_return_status_cint_err,
+    to_return_status_cint_db_err,
+};
+
+
+// Why the conflict handler was called for a change.
+// @see https://www.sqlite.org/session/c_changeset_conflict.html
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Serialize, Deserialize)]
+#[derive(Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictType {
+    // The row exists, but its current values do not match the changes old values (update, delete).
+    Data,
+    // The row to update or delete does not exist.
+    NotFound,
+    // An insert for a primary key that already exists.
+    Conflict,
+    // A change violated a `UNIQUE`, `CHECK` or `NOT NULL` constraint.
+    Constraint,
+    // The changeset left foreign key violations; reported once, after all changes are applied.
+    ForeignKey,
+}
+
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Serialize, Deserialize)]
+#[derive(Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictAction {
+    // Skip the conflicting change.
+    Omit,
+    // Overwrite the row with the change. Only valid for `Data` and `Conflict`; treated as `Omit` for the other types.
+    Replace,
+    // Undo every change applied so far and return `SQLITE_ABORT`.
+    Abort,
+}
+
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Serialize, Deserialize)]
+#[derive(Clone)]
+pub struct Conflict {
+    pub kind: ConflictType,
+
+    // `None` for `ForeignKey`, which is not about a single change.
+    pub table: Option<String>,
+    pub op: Option<ChangeOp>,
+}
+
+// Not `'static`, as it is only called during `apply_changeset`.
+pub type ConflictFn<'a> = dyn FnMut(&Conflict) -> ConflictAction + 'a;
+
+// `true` = record the changes to the table.
+pub type TableFilterFn = dyn Fn(&str) -> bool;
+
+
+// Records the changes made to a connection, so they can be applied to another database with `apply_changeset`.
+// @see https://www.sqlite.org/sessionintro.html
+//
+// - Only tables with a `PRIMARY KEY` are recorded; rows with a `NULL` in a primary key column are ignored.
+// - A changeset is the *net* change: a row inserted and then deleted is not included.
+// - Uses the pre-update hook internally; `DbHandle::set_preupdate_hook` fails while a session exists.
+//
+// Lifetimes: like `BlobHandle`, the session cannot outlive the connection (it must be deleted before the connection closes).
+// - `new_unbound` is for storing it next to its `DbHandle`.
+pub struct Session<'a> {
+    session: *mut sqlite3_session,
+
+    // `DbHandle.sessions`; decremented on drop.
+    sessions: Rc<Cell<usize>>,
+
+    // Double boxed so the pointer passed to SQLite is thin and does not move; dropped after the session is deleted.
+    table_filter: Option<Box<Box<TableFilterFn>>>,
+
+    db: PhantomData<&'a DbHandle>,
+}
+
+impl<'a> std::fmt::Debug for Session<'a> {
+    fn fmt(&self, fm: &mut std::fmt::Formatter) -> std::fmt::Result {
+        fm.debug_struct("Session")
+            .field("session", &self.session)
+            .field("table_filter", &self.table_filter.is_some())
+            .finish()
+    }
+}
+
+
+impl<'a> Session<'a> {
+    // `db_name` = "main", "temp" or an `ATTACH` name.
+    pub fn new(db: &'a DbHandle, db_name: &str) -> Result<Session<'a>, ReturnStatus> {
+        unsafe { Session::new_unbound(db, db_name) }
+    }
+
+    // Like `new`, but the session does not borrow `db`.
+    // Safety: the session must be dropped before `db` is; E.g. declare it before the `DbHandle` in the struct that owns both.
+    pub unsafe fn new_unbound(db: &DbHandle, db_name: &str) -> Result<Session<'a>, ReturnStatus> {
+        let db_c = to_cstr(db_name);
+        let mut session: *mut sqlite3_session = ptr::null_mut();
+
+        let r = unsafe { sqlite3session_create(db.db, db_c.as_ptr(), &mut session) };
+        to_return_status_cint_db_err(&r, db.db)?;
+
+        db.sessions.set(db.sessions.get() + 1);
+        Ok(Session { session, sessions: db.sessions.clone(), table_filter: None, db: PhantomData })
+    }
+
+    // Record changes to `table`, or all tables (including ones created later) for `None`.
+    // Note: The table does not need to exist yet.
+    pub fn attach(&self, table: Option<&str>) -> Result<(), ReturnStatus> {
+        let table_c = table.map(to_cstr);
+        let table_ptr = table_c.as_ref().map_or(ptr::null(), |t| t.as_ptr());
+
+        let r = unsafe { sqlite3session_attach(self.session, table_ptr) };
+        to_return_status_cint_err(&r)?;
+        Ok(())
+    }
+
+    // Only record the tables `f` returns `true` for; called once per table, on its first change.
+    // @see https://www.sqlite.org/session/sqlite3session_table_filter.html
+    // - Only applies to tables recorded by `attach(None)`.
+    pub fn set_table_filter(&mut self, f: Option<Box<TableFilterFn>>) {
+        let mut f = f.map(Box::new);
+        let cb = f.as_ref().map(|_| call_table_filter as unsafe extern "C" fn(_, _) -> _);
+        let ctx = f.as_mut().map_or(ptr::null_mut(), |b| &mut **b as *mut Box<TableFilterFn> as *mut c_void);
+
+        unsafe { sqlite3session_table_filter(self.session, cb, ctx) };
+        self.table_filter = f;
+    }
+
+    // Contains the old and new values of each changed row, so conflicts can be detected when applied.
+    pub fn changeset(&self) -> Result<Vec<u8>, ReturnStatus> {
+        self.get(sqlite3session_changeset)
+    }
+
+    // Smaller than a changeset: only the primary key of updated/deleted rows and the new values of updated columns.
+    // - `ConflictType::Data` is never reported when applying a patchset.
+    pub fn patchset(&self) -> Result<Vec<u8>, ReturnStatus> {
+        self.get(sqlite3session_patchset)
+    }
+
+    // `false` if any change has been recorded, even one that has since been undone.
+    pub fn is_empty(&self) -> bool {
+        unsafe { sqlite3session_isempty(self.session) != 0 }
+    }
+
+    // Changes made while disabled are not recorded. Sessions start enabled.
+    pub fn set_enabled(&self, is_enabled: bool) {
+        unsafe { sqlite3session_enable(self.session, is_enabled as c_int) };
+    }
+
+    fn get(&self, f: unsafe extern "C" fn(*mut sqlite3_session, *mut c_int, *mut *mut c_void) -> c_int) -> Result<Vec<u8>, ReturnStatus> {
+        let mut n: c_int = 0;
+        let mut p: *mut c_void = ptr::null_mut();
+
+        let r = unsafe { f(self.session, &mut n, &mut p) };
+        to_return_status_cint_err(&r)?;
+
+        Ok(take_changeset(p, n))
+    }
+}
+
+impl<'a> Drop for Session<'a> {
+    fn drop(&mut self) {
+        unsafe { sqlite3session_delete(self.session) };
+        self.sessions.set(self.sessions.get() - 1);
+    }
+}
+
+unsafe extern "C" fn call_table_filter(ctx: *mut c_void, table: *const c_char) -> c_int {
+    let f = &*(ctx as *const Box<TableFilterFn>);
+    let table = CStr::from_ptr(table).to_string_lossy();
+
+    // A panic records the table, as if there was no filter.
+    catch_panic(|| Ok(f(&table))).unwrap_or(true) as c_int
+}
+
+// Copies a changeset allocated by SQLite into a `Vec`, then frees it.
+fn take_changeset(p: *mut c_void, n: c_int) -> Vec<u8> {
+    // Null when there are no changes.
+    if p.is_null() {
+        return vec![];
+    }
+
+    let v = to_owned_vec(p as *const u8, &(n as u32));
+    unsafe { sqlite3_free(p) };
+    v
+}
+
+
+// Applies a changeset or patchset from `Session`; all or nothing (a savepoint is used internally).
+// @see https://www.sqlite.org/session/sqlite3changeset_apply.html
+// - Tables are matched by name; a table missing in `db`, or with different primary key columns, is skipped without a conflict.
+pub fn apply_changeset(db: &DbHandle, changeset: &[u8], on_conflict: &mut ConflictFn) -> Result<(), ReturnStatus> {
+    let mut ctx: &mut ConflictFn = on_conflict;
+
+    let r = unsafe {
+        sqlite3changeset_apply(
+            db.db,
+            changeset.len() as c_int,
+            // Not written to.
+            changeset.as_ptr() as *mut c_void,
+            None,
+            Some(call_conflict),
+            &mut ctx as *mut &mut ConflictFn as *mut c_void,
+        )
+    };
+
+    // E.g. `SQLITE_ABORT` when the handler returns `ConflictAction::Abort`.
+    to_return_status_cint_db_err(&r, db.db)?;
+    Ok(())
+}
+
+
+// Inserts become deletes, deletes become inserts, and the old and new values of updates are swapped.
+// - Applying the result undoes the changeset. Patchsets cannot be inverted (`SQLITE_CORRUPT`).
+pub fn invert_changeset(changeset: &[u8]) -> Result<Vec<u8>, ReturnStatus> {
+    let mut n: c_int = 0;
+    let mut p: *mut c_void = ptr::null_mut();
+
+    let r = unsafe { sqlite3changeset_invert(changeset.len() as c_int, changeset.as_ptr() as *const c_void, &mut n, &mut p) };
+    to_return_status_cint_err(&r)?;
+
+    Ok(take_changeset(p, n))
+}
+
+
+fn get_conflict_type(t: c_int) -> ConflictType {
+    match t as u32 {
+        SQLITE_CHANGESET_DATA => ConflictType::Data,
+        SQLITE_CHANGESET_NOTFOUND => ConflictType::NotFound,
+        SQLITE_CHANGESET_CONFLICT => ConflictType::Conflict,
+        SQLITE_CHANGESET_CONSTRAINT => ConflictType::Constraint,
+        SQLITE_CHANGESET_FOREIGN_KEY => ConflictType::ForeignKey,
+        _ => panic!("Unknown changeset conflict type, code={}", t)
+    }
+}
+
+unsafe fn get_conflict(t: c_int, iter: *mut sqlite3_changeset_iter) -> Conflict {
+    let kind = get_conflict_type(t);
+
+    // The iterator can only be used with `sqlite3changeset_fk_conflicts` for foreign key conflicts.
+    if kind == ConflictType::ForeignKey {
+        return Conflict { kind, table: None, op: None };
+    }
+
+    let mut table: *const c_char = ptr::null();
+    let mut n_col: c_int = 0;
+    let mut op: c_int = 0;
+    let mut indirect: c_int = 0;
+    sqlite3changeset_op(iter, &mut table, &mut n_col, &mut op, &mut indirect);
+
+    Conflict {
+        kind,
+        table: Some(CStr::from_ptr(table).to_string_lossy().into_owned()),
+        op: Some(get_op(op)),
+    }
+}
+
+unsafe extern "C" fn call_conflict(ctx: *mut c_void, t: c_int, iter: *mut sqlite3_changeset_iter) -> c_int {
+    let f = &mut *(ctx as *mut &mut ConflictFn);
+    // A panic (including reading the conflict) aborts, as nothing is known about how the conflict should be resolved.
+    let (action, can_replace) = match catch_panic(|| {
+        let c = get_conflict(t, iter);
+
+        // "SQLITE_CHANGESET_REPLACE may only be returned [for] SQLITE_CHANGESET_DATA or SQLITE_CHANGESET_CONFLICT", otherwise the apply fails with `SQLITE_MISUSE`.
+        let can_replace = c.kind == ConflictType::Data || c.kind == ConflictType::Conflict;
+        Ok((f(&c), can_replace))
+    }) {
+        Ok(x) => x,
+        Err(_) => (ConflictAction::Abort, false),
+    };
+
+    let code = match action {
+        ConflictAction::Replace if can_replace => SQLITE_CHANGESET_REPLACE,
+        ConflictAction::Replace | ConflictAction::Omit => SQLITE_CHANGESET_OMIT,
+        ConflictAction::Abort => SQLITE_CHANGESET_ABORT,
+    };
+
+    code as c_int
+}
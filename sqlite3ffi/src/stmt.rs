@@ -8,8 +8,10 @@ use std::os::raw::{
     c_void,
 };
 use std::ptr;
+use std::mem;
 use std::mem::MaybeUninit;
 use std::ffi::CStr;
+use std::time::Instant;
 
 extern crate chrono;
 
@@ -29,9 +31,10 @@ use crate::cffi::{
     sqlite3_int64,
 
     // Functions
-    sqlite3_prepare_v2,
+    sqlite3_prepare_v3,
     sqlite3_step,
     sqlite3_reset,
+    sqlite3_clear_bindings,
     sqlite3_finalize,
     sqlite3_data_count,
     sqlite3_column_type,
@@ -42,6 +45,10 @@ use crate::cffi::{
     sqlite3_column_bytes,
     sqlite3_column_origin_name,
     sqlite3_column_name,
+    sqlite3_column_database_name,
+    sqlite3_column_table_name,
+    sqlite3_column_decltype,
+    sqlite3_column_count,
     sqlite3_stmt_readonly,
     sqlite3_sql,
     sqlite3_changes,
@@ -52,6 +59,7 @@ use crate::cffi::{
     sqlite3_bind_blob,
 
     // Constants
+    SQLITE_PREPARE_PERSISTENT,
     SQLITE_INTEGER,
     SQLITE_FLOAT,
     SQLITE_TEXT,
@@ -67,7 +75,13 @@ use crate::errmap;
 use crate::err;
 use crate::fns;
 use crate::placeholder;
+use crate::stmt_cache;
 use crate::db::DbHandle;
+use crate::cursor::Cursor;
+use crate::trace::{
+    QueryTiming,
+    get_stmt_status,
+};
 
 use fns::to_cstr;
 use errmap::PrimaryRC;
@@ -80,6 +94,7 @@ use err::{
 
 
 use placeholder::PlaceholderMeta;
+use stmt_cache::CachedStmt;
 
 
 use crate::placeholder::PlaceholderTypes;
@@ -87,7 +102,7 @@ use crate::placeholder::PlaceholderTypes;
 
 // @see https://www.sqlite.org/c3ref/stmt.html
 // @see https://www.sqlite.org/cintro.html ("> 50% of the query is used in creating the statement.")
-//      - @todo/medium cache statements
+//      - See `StmtHandle::new_cached` for statements that are re-used via the `DbHandle` statement cache.
 #[derive(Debug)]
 pub struct StmtHandle<'a> {
 
@@ -100,16 +115,19 @@ pub struct StmtHandle<'a> {
     // but *scopes call `drop` on owned variables in the reverse order they were created.*
     // A stmt always has a db, but a db does not always have a stmt. This avoids Option and unnecessary if branches.
 
-    db: &'a DbHandle,
-    stmt: *mut sqlite3_stmt,
+    pub(crate) db: &'a DbHandle,
+    pub(crate) stmt: *mut sqlite3_stmt,
     pub placeholder_meta: PlaceholderMeta,
     pub is_read_only: bool,
+
+    // `Some(q)` = return the stmt to the db handles cache under key `q` on drop (instead of finalizing it).
+    cache_key: Option<String>,
 }
 
 
 impl StmtHandle<'_> {
     pub fn new<'a>(db: &'a DbHandle, q: &str) -> Result<StmtHandle<'a>, ReturnStatus> {
-        let stmt = StmtHandle::new_stmt(db, q)?;
+        let stmt = StmtHandle::new_stmt(db, q, 0)?;
 
         // Compute place holder data from `stmt` before `StmtHandle` creation so its always `Some`.
         // Assumption: placeholder data never changes for the same `stmt`, will be needed in most cases.
@@ -122,22 +140,95 @@ impl StmtHandle<'_> {
                 stmt,
                 placeholder_meta,
                 is_read_only,
+                cache_key: None,
             }
         )
     }
 
-    fn new_stmt<'a>(db: &'a DbHandle, q: &str) -> Result<*mut sqlite3_stmt, ReturnStatus> {
+    // Same as `new`, but re-uses a previously prepared `stmt` with the same SQL string from the `db` cache.
+    // - On drop the `stmt` is reset, its bindings cleared, and it is returned to the cache.
+    pub fn new_cached<'a>(db: &'a DbHandle, q: &str) -> Result<StmtHandle<'a>, ReturnStatus> {
+        if let Some(c) = db.stmt_cache.borrow_mut().take(q) {
+            let CachedStmt { stmt, placeholder_meta, is_read_only } = c;
+
+            return Ok(
+                StmtHandle {
+                    db,
+                    stmt,
+                    placeholder_meta,
+                    is_read_only,
+                    cache_key: Some(q.to_string()),
+                }
+            );
+        }
+
+        // `SQLITE_PREPARE_PERSISTENT` = hint to SQLite that the stmt will be retained for a long time and re-used many times.
+        let stmt = StmtHandle::new_stmt(db, q, SQLITE_PREPARE_PERSISTENT)?;
+        let placeholder_meta = PlaceholderMeta::new(stmt);
+        let is_read_only = is_read_only(stmt);
+
+        Ok(
+            StmtHandle {
+                db,
+                stmt,
+                placeholder_meta,
+                is_read_only,
+                cache_key: Some(q.to_string()),
+            }
+        )
+    }
+
+    // Prepares the statement starting at byte `offset` of `q_c` (a multi statement script).
+    // - Returns the statement and the byte offset of the text that follows it.
+    // - `None` = the remaining text contains no SQL (E.g. only whitespace or comments).
+    pub fn new_at<'a>(db: &'a DbHandle, q_c: &CStr, offset: usize) -> Result<(Option<StmtHandle<'a>>, usize), ReturnStatus> {
+        let (stmt, next) = StmtHandle::new_stmt_tail(db, q_c, offset, 0)?;
+
+        if stmt.is_null() {
+            return Ok((None, next));
+        }
+
+        let placeholder_meta = PlaceholderMeta::new(stmt);
+        let is_read_only = is_read_only(stmt);
+
+        Ok((
+            Some(StmtHandle {
+                db,
+                stmt,
+                placeholder_meta,
+                is_read_only,
+                cache_key: None,
+            }),
+            next
+        ))
+    }
+
+    fn new_stmt<'a>(db: &'a DbHandle, q: &str, prep_flags: u32) -> Result<*mut sqlite3_stmt, ReturnStatus> {
+        let q_c = to_cstr(q);
+        let (stmt, _) = StmtHandle::new_stmt_tail(db, &q_c, 0, prep_flags)?;
+
+        Ok(stmt)
+    }
+
+    // @see https://www.sqlite.org/c3ref/prepare.html
+    // - `sqlite3_prepare_v3` with `prep_flags=0` is the same as `sqlite3_prepare_v2`.
+    // - Only the first statement is compiled, `pzTail` is set to the first byte past the end of it.
+    fn new_stmt_tail(db: &DbHandle, q_c: &CStr, offset: usize, prep_flags: u32) -> Result<(*mut sqlite3_stmt, usize), ReturnStatus> {
+        let num_bytes = q_c.to_bytes_with_nul().len();
+        assert!(offset < num_bytes, "Offset is past the end of the SQL string.");
+
         let mut mu = MaybeUninit::uninit();
         let mut stmt: *mut sqlite3_stmt = mu.as_mut_ptr();
-        let q_c = to_cstr(q);
+        let mut tail: *const c_char = ptr::null();
 
         let r = unsafe {
-            sqlite3_prepare_v2(
+            sqlite3_prepare_v3(
                 db.db,
-                q_c.as_ptr(),
-                q_c.to_bytes_with_nul().len() as ::std::os::raw::c_int,
+                q_c.as_ptr().add(offset),
+                (num_bytes - offset) as ::std::os::raw::c_int,
+                prep_flags as ::std::os::raw::c_uint,
                 &mut stmt,
-                ptr::null_mut(),
+                &mut tail,
             )
         };
 
@@ -149,93 +240,55 @@ impl StmtHandle<'_> {
         // - "sqlite3_finalize() on a NULL pointer is a harmless no-op."
         to_return_status_cint_db_err(&r, db.db)?;
 
+        // Offset of `tail` from the start of the whole string (excluding the nul terminator).
+        let next = if tail.is_null() {
+            num_bytes - 1
+        } else {
+            tail as usize - q_c.as_ptr() as usize
+        };
 
-        Ok(stmt)
+        Ok((stmt, next))
     }
 
 
+    // Lazily steps the `stmt`, one row at a time.
+    pub fn cursor<'s>(&'s self) -> Cursor<'s> {
+        Cursor::new(self)
+    }
+
+    // Collects every row of the `cursor` into an `RSet`.
     pub fn run(&self) -> Result<RSet, ReturnStatus> {
-        let StmtHandle { db, stmt, is_read_only, .. } = &self;
+        let start = Instant::now();
+        let mut c = self.cursor();
 
+        let col_names = c.col_names();
 
         let mut rset = RSet {
-            is_read_only: *is_read_only,
-            is_iud: is_iud(*stmt),
+            is_read_only: self.is_read_only,
+            is_iud: c.is_iud(),
+            num_cols: col_names.len() as u32,
+            col_names,
 
             ..Default::default()
         };
 
 
-        let mut add_row = |row: Vec<Val>| {
-            if rset.rows.data.len() == 0 {
-                // First row.
-
-                rset.col_names = get_headers(*stmt);
-                rset.num_cols = row.len() as u32;
-            }
-            rset.rows.data.push(row);
-        };
+        while let Some(row) = c.next() {
+            rset.rows.data.push(row?);
+        }
 
+        set_storage_classes(&mut rset.col_names, &rset.rows.data);
+        rset.rows_changed = c.rows_changed();
+        rset.num_rows = rset.rows.data.len() as u32;
 
-        loop {
-            let r = unsafe { sqlite3_step(*stmt) };
-            let step = to_return_status_cint_db_err(&r, db.db);
-
-            match step {
-                Err(status) => {
-                    match status.primary.id {
-                        PrimaryRC::SQLITE_BUSY => {
-                            // @todo/important If "COMMIT" or outside of transaction, retry `step()`, else "ROLLBACK"
-                            // @see https://www.sqlite.org/c3ref/step.html
-//                            dbg!(("sqlite3_step: ", &status));
-
-                            return Err(status);
-                        }
-                        _ => {
-                            // SQLITE_ERROR etc
-
-                            // Errors: (FULL, IOERR, BUSY, NOMEM)
-                            // @todo/low "It is recommended that applications respond to the errors listed above by explicitly issuing a ROLLBACK command"
-                            // @see https://www.sqlite.org/lang_transaction.html
-                            dbg!(format!("sqlite3_step: {:?}", status));
-                            return Err(status);
-                        }
-                    }
-                }
-                Ok(status) => {
-                    match status.primary.id {
-                        PrimaryRC::SQLITE_ROW => {
-                            let row = get_row(*stmt);
-                            add_row(row);
-                            continue;
-                        }
-
-                        PrimaryRC::SQLITE_DONE => {
-                            // `sqlite3_reset`
-                            // - Resets the VM so the `sqlite3_step` can be called on the `stmt` again.
-                            // - Will return last error code (but `sqlite3_step` error should be the same).
-                            // - Keeps bindings (call `sqlite3_clear_bindings` to clear them).
-                            // - `sqlite3_step` automatically calls this.
-                            unsafe {
-                                sqlite3_reset(*stmt);
-                            }
-
-                            // @see https://www.sqlite.org/pragma.html#pragma_data_version (determine if file has changed).
-                            if rset.is_iud {
-                                rset.rows_changed = Some(get_changes(db.db));
-                            }
-
-                            rset.num_rows = rset.rows.data.len() as u32;
-                            return Ok(rset);
-                        }
-                        _ => {
-                            // Only SQLITE_OK remaining. Create enum of non-error values so compiler can verify `match`?
-                            panic!("sqlite3_step Ok values are not exhaustive. {:?}", status)
-                        }
-                    }
-                }
-            }
+        if self.db.rset_timing.get() {
+            rset.timing = Some(QueryTiming {
+                duration_us: start.elapsed().as_micros() as u64,
+                status: get_stmt_status(self.stmt),
+            });
         }
+
+        Ok(rset)
     }
 
 
@@ -460,7 +513,7 @@ pub struct KeyVal {
 // @see https://www.sqlite.org/c3ref/column_blob.html
 #[derive(Debug)]
 #[allow(non_camel_case_types)]
-enum Type {
+pub(crate) enum Type {
     SQLITE_INTEGER,
     SQLITE_FLOAT,
     SQLITE_TEXT,
@@ -531,7 +584,7 @@ pub enum Val {
 //}
 
 
-fn get_cell_type(code: u32) -> Type {
+pub(crate) fn get_cell_type(code: u32) -> Type {
     match code {
         SQLITE_INTEGER => Type::SQLITE_INTEGER,
         SQLITE_FLOAT => Type::SQLITE_FLOAT,
@@ -544,52 +597,65 @@ fn get_cell_type(code: u32) -> Type {
 
 
 // Note: Requires `SQLITE_ENABLE_COLUMN_METADATA` compile flag.
-fn get_headers(stmt: *mut sqlite3_stmt) -> Vec<ColName> {
+// - Uses `sqlite3_column_count`, so headers are available before the first row (and for queries that return no rows).
+// - `storage_class` and `storage_classes` are not set; they depend on the rows.
+pub(crate) fn get_headers(stmt: *mut sqlite3_stmt) -> Vec<ColName> {
     let mut headers = vec![];
 
-    let num_cols = get_num_cols(stmt);
+    let num_cols = unsafe { sqlite3_column_count(stmt) };
 
     for n in 0..num_cols {
         let n_c = n as c_int;
 
+        // @see https://www.sqlite.org/c3ref/column_name.html
+        // @see https://www.sqlite.org/c3ref/column_database_name.html
+        // @see https://www.sqlite.org/c3ref/column_decltype.html
+        let c_name = unsafe {
+            let name = sqlite3_column_name(stmt, n_c);
+            assert!(!name.is_null());
+
+            ColName {
+                name: CStr::from_ptr(name).to_string_lossy().into_owned(),
+                name_origin: to_opt_string(sqlite3_column_origin_name(stmt, n_c)),
+                db_name: to_opt_string(sqlite3_column_database_name(stmt, n_c)),
+                table_name: to_opt_string(sqlite3_column_table_name(stmt, n_c)),
+                decl_type: to_opt_string(sqlite3_column_decltype(stmt, n_c)),
+                storage_class: None,
+                storage_classes: None,
+            }
+        };
 
-        let (ptr_a, ptr_b) = unsafe {
-
-            // @see https://www.sqlite.org/c3ref/column_name.html
-            // @see https://www.sqlite.org/c3ref/column_database_name.html
-
-            let a: *const c_char = sqlite3_column_name(stmt, n_c);
-            let b: *const c_char = sqlite3_column_origin_name(stmt, n_c);
-
-            assert!(!a.is_null());
+        headers.push(c_name);
+    }
 
-            (
-                CStr::from_ptr(a as *const c_char),
-                if b.is_null() {
-                    None
-                } else {
-                    Some(CStr::from_ptr(b as *const c_char))
-                }
-            )
-        };
+    return headers;
+}
 
+// NULL = `None`.
+unsafe fn to_opt_string(ptr: *const c_char) -> Option<String> {
+    if ptr.is_null() {
+        return None;
+    }
 
-        let name = ptr_a.to_string_lossy().into_owned();
-        let name_origin = match ptr_b {
-            None => None,
-            Some(ptr) => Some(ptr.to_string_lossy().into_owned())
-        };
+    Some(CStr::from_ptr(ptr).to_string_lossy().into_owned())
+}
 
-        let c_name = ColName { name, name_origin };
+// Sets `storage_class` from the values in each column, and `storage_classes` for columns that mix classes.
+fn set_storage_classes(col_names: &mut Vec<ColName>, rows: &Vec<Vec<Val>>) {
+    for (n, c) in col_names.iter_mut().enumerate() {
+        let all: Vec<StorageClass> = rows.iter().map(|row| StorageClass::from(&row[n])).collect();
+        let mut classes = all.iter().filter(|sc| **sc != StorageClass::Null);
 
-        headers.push(c_name);
+        match classes.next() {
+            None => {}
+            Some(first) if classes.all(|sc| sc == first) => c.storage_class = Some(first.clone()),
+            Some(_) => c.storage_classes = Some(all),
+        }
     }
-
-    return headers;
 }
 
 
-fn get_num_cols(stmt: *mut sqlite3_stmt) -> u32 {
+pub(crate) fn get_num_cols(stmt: *mut sqlite3_stmt) -> u32 {
     unsafe {
         sqlite3_data_count(stmt) as u32
     }
@@ -597,67 +663,69 @@ fn get_num_cols(stmt: *mut sqlite3_stmt) -> u32 {
 
 // @see https://www.sqlite.org/c3ref/column_blob.html
 // - "pointers returned are valid until a type conversion occurs as described above, or until sqlite3_step() or sqlite3_reset() or sqlite3_finalize() is called. The memory space used to hold strings and BLOBs is freed automatically."
-fn get_row(stmt: *mut sqlite3_stmt) -> Vec<Val> {
+pub(crate) fn get_row(stmt: *mut sqlite3_stmt) -> Vec<Val> {
     let mut rw = vec![];
 
     let num_cols = get_num_cols(stmt);
 
     for n in 0..num_cols {
-        let n_c = n as c_int;
+        rw.push(get_cell(stmt, n));
+    }
 
-        let t = get_cell_type(unsafe {
-            sqlite3_column_type(stmt, n_c) as u32
-        });
+    return rw;
+}
 
-        let v = match t {
-            Type::SQLITE_INTEGER => {
-                // @todo/low Use smaller int type if possible.
-                // @todo/low Write tests around for the range of JSON's number type (float).
-                Val::I64(
-                    unsafe { sqlite3_column_int64(stmt, n_c) } as i64
-                )
-            }
-            Type::SQLITE_FLOAT => {
-                Val::F64(
-                    unsafe { sqlite3_column_double(stmt, n_c) } as f64
-                )
-            }
-            Type::SQLITE_TEXT => {
-                // Note: `sqlite3_column_text` is UTF-8, and so is Rusts `String`
-                let ptr = unsafe {
-                    let s = sqlite3_column_text(stmt, n_c);
+pub(crate) fn get_cell(stmt: *mut sqlite3_stmt, n: u32) -> Val {
+    let n_c = n as c_int;
 
-                    // @todo/low Should the length of string in bytes be read from SQLite here? E.g: `sqlite3_column_bytes`
-                    CStr::from_ptr(s as *const c_char)
-                };
+    let t = get_cell_type(unsafe {
+        sqlite3_column_type(stmt, n_c) as u32
+    });
 
-                Val::String(
-                    ptr.to_string_lossy().into_owned()
-                )
-            }
-            Type::SQLITE_NULL => {
-                Val::Null
-            }
-            Type::SQLITE_BLOB => {
-                let (ptr, num_bytes) = unsafe {
-                    (
-                        sqlite3_column_blob(stmt, n_c) as *const u8,
-                        sqlite3_column_bytes(stmt, n_c) as u32
-                    )
-                };
+    match t {
+        Type::SQLITE_INTEGER => {
+            // @todo/low Use smaller int type if possible.
+            // @todo/low Write tests around for the range of JSON's number type (float).
+            Val::I64(
+                unsafe { sqlite3_column_int64(stmt, n_c) } as i64
+            )
+        }
+        Type::SQLITE_FLOAT => {
+            Val::F64(
+                unsafe { sqlite3_column_double(stmt, n_c) } as f64
+            )
+        }
+        Type::SQLITE_TEXT => {
+            // Note: `sqlite3_column_text` is UTF-8, and so is Rusts `String`
+            let ptr = unsafe {
+                let s = sqlite3_column_text(stmt, n_c);
 
-                Val::Blob(to_owned_vec(ptr, &num_bytes))
-            }
-        };
+                // @todo/low Should the length of string in bytes be read from SQLite here? E.g: `sqlite3_column_bytes`
+                CStr::from_ptr(s as *const c_char)
+            };
 
-        rw.push(v);
-    }
+            Val::String(
+                ptr.to_string_lossy().into_owned()
+            )
+        }
+        Type::SQLITE_NULL => {
+            Val::Null
+        }
+        Type::SQLITE_BLOB => {
+            let (ptr, num_bytes) = unsafe {
+                (
+                    sqlite3_column_blob(stmt, n_c) as *const u8,
+                    sqlite3_column_bytes(stmt, n_c) as u32
+                )
+            };
 
-    return rw;
+            Val::Blob(to_owned_vec(ptr, &num_bytes))
+        }
+    }
 }
 
 
-fn to_owned_vec(ptr: *const u8, num_bytes: &u32) -> Vec<u8> {
+pub(crate) fn to_owned_vec(ptr: *const u8, num_bytes: &u32) -> Vec<u8> {
     let n = *num_bytes as usize;
     let mut dst = Vec::with_capacity(n);
 
@@ -683,7 +751,7 @@ fn is_read_only(stmt: *mut sqlite3_stmt) -> bool {
 // @see https://www.sqlite.org/c3ref/changes.html
 // If query starts with (INSERT|UPDATE|DELETE).
 // `sqlite_changes` only counts rows modified by these keywords.
-fn is_iud(stmt: *mut sqlite3_stmt) -> bool {
+pub(crate) fn is_iud(stmt: *mut sqlite3_stmt) -> bool {
     let ptr = unsafe {
         let s = sqlite3_sql(stmt);
         CStr::from_ptr(s as *const c_char)
@@ -701,7 +769,7 @@ fn is_iud(stmt: *mut sqlite3_stmt) -> bool {
 // Num rows modified.
 // - INSERT|UPDATE|DELETE only.
 // @see https://www.sqlite.org/c3ref/changes.html
-fn get_changes(db: *mut sqlite3) -> u64 {
+pub(crate) fn get_changes(db: *mut sqlite3) -> u64 {
     unsafe {
         sqlite3_changes(db) as u64
     }
@@ -724,12 +792,16 @@ pub struct RSet {
     // @todo/low total_changes?
     pub rows_changed: Option<u64>,
 
-    // @todo/low col AS x names, db, and table meta data. @see https://www.sqlite.org/c3ref/column_database_name.html
+    // Set even when there are no rows.
     pub col_names: Vec<ColName>,
     pub num_cols: u32,
     pub num_rows: u32,
 
     pub rows: Rows,
+
+    // Only set when `DbHandle::set_rset_timing` is on.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timing: Option<QueryTiming>,
 }
 
 
@@ -745,8 +817,51 @@ pub struct Rows {
 #[derive(PartialEq)]
 #[derive(Serialize, Deserialize)]
 pub struct ColName {
+    // `col AS x` = `x`.
     pub name: String,
+
+    // The table column the result is read from (E.g. `col` for `col AS x`); `None` for expressions.
     pub name_origin: Option<String>,
+    pub db_name: Option<String>,
+    pub table_name: Option<String>,
+
+    // The type in `CREATE TABLE`; `None` for expressions and columns declared without a type.
+    pub decl_type: Option<String>,
+
+    // The storage class of every non-NULL value in the column; `None` if they differ, or if there are none.
+    // - A column of any declared type can hold values of any class, @see https://www.sqlite.org/datatype3.html
+    pub storage_class: Option<StorageClass>,
+
+    // One per row (including `Null`); only set when the column mixes classes, so `storage_class` cannot describe it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub storage_classes: Option<Vec<StorageClass>>,
+}
+
+
+// @see https://www.sqlite.org/datatype3.html#storage_classes_and_datatypes
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Clone)]
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageClass {
+    Null,
+    Integer,
+    Real,
+    Text,
+    Blob,
+}
+
+impl From<&Val> for StorageClass {
+    fn from(v: &Val) -> Self {
+        match v {
+            Val::Null => StorageClass::Null,
+            Val::I64(_) => StorageClass::Integer,
+            Val::F64(_) => StorageClass::Real,
+            Val::String(_) => StorageClass::Text,
+            Val::Blob(_) => StorageClass::Blob,
+        }
+    }
 }
 
 
@@ -761,6 +876,7 @@ impl Default for RSet {
             num_cols: 0,
             num_rows: 0,
             rows: Rows { data: vec![] },
+            timing: None,
         }
     }
 }
@@ -774,21 +890,45 @@ impl Drop for StmtHandle<'_> {
         let stmt = self.stmt;
         assert!(!(stmt.is_null()), "Drop called but StmtHandle.stmt was null.");
 
-        // @see https://www.sqlite.org/c3ref/finalize.html
-        // - Every `sqlite3_stmt` must be run through `finalize` to avoid memory leaks.
-        // - `finalize` can be called at any time.
-        // - `finalize` returns the last error for the last evaluation of the statement, or SQLITE_OK otherwise.
-
-        let r = unsafe { sqlite3_finalize(stmt) };
-        let finalize = to_return_status_cint(&r);
+        if let Some(q) = self.cache_key.take() {
+            // Release any read/write locks held by an unfinished `stmt`, and do not leak bound values into the next use.
+            unsafe {
+                sqlite3_reset(stmt);
+                sqlite3_clear_bindings(stmt);
+            }
 
+            let c = CachedStmt {
+                stmt,
+                placeholder_meta: mem::replace(&mut self.placeholder_meta, PlaceholderMeta::default()),
+                is_read_only: self.is_read_only,
+            };
 
-        // This will return the last error of the statement.
-        // Even if `sqlite3_finalize` returns an error, it still needs to be called to free memory of the statement.
-        if !finalize.is_ok {
-            dbg!(finalize);
+            let evicted = self.db.stmt_cache.borrow_mut().put(q, c);
+            for e in evicted {
+                finalize(e.stmt);
+            }
+            return;
         }
 
+        finalize(stmt);
+
         // @todo/medium Make sure pointer memory is freed/zeroed/null;
     }
 }
+
+
+// @see https://www.sqlite.org/c3ref/finalize.html
+// - Every `sqlite3_stmt` must be run through `finalize` to avoid memory leaks.
+// - `finalize` can be called at any time.
+// - `finalize` returns the last error for the last evaluation of the statement, or SQLITE_OK otherwise.
+pub fn finalize(stmt: *mut sqlite3_stmt) {
+    let r = unsafe { sqlite3_finalize(stmt) };
+    let finalize = to_return_status_cint(&r);
+
+
+    // This will return the last error of the statement.
+    // Even if `sqlite3_finalize` returns an error, it still needs to be called to free memory of the statement.
+    if !finalize.is_ok {
+        dbg!(finalize);
+    }
+}
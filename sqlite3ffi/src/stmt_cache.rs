@@ -0,0 +1,120 @@
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
+use crate::cffi::{
+    // Objects
+    sqlite3_stmt,
+};
+
+use crate::placeholder::PlaceholderMeta;
+
+
+// Default number of prepared statements kept per `DbHandle`.
+pub const DEFAULT_STMT_CACHE_CAPACITY: usize = 32;
+
+
+// A prepared statement that is not currently in use.
+// - `stmt` has been reset and its bindings cleared before being placed in the cache.
+// - `placeholder_meta` and `is_read_only` are kept so they do not need to be read from SQLite again.
+#[derive(Debug)]
+pub struct CachedStmt {
+    pub stmt: *mut sqlite3_stmt,
+    pub placeholder_meta: PlaceholderMeta,
+    pub is_read_only: bool,
+}
+
+
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Serialize, Deserialize)]
+#[derive(Clone)]
+pub struct StmtCacheStats {
+    pub capacity: usize,
+    pub len: usize,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+
+// LRU cache of prepared statements keyed by their SQL string.
+// @see https://www.sqlite.org/c3ref/c_prepare_normalize.html (`SQLITE_PREPARE_PERSISTENT`)
+//
+// - Front of `entries` = most recently used.
+// - A statement is *removed* from the cache while it is in use, and returned when its `StmtHandle` drops.
+//      - Two handles for the same SQL at the same time results in the second being a miss (a new statement is prepared).
+// - Linear search is used as the capacity is small (a handful of queries run many times).
+//
+// Note: The cache does not finalize statements, evicted entries are returned so the owner of the db connection can finalize them.
+#[derive(Debug)]
+pub struct StmtCache {
+    capacity: usize,
+    entries: VecDeque<(String, CachedStmt)>,
+    hits: u64,
+    misses: u64,
+}
+
+
+impl StmtCache {
+    pub fn new(capacity: usize) -> StmtCache {
+        StmtCache {
+            capacity,
+            entries: VecDeque::new(),
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    pub fn take(&mut self, q: &str) -> Option<CachedStmt> {
+        match self.entries.iter().position(|(k, _)| k == q) {
+            Some(i) => {
+                self.hits += 1;
+                self.entries.remove(i).map(|(_, c)| c)
+            }
+            None => {
+                self.misses += 1;
+                None
+            }
+        }
+    }
+
+    // Returns the statements that no longer fit in the cache.
+    pub fn put(&mut self, q: String, c: CachedStmt) -> Vec<CachedStmt> {
+        if self.capacity == 0 || self.entries.iter().any(|(k, _)| *k == q) {
+            return vec![c];
+        }
+
+        self.entries.push_front((q, c));
+        self.evict_to_capacity()
+    }
+
+    pub fn set_capacity(&mut self, capacity: usize) -> Vec<CachedStmt> {
+        self.capacity = capacity;
+        self.evict_to_capacity()
+    }
+
+    pub fn clear(&mut self) -> Vec<CachedStmt> {
+        self.entries.drain(..).map(|(_, c)| c).collect()
+    }
+
+    pub fn stats(&self) -> StmtCacheStats {
+        StmtCacheStats {
+            capacity: self.capacity,
+            len: self.entries.len(),
+            hits: self.hits,
+            misses: self.misses,
+        }
+    }
+
+    fn evict_to_capacity(&mut self) -> Vec<CachedStmt> {
+        let mut evicted = vec![];
+
+        while self.entries.len() > self.capacity {
+            if let Some((_, c)) = self.entries.pop_back() {
+                evicted.push(c);
+            }
+        }
+
+        evicted
+    }
+}
@@ -0,0 +1,109 @@
+use super::*;
+use crate::aggregate::Aggregate;
+use crate::test_utils::new_mem_db;
+
+
+// `rs_join(value, separator)`
+#[derive(Default)]
+struct Join {
+    parts: Vec<String>,
+    sep: String,
+}
+
+impl Aggregate for Join {
+    fn step(&mut self, args: &[Val]) -> Result<(), String> {
+        match (&args[0], &args[1]) {
+            (Val::String(v), Val::String(sep)) => {
+                self.parts.push(v.clone());
+                self.sep = sep.clone();
+                Ok(())
+            }
+            _ => Err("Expected (text, text).".to_string())
+        }
+    }
+
+    fn value(&self) -> Result<Val, String> {
+        Ok(Val::String(self.parts.join(&self.sep)))
+    }
+}
+
+
+// `rs_sum(i)` with `inverse` for sliding window frames.
+#[derive(Default)]
+struct Sum {
+    total: i64,
+}
+
+impl Aggregate for Sum {
+    fn step(&mut self, args: &[Val]) -> Result<(), String> {
+        match args[0] {
+            Val::I64(i) => self.total += i,
+            _ => panic!("Not an integer.")
+        }
+        Ok(())
+    }
+
+    fn inverse(&mut self, args: &[Val]) -> Result<(), String> {
+        if let Val::I64(i) = args[0] {
+            self.total -= i;
+        }
+        Ok(())
+    }
+
+    fn value(&self) -> Result<Val, String> {
+        Ok(Val::I64(self.total))
+    }
+}
+
+
+fn new_db() -> DbHandle {
+    let h = new_mem_db("
+        CREATE TABLE t1(id INTEGER PRIMARY KEY, grp TEXT, name TEXT);
+        INSERT INTO t1 (id, grp, name) VALUES (1, 'a', 'x'), (2, 'a', 'y'), (3, 'b', 'z'), (4, 'b', 'w');
+    ");
+
+    h.create_aggregate_function("rs_join", 2, true, false, Box::new(|| Box::new(Join::default()))).unwrap();
+    h.create_aggregate_function("rs_sum", 1, true, true, Box::new(|| Box::new(Sum::default()))).unwrap();
+    h
+}
+
+
+#[test]
+fn test_aggregate_groups() {
+    let h = new_db();
+
+    let rset = h.run("SELECT grp, rs_join(name, '|') FROM t1 GROUP BY grp ORDER BY grp").unwrap();
+    assert_eq!(rset.rows.data, vec![
+        vec![Val::String("a".to_string()), Val::String("x|y".to_string())],
+        vec![Val::String("b".to_string()), Val::String("z|w".to_string())],
+    ]);
+
+    // Assert: A group with no rows is finalized from a fresh state.
+    let rset = h.run("SELECT rs_join(name, '|'), rs_sum(id) FROM t1 WHERE id > 100").unwrap();
+    assert_eq!(rset.rows.data[0], vec![Val::String("".to_string()), Val::I64(0)]);
+
+    match h.run("SELECT rs_join(id, '|') FROM t1") {
+        Err(rs) => assert!(rs.err_msg.unwrap().contains("Expected (text, text).")),
+        Ok(_) => assert!(false)
+    }
+
+    // Assert: Only window functions can be used with `OVER`.
+    assert!(h.run("SELECT rs_join(name, '|') OVER (ORDER BY id) FROM t1").is_err());
+}
+
+
+#[test]
+fn test_aggregate_window() {
+    let h = new_db();
+
+    let rset = h.run("SELECT rs_sum(id) OVER (ORDER BY id ROWS BETWEEN 1 PRECEDING AND CURRENT ROW) FROM t1 ORDER BY id").unwrap();
+    let sums: Vec<Val> = rset.rows.data.into_iter().map(|mut r| r.remove(0)).collect();
+    assert_eq!(sums, vec![Val::I64(1), Val::I64(3), Val::I64(5), Val::I64(7)]);
+
+    // Assert: Panics are returned as errors.
+    match h.run("SELECT rs_sum(name) OVER (ORDER BY id) FROM t1") {
+        Err(rs) => assert!(rs.err_msg.unwrap().contains("panicked")),
+        Ok(_) => assert!(false)
+    }
+    assert_eq!(h.run("SELECT rs_sum(id) FROM t1").unwrap().rows.data[0][0], Val::I64(10));
+}
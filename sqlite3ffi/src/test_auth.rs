@@ -0,0 +1,81 @@
+use super::*;
+use std::rc::Rc;
+use std::cell::RefCell;
+use crate::errmap::PrimaryRC;
+use crate::auth::{
+    AuthAction,
+    AuthContext,
+    Authorization,
+    is_not_authorized,
+};
+
+
+#[test]
+fn test_authorizer() {
+    let h = DbHandle::new(":memory:".to_string()).unwrap();
+    h.run("CREATE TABLE t1(a, secret)").unwrap();
+    h.run("INSERT INTO t1 (a, secret) VALUES (1, 'x')").unwrap();
+
+    let seen = Rc::new(RefCell::new(vec![]));
+    let s = seen.clone();
+    h.set_authorizer(Some(Box::new(move |c: &AuthContext| {
+        s.borrow_mut().push(c.clone());
+
+        match &c.action {
+            AuthAction::Attach { .. } => Authorization::Deny,
+            AuthAction::Function { name } if name == "hex" => Authorization::Deny,
+            AuthAction::Pragma { name, .. } if name == "writable_schema" => Authorization::Deny,
+            AuthAction::Read { column, .. } if column == "secret" => Authorization::Ignore,
+            _ => Authorization::Allow
+        }
+    })));
+
+    // Assert: Actions and their arguments are typed.
+    h.run("UPDATE t1 SET a = 2").unwrap();
+    assert!(seen.borrow().contains(&AuthContext {
+        action: AuthAction::Update { table: "t1".to_string(), column: "a".to_string() },
+        db_name: Some("main".to_string()),
+        accessor: None,
+    }));
+
+    // Assert: `Ignore` reads the column as NULL.
+    assert_eq!(h.run("SELECT a, secret FROM t1").unwrap().rows.data[0], vec![Val::I64(2), Val::Null]);
+    assert_eq!(h.take_auth_denied(), None);
+
+    // Assert: Denied statements fail to prepare, and the denied action is kept.
+    let e = h.run("ATTACH ':memory:' AS other").unwrap_err();
+    assert_eq!(e.primary.id, PrimaryRC::SQLITE_AUTH);
+    assert!(is_not_authorized(&e));
+    assert_eq!(h.take_auth_denied().unwrap().action, AuthAction::Attach { file: ":memory:".to_string() });
+    assert_eq!(h.take_auth_denied(), None);
+
+    // Assert: Denied functions fail with `SQLITE_ERROR`.
+    let e = h.run("SELECT hex(a) FROM t1").unwrap_err();
+    assert_eq!(e.primary.id, PrimaryRC::SQLITE_ERROR);
+    assert!(is_not_authorized(&e));
+    assert_eq!(h.take_auth_denied().unwrap().action, AuthAction::Function { name: "hex".to_string() });
+    assert!(!is_not_authorized(&h.run("SELECT * FROM missing").unwrap_err()));
+
+    assert!(h.run("PRAGMA writable_schema = ON").is_err());
+    assert_eq!(h.take_auth_denied().unwrap().action, AuthAction::Pragma { name: "writable_schema".to_string(), arg: Some("ON".to_string()) });
+
+    h.set_authorizer(None);
+    assert_eq!(h.run("SELECT secret FROM t1").unwrap().rows.data[0][0], Val::String("x".to_string()));
+}
+
+
+// Assert: Cached statements are checked again after the authorizer is set.
+#[test]
+fn test_authorizer_cached_stmt() {
+    let h = DbHandle::new(":memory:".to_string()).unwrap();
+    h.run("CREATE TABLE t1(a)").unwrap();
+    h.run("SELECT * FROM t1").unwrap();
+
+    h.set_authorizer(Some(Box::new(|c: &AuthContext| match c.action {
+        AuthAction::Read { .. } => Authorization::Deny,
+        _ => Authorization::Allow
+    })));
+
+    assert!(h.run("SELECT * FROM t1").is_err());
+    assert!(h.take_auth_denied().is_some());
+}
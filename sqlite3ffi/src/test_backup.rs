@@ -0,0 +1,78 @@
+use super::*;
+use std::time::Duration;
+use crate::backup::{Backup, BackupProgress};
+use crate::errmap::PrimaryRC;
+
+
+fn new_src(num_rows: i64) -> DbHandle {
+    let h = DbHandle::new(":memory:".to_string()).unwrap();
+    h.run("CREATE TABLE t1(id INTEGER PRIMARY KEY, data BLOB)").unwrap();
+
+    for i in 0..num_rows {
+        h.run_index("INSERT INTO t1 (id, data) VALUES (?, zeroblob(2000))", &vec![Val::I64(i)]).unwrap();
+    }
+    h
+}
+
+
+#[test]
+fn test_backup_progress() {
+    let src = new_src(20);
+    let dst = DbHandle::new(":memory:".to_string()).unwrap();
+    dst.run("CREATE TABLE old(a)").unwrap();
+
+    let mut all: Vec<BackupProgress> = vec![];
+    {
+        let b = Backup::new(&src, &dst).unwrap();
+        b.run(5, Duration::from_millis(0), Duration::from_secs(1), |p| all.push(p.clone())).unwrap();
+    }
+
+    // Assert: Progress is reported per step, ending with no remaining pages.
+    assert!(all.len() > 1);
+    let last = all.last().unwrap();
+    assert!(last.is_done);
+    assert_eq!(last.remaining, 0);
+    assert_eq!(all[0].remaining, last.page_count - 5);
+
+    // Assert: Destination is replaced by the source.
+    assert_eq!(dst.run("SELECT count(*) FROM t1").unwrap().rows.data[0][0], Val::I64(20));
+    assert!(dst.run("SELECT * FROM old").is_err());
+}
+
+
+#[test]
+fn test_backup_src_writes_during_backup() {
+    let src = new_src(20);
+    let dst = DbHandle::new(":memory:".to_string()).unwrap();
+
+    {
+        let b = Backup::new(&src, &dst).unwrap();
+        assert!(!b.step(2).unwrap().is_done);
+
+        // Assert: Writes using the source connection are included in the backup.
+        src.run("INSERT INTO t1 (id, data) VALUES (100, zeroblob(10))").unwrap();
+        assert!(b.step(-1).unwrap().is_done);
+    }
+
+    assert_eq!(dst.run("SELECT count(*) FROM t1").unwrap().rows.data[0][0], Val::I64(21));
+}
+
+
+// Assert: A lock held by another connection fails the backup with `SQLITE_BUSY` after `max_busy`, instead of retrying forever.
+#[test]
+fn test_backup_max_busy() {
+    let src = new_src(5);
+    let file = format!("/tmp/del-backup-busy-{}.sqlite3", std::process::id());
+    let dst = DbHandle::new(file.clone()).unwrap();
+    let other = DbHandle::new(file.clone()).unwrap();
+    other.run("BEGIN EXCLUSIVE").unwrap();
+
+    {
+        let b = Backup::new(&src, &dst).unwrap();
+        let e = b.run(5, Duration::from_millis(5), Duration::from_millis(50), |_| {}).unwrap_err();
+        assert_eq!(e.primary.id, PrimaryRC::SQLITE_BUSY);
+    }
+
+    other.run("ROLLBACK").unwrap();
+    std::fs::remove_file(&file).unwrap();
+}
@@ -0,0 +1,74 @@
+use super::*;
+use std::io::{Read, Write, Seek, SeekFrom};
+use crate::test_utils::new_mem_db;
+
+
+const SCHEMA: &str = "
+    CREATE TABLE t1(id INTEGER PRIMARY KEY, data BLOB, name TEXT);
+    INSERT INTO t1 (id, data, name) VALUES (1, zeroblob(10), 'a'), (2, x'0102030405', 'b');
+";
+
+
+#[test]
+fn test_blob_read_write_chunks() {
+    let h = new_mem_db(SCHEMA);
+
+    {
+        let b = h.open_blob("t1", "data", 1, true).unwrap();
+        assert_eq!(b.len(), 10);
+
+        b.write_at(0, &[1, 2, 3]).unwrap();
+        b.write_at(8, &[9, 10]).unwrap();
+        assert_eq!(b.read_at(2, 7).unwrap(), vec![3, 0, 0, 0, 0, 0, 9]);
+
+        // Assert: Blob size cannot change.
+        assert!(b.write_at(9, &[1, 2]).is_err());
+        assert!(b.read_at(5, 6).is_err());
+
+        // Assert: Lengths from a client are checked before allocating.
+        assert!(b.read_at(0, u32::MAX).is_err());
+        assert!(b.read_at(u32::MAX, 1).is_err());
+    }
+
+    let rset = h.run("SELECT data FROM t1 WHERE id = 1").unwrap();
+    assert_eq!(rset.rows.data[0][0], Val::Blob(vec![1, 2, 3, 0, 0, 0, 0, 0, 9, 10]));
+}
+
+
+#[test]
+fn test_blob_read_only_and_reopen() {
+    let h = new_mem_db(SCHEMA);
+
+    let mut b = h.open_blob("t1", "data", 2, false).unwrap();
+    assert_eq!(b.read_at(0, 5).unwrap(), vec![1, 2, 3, 4, 5]);
+
+    match b.write_at(0, &[1]) {
+        Err(rs) => assert_eq!(rs.primary.id, PrimaryRC::SQLITE_READONLY),
+        Ok(_) => assert!(false)
+    }
+
+    b.reopen(1).unwrap();
+    assert_eq!(b.len(), 10);
+
+    // Assert: Missing rows and `INTEGER PRIMARY KEY` columns cannot be opened.
+    assert!(h.open_blob("t1", "data", 3, false).is_err());
+    assert!(h.open_blob("t1", "id", 1, false).is_err());
+}
+
+
+#[test]
+fn test_blob_io_traits() {
+    let h = new_mem_db(SCHEMA);
+    let mut b = h.open_blob("t1", "data", 1, true).unwrap();
+
+    let mut src: &[u8] = &[7; 10];
+    assert_eq!(std::io::copy(&mut src, &mut b).unwrap(), 10);
+    assert!(b.write(&[1]).is_err());
+
+    b.seek(SeekFrom::Start(0)).unwrap();
+    let mut out = vec![];
+    b.read_to_end(&mut out).unwrap();
+    assert_eq!(out, vec![7; 10]);
+
+    assert!(b.seek(SeekFrom::End(1)).is_err());
+}
@@ -0,0 +1,65 @@
+use super::*;
+use std::rc::Rc;
+use std::cell::Cell;
+use crate::checkpoint::{
+    Checkpoint,
+    CheckpointMode,
+};
+
+static TEST_OUTPUT_DIR: &'static str = "/tmp";
+
+fn get_test_file() -> String {
+    let now: DateTime<Utc> = Utc::now();
+    format!("{}/del-test-checkpoint-{:?}.sqlite3", TEST_OUTPUT_DIR, now)
+}
+
+
+// Assert: A reader holding an old snapshot blocks the checkpoint until it ends.
+#[test]
+fn test_checkpoint() {
+    let file = get_test_file();
+    let w = DbHandle::new(file.clone()).unwrap();
+    w.run("PRAGMA journal_mode=WAL").unwrap();
+
+    let frames = Rc::new(Cell::new(0));
+    let f = frames.clone();
+    w.set_wal_hook(Some(Box::new(move |db_name, n| {
+        assert_eq!(db_name, "main");
+        f.set(n);
+    })));
+
+    w.run("CREATE TABLE t1(a)").unwrap();
+    w.run("INSERT INTO t1 (a) VALUES (1)").unwrap();
+    assert!(frames.get() > 0);
+
+    // Assert: The hook replaces the auto checkpoint, so nothing has been checkpointed yet.
+    let c = w.checkpoint(None, CheckpointMode::Passive).unwrap();
+    assert!(c.is_complete());
+    assert_eq!(c.wal_frames, frames.get());
+
+    let r = DbHandle::new(file.clone()).unwrap();
+    r.run("BEGIN").unwrap();
+    r.run("SELECT * FROM t1").unwrap();
+
+    w.run("INSERT INTO t1 (a) VALUES (2)").unwrap();
+
+    let c = w.checkpoint(Some("main"), CheckpointMode::Passive).unwrap();
+    assert!(!c.is_complete());
+    assert!(c.checkpointed_frames < c.wal_frames);
+
+    match w.checkpoint(None, CheckpointMode::Truncate).unwrap() {
+        Checkpoint { is_busy: true, .. } => {}
+        _ => assert!(false)
+    }
+
+    r.run("COMMIT").unwrap();
+    assert_eq!(w.checkpoint(None, CheckpointMode::Truncate).unwrap(), Checkpoint { wal_frames: 0, checkpointed_frames: 0, is_busy: false });
+
+    // Assert: Not possible inside a transaction on the same connection.
+    w.run("BEGIN").unwrap();
+    w.run("SELECT * FROM t1").unwrap();
+    match w.checkpoint(None, CheckpointMode::Passive) {
+        Err(rs) => assert_eq!(rs.primary.id, PrimaryRC::SQLITE_LOCKED),
+        Ok(_) => assert!(false)
+    }
+}
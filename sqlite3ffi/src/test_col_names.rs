@@ -0,0 +1,44 @@
+use super::*;
+use crate::stmt::{ColName, StorageClass};
+
+
+fn col(name: &str, origin: Option<&str>, decl_type: Option<&str>, storage_class: Option<StorageClass>) -> ColName {
+    let table_name = origin.map(|_| "t1".to_string());
+
+    ColName {
+        name: name.to_string(),
+        name_origin: origin.map(|s| s.to_string()),
+        db_name: table_name.as_ref().map(|_| "main".to_string()),
+        table_name,
+        decl_type: decl_type.map(|s| s.to_string()),
+        storage_class,
+        storage_classes: None,
+    }
+}
+
+
+#[test]
+fn test_col_names() {
+    let h = DbHandle::new(":memory:".to_string()).unwrap();
+    h.run("CREATE TABLE t1(a INTEGER PRIMARY KEY, b TEXT, c)").unwrap();
+    h.run("INSERT INTO t1 (a, b, c) VALUES (1, 'x', 1), (2, NULL, 'y')").unwrap();
+
+    let rset = h.run("SELECT a AS id, b, c, a + 1 FROM t1").unwrap();
+    assert_eq!(rset.num_cols, 4);
+    assert_eq!(rset.col_names, vec![
+        col("id", Some("a"), Some("INTEGER"), Some(StorageClass::Integer)),
+        // Assert: NULLs are ignored.
+        col("b", Some("b"), Some("TEXT"), Some(StorageClass::Text)),
+        // Assert: A column without a declared type can mix classes; each rows class is then listed.
+        ColName { storage_classes: Some(vec![StorageClass::Integer, StorageClass::Text]), ..col("c", Some("c"), None, None) },
+        col("a + 1", None, None, Some(StorageClass::Integer)),
+    ]);
+
+    // Assert: Metadata is set when there are no rows.
+    let rset = h.run("SELECT a AS id, b, c, a + 1 FROM t1 WHERE a > 10").unwrap();
+    assert_eq!((rset.num_rows, rset.num_cols), (0, 4));
+    assert_eq!(rset.col_names[0], col("id", Some("a"), Some("INTEGER"), None));
+
+    let rset = h.run("INSERT INTO t1 (b) VALUES ('z')").unwrap();
+    assert_eq!((rset.num_cols, rset.col_names.len()), (0, 0));
+}
@@ -0,0 +1,57 @@
+use super::*;
+use std::cmp::Ordering;
+use crate::collation::{
+    create_builtin_collations,
+    natural_cmp,
+    casefold_cmp,
+};
+
+
+fn names(h: &DbHandle, q: &str) -> Vec<Val> {
+    h.run(q).unwrap().rows.data.into_iter().map(|mut r| r.remove(0)).collect()
+}
+
+fn s(v: &str) -> Val {
+    Val::String(v.to_string())
+}
+
+
+#[test]
+fn test_natural_and_casefold_cmp() {
+    assert_eq!(natural_cmp("file2", "file10"), Ordering::Less);
+    assert_eq!(natural_cmp("file10", "file10b"), Ordering::Less);
+    assert_eq!(natural_cmp("a01", "a1"), Ordering::Greater);
+    assert_eq!(natural_cmp("a99999999999999999999999", "a100000000000000000000000"), Ordering::Less);
+    assert_eq!(natural_cmp("x", "x"), Ordering::Equal);
+
+    assert_eq!(casefold_cmp("ÄBC", "äbc"), Ordering::Equal);
+    assert_eq!(casefold_cmp("Émile", "emile"), Ordering::Greater);
+}
+
+
+#[test]
+fn test_builtin_collations() {
+    let h = DbHandle::new(":memory:".to_string()).unwrap();
+    create_builtin_collations(&h).unwrap();
+
+    h.run("CREATE TABLE t1(name TEXT COLLATE NATSORT)").unwrap();
+    h.run("INSERT INTO t1 (name) VALUES ('file10'), ('file2'), ('file1')").unwrap();
+
+    assert_eq!(names(&h, "SELECT name FROM t1 ORDER BY name"), vec![s("file1"), s("file2"), s("file10")]);
+    assert_eq!(names(&h, "SELECT name FROM t1 ORDER BY name COLLATE BINARY"), vec![s("file1"), s("file10"), s("file2")]);
+
+    assert_eq!(names(&h, "SELECT 'ÖL' = 'öl' COLLATE CASEFOLD"), vec![Val::I64(1)]);
+    assert_eq!(h.run("SELECT 'ÖL' = 'öl' COLLATE NOCASE").unwrap().rows.data[0][0], Val::I64(0));
+}
+
+
+#[test]
+fn test_custom_collation() {
+    let h = DbHandle::new(":memory:".to_string()).unwrap();
+
+    // Assert: Unknown collations are an error.
+    assert!(h.run("SELECT 'a' = 'b' COLLATE BY_LEN").is_err());
+
+    h.create_collation("BY_LEN", Box::new(|a, b| a.len().cmp(&b.len()))).unwrap();
+    assert_eq!(h.run("SELECT 'a' = 'b' COLLATE BY_LEN").unwrap().rows.data[0][0], Val::I64(1));
+}
@@ -0,0 +1,86 @@
+use super::*;
+
+
+fn new_db_with_rows(n: i64) -> DbHandle {
+    let h = DbHandle::new(":memory:".to_string()).unwrap();
+    h.run("CREATE TABLE t1(a INTEGER PRIMARY KEY, b TEXT, c BLOB);").unwrap();
+
+    for i in 0..n {
+        let vals = vec![
+            Val::I64(i),
+            Val::String(format!("row {}", i)),
+            Val::Blob(vec![i as u8, 1, 2]),
+        ];
+        h.run_index("INSERT INTO t1 (a, b, c) VALUES (?, ?, ?)", &vals).unwrap();
+    }
+
+    h
+}
+
+
+#[test]
+fn test_cursor_rows() {
+    let h = new_db_with_rows(5);
+    let s = StmtHandle::new(&h, "SELECT a, b FROM t1 ORDER BY a").unwrap();
+
+    let mut c = s.cursor();
+    let first = c.next().unwrap().unwrap();
+    assert_eq!(first, vec![Val::I64(0), Val::String("row 0".to_string())]);
+    assert_eq!(c.col_names()[1].name, "b");
+
+    let rest: Vec<Vec<Val>> = c.by_ref().map(|r| r.unwrap()).collect();
+    assert_eq!(rest.len(), 4);
+    assert!(c.is_done());
+    assert_eq!(c.num_rows(), 5);
+}
+
+
+#[test]
+fn test_cursor_stop_early() {
+    let h = new_db_with_rows(100);
+    let s = StmtHandle::new(&h, "SELECT a FROM t1 ORDER BY a").unwrap();
+
+    // Assert: Only the rows needed are read.
+    {
+        let taken: Vec<Vec<Val>> = s.cursor().take(3).map(|r| r.unwrap()).collect();
+        assert_eq!(taken, vec![vec![Val::I64(0)], vec![Val::I64(1)], vec![Val::I64(2)]]);
+    }
+
+    // Assert: Dropping the cursor resets the stmt so it starts from the first row again.
+    let rset = s.run().unwrap();
+    assert_eq!(rset.num_rows, 100);
+    assert_eq!(rset.rows.data[0][0], Val::I64(0));
+}
+
+
+#[test]
+fn test_cursor_rows_changed() {
+    let h = new_db_with_rows(5);
+    let s = StmtHandle::new(&h, "UPDATE t1 SET b = 'x' WHERE a < 3").unwrap();
+
+    let mut c = s.cursor();
+    assert!(c.next().is_none());
+    assert_eq!(c.rows_changed(), Some(3));
+}
+
+
+#[test]
+fn test_cursor_row_view() {
+    let h = new_db_with_rows(2);
+    let s = StmtHandle::new(&h, "SELECT a, b, c FROM t1 ORDER BY a").unwrap();
+
+    let mut c = s.cursor();
+    let mut n = 0;
+
+    while let Some(row) = c.next_view() {
+        let row = row.unwrap();
+        assert_eq!(row.num_cols(), 3);
+        assert_eq!(row.get(0), Val::I64(n));
+        assert_eq!(row.get_text(1), Some(format!("row {}", n).as_str()));
+        assert_eq!(row.get_text(0), None);
+        assert_eq!(row.get_blob(2), Some(&[n as u8, 1, 2][..]));
+        n += 1;
+    }
+
+    assert_eq!(n, 2);
+}
@@ -0,0 +1,32 @@
+use super::*;
+use crate::db_config::{
+    DbConfig,
+    DbConfigFlag,
+};
+
+
+#[test]
+fn test_db_config() {
+    let h = DbHandle::new(":memory:".to_string()).unwrap();
+    assert_eq!(h.get_db_config_flag(DbConfigFlag::Defensive).unwrap(), false);
+    assert_eq!(h.get_db_config_flag(DbConfigFlag::EnableTrigger).unwrap(), true);
+
+    h.run("CREATE TABLE t1(a)").unwrap();
+    h.run("PRAGMA writable_schema = ON").unwrap();
+    h.run("PRAGMA writable_schema = OFF").unwrap();
+
+    // Assert: `None` leaves the current value.
+    h.set_db_config(&DbConfig::secure().set(DbConfigFlag::EnableTrigger, false)).unwrap();
+    let c = h.get_db_config().unwrap();
+    assert_eq!(c.defensive, Some(true));
+    assert_eq!(c.enable_trigger, Some(false));
+    assert_eq!(c.enable_load_extension, Some(false));
+    // Note: `SQLITE_DEFAULT_FOREIGN_KEYS=1` (see `build.rs`).
+    assert_eq!(c.enable_fkey, Some(true));
+
+    // Assert: Defensive mode ignores `writable_schema`, so `sqlite_master` is read only.
+    h.run("PRAGMA writable_schema = ON").unwrap();
+    assert!(h.run("UPDATE sqlite_master SET sql = 'x'").is_err());
+
+    assert_eq!(h.set_db_config_flag(DbConfigFlag::Defensive, false).unwrap(), false);
+}
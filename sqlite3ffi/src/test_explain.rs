@@ -0,0 +1,28 @@
+use super::*;
+
+
+#[test]
+fn test_explain_query_plan() {
+    let h = DbHandle::new(":memory:".to_string()).unwrap();
+    h.run("CREATE TABLE t1(a, b)").unwrap();
+    h.run("CREATE TABLE t2(a, b)").unwrap();
+    h.run("CREATE INDEX t1_a ON t1(a)").unwrap();
+
+    let p = h.explain_query_plan("SELECT * FROM t1 WHERE a = :a").unwrap();
+    assert_eq!(p.nodes.len(), 1);
+    assert!(p.nodes[0].detail.starts_with("SEARCH TABLE t1 USING INDEX t1_a"));
+    assert!(!p.has_full_scan);
+
+    // Assert: Nested nodes are children of their parent; the full scan is flagged.
+    let p = h.explain_query_plan("SELECT * FROM t1 WHERE a IN (SELECT a FROM t2 WHERE b = 1)").unwrap();
+    assert!(p.has_full_scan);
+
+    let list = p.nodes.iter().find(|n| n.detail.contains("LIST SUBQUERY")).unwrap();
+    assert_eq!(list.children.len(), 1);
+    assert_eq!(list.children[0].parent, list.id);
+    assert_eq!(list.children[0].detail, "SCAN TABLE t2");
+    assert!(list.children[0].is_full_scan);
+    assert!(!list.is_full_scan);
+
+    assert!(h.explain_query_plan("SELECT * FROM missing").is_err());
+}
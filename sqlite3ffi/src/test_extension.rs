@@ -0,0 +1,38 @@
+use super::*;
+use std::fs;
+use crate::errmap::PrimaryRC;
+use crate::auth::is_not_authorized;
+use crate::db_config::DbConfigFlag;
+use crate::extension::set_extension_allowlist;
+
+
+// Note: The allowlist is process wide; only this test sets it.
+#[test]
+fn test_load_extension_allowlist() {
+    let dir = format!("/tmp/del-ext-{}", std::process::id());
+    fs::create_dir_all(&dir).unwrap();
+    let file = format!("{}/not_an_extension.so", dir);
+    fs::write(&file, "x").unwrap();
+
+    let h = DbHandle::new(":memory:".to_string()).unwrap();
+
+    let e = h.load_extension(&file, None).unwrap_err();
+    assert_eq!(e.primary.id, PrimaryRC::SQLITE_AUTH);
+    assert!(is_not_authorized(&e));
+
+    assert!(set_extension_allowlist(&[format!("{}/missing.so", dir)]).is_err());
+    set_extension_allowlist(&[file.clone()]).unwrap();
+
+    // Assert: Allowed paths are passed to SQLite, which rejects files that are not shared libraries.
+    h.set_db_config_flag(DbConfigFlag::EnableLoadExtension, true).unwrap();
+    let e = h.load_extension(&format!("{}/../{}/not_an_extension.so", dir, dir.trim_start_matches("/tmp/")), Some("init")).unwrap_err();
+    assert_eq!(e.primary.id, PrimaryRC::SQLITE_ERROR);
+    assert!(e.err_msg.is_some());
+
+    // Assert: The C API flag is restored, but SQL still cannot load extensions.
+    assert_eq!(h.get_db_config_flag(DbConfigFlag::EnableLoadExtension).unwrap(), true);
+    assert!(h.run(&format!("SELECT load_extension('{}')", file)).is_err());
+
+    set_extension_allowlist(&[]).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+}
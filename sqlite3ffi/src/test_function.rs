@@ -0,0 +1,63 @@
+use super::*;
+
+
+#[test]
+fn test_scalar_function() {
+    let h = DbHandle::new(":memory:".to_string()).unwrap();
+
+    h.create_scalar_function("rs_concat", -1, true, Box::new(|args| {
+        let s: Vec<String> = args.iter().map(|v| match v {
+            Val::I64(i) => i.to_string(),
+            Val::String(s) => s.clone(),
+            Val::Null => "null".to_string(),
+            _ => "?".to_string(),
+        }).collect();
+
+        Ok(Val::String(s.join(",")))
+    })).unwrap();
+
+    h.create_scalar_function("rs_blob_len", 1, true, Box::new(|args| {
+        match &args[0] {
+            Val::Blob(b) => Ok(Val::I64(b.len() as i64)),
+            _ => Err("Expected a blob.".to_string())
+        }
+    })).unwrap();
+
+    let rset = h.run("SELECT rs_concat(1, 'a', NULL), rs_concat(), rs_blob_len(x'010203'), rs_blob_len(zeroblob(0))").unwrap();
+    assert_eq!(rset.rows.data[0], vec![
+        Val::String("1,a,null".to_string()),
+        Val::String("".to_string()),
+        Val::I64(3),
+        Val::I64(0),
+    ]);
+
+    // Assert: `Err` is returned as a SQL error with the message.
+    match h.run("SELECT rs_blob_len(1)") {
+        Err(rs) => {
+            assert_eq!(rs.primary.id, PrimaryRC::SQLITE_ERROR);
+            assert!(rs.err_msg.unwrap().contains("Expected a blob."));
+        }
+        Ok(_) => assert!(false)
+    }
+
+    // Assert: Arity is enforced by SQLite.
+    assert!(h.run("SELECT rs_blob_len(1, 2)").is_err());
+}
+
+
+#[test]
+fn test_scalar_function_panic() {
+    let h = DbHandle::new(":memory:".to_string()).unwrap();
+
+    h.create_scalar_function("rs_panic", 0, false, Box::new(|_| {
+        panic!("Panic inside a SQL function.");
+    })).unwrap();
+
+    match h.run("SELECT rs_panic()") {
+        Err(rs) => assert!(rs.err_msg.unwrap().contains("panicked")),
+        Ok(_) => assert!(false)
+    }
+
+    // Assert: The connection is still usable after a panic.
+    assert_eq!(h.run("SELECT 1").unwrap().rows.data[0][0], Val::I64(1));
+}
@@ -0,0 +1,144 @@
+use super::*;
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::time::Duration;
+use crate::hooks::{RowChange, ChangeOp, PreUpdate};
+
+
+fn ch(table: &str, op: ChangeOp, rowid: i64) -> RowChange {
+    RowChange { table: table.to_string(), op, rowid }
+}
+
+
+#[test]
+fn test_update_hook() {
+    let h = DbHandle::new(":memory:".to_string()).unwrap();
+    h.run("CREATE TABLE t1(id INTEGER PRIMARY KEY, a)").unwrap();
+
+    let changes = Rc::new(RefCell::new(vec![]));
+    let c = changes.clone();
+    h.set_update_hook(Some(Box::new(move |rc| c.borrow_mut().push(rc.clone()))));
+
+    h.run("INSERT INTO t1 (id, a) VALUES (1, 'a'), (2, 'b')").unwrap();
+    h.run("UPDATE t1 SET a = 'c' WHERE id = 2").unwrap();
+    h.run("DELETE FROM t1 WHERE id = 1").unwrap();
+
+    assert_eq!(*changes.borrow(), vec![
+        ch("t1", ChangeOp::Insert, 1),
+        ch("t1", ChangeOp::Insert, 2),
+        ch("t1", ChangeOp::Update, 2),
+        ch("t1", ChangeOp::Delete, 1),
+    ]);
+
+    // Assert: Removed hooks are not called.
+    h.set_update_hook(None);
+    h.run("INSERT INTO t1 (id, a) VALUES (3, 'd')").unwrap();
+    assert_eq!(changes.borrow().len(), 4);
+}
+
+
+#[test]
+fn test_commit_and_rollback_hooks() {
+    let h = DbHandle::new(":memory:".to_string()).unwrap();
+    h.run("CREATE TABLE t1(a)").unwrap();
+
+    let rollbacks = Rc::new(RefCell::new(0));
+    let r = rollbacks.clone();
+    h.set_rollback_hook(Some(Box::new(move || *r.borrow_mut() += 1)));
+
+    // Assert: Returning `true` from the commit hook turns the commit into a rollback.
+    h.set_commit_hook(Some(Box::new(|| true)));
+    h.run("BEGIN").unwrap();
+    h.run("INSERT INTO t1 (a) VALUES (1)").unwrap();
+
+    match h.run("COMMIT") {
+        Err(rs) => assert_eq!(rs.primary.id, PrimaryRC::SQLITE_CONSTRAINT),
+        Ok(_) => assert!(false)
+    }
+    assert_eq!(*rollbacks.borrow(), 1);
+
+    h.set_commit_hook(Some(Box::new(|| false)));
+    h.run("INSERT INTO t1 (a) VALUES (2)").unwrap();
+    h.run("BEGIN").unwrap();
+    h.run("ROLLBACK").unwrap();
+
+    assert_eq!(*rollbacks.borrow(), 2);
+    assert_eq!(h.run("SELECT a FROM t1").unwrap().rows.data, vec![vec![Val::I64(2)]]);
+}
+
+
+#[test]
+fn test_preupdate_hook() {
+    let h = DbHandle::new(":memory:".to_string()).unwrap();
+    h.run("CREATE TABLE t1(id INTEGER PRIMARY KEY, a, b)").unwrap();
+    h.run("CREATE TABLE t2(k TEXT PRIMARY KEY, v) WITHOUT ROWID").unwrap();
+
+    let changes: Rc<RefCell<Vec<PreUpdate>>> = Rc::new(RefCell::new(vec![]));
+    let c = changes.clone();
+    h.set_preupdate_hook(Some(Box::new(move |pu| c.borrow_mut().push(pu.clone())))).unwrap();
+
+    h.run("INSERT INTO t1 (id, a, b) VALUES (1, 'a', 10)").unwrap();
+    h.run("UPDATE t1 SET b = 11 WHERE id = 1").unwrap();
+    h.run("DELETE FROM t1 WHERE id = 1").unwrap();
+
+    {
+        let c = changes.borrow();
+        assert_eq!(c.len(), 3);
+
+        assert_eq!((&c[0].op, c[0].old.as_ref(), c[0].new_rowid), (&ChangeOp::Insert, None, Some(1)));
+        assert_eq!(c[0].new, Some(vec![Val::I64(1), Val::String("a".to_string()), Val::I64(10)]));
+
+        assert_eq!(c[1].op, ChangeOp::Update);
+        assert_eq!(c[1].old.as_ref().unwrap()[2], Val::I64(10));
+        assert_eq!(c[1].new.as_ref().unwrap()[2], Val::I64(11));
+        assert_eq!(c[1].changed_columns(), vec![2]);
+
+        assert_eq!((&c[2].op, c[2].new.as_ref(), c[2].old_rowid), (&ChangeOp::Delete, None, Some(1)));
+        assert_eq!((c[2].db_name.as_str(), c[2].table.as_str(), c[2].depth), ("main", "t1", 0));
+    }
+
+    // Assert: Unlike the update hook, called for `WITHOUT ROWID` tables.
+    h.run("INSERT INTO t2 (k, v) VALUES ('x', 1)").unwrap();
+    assert_eq!(changes.borrow()[3].new, Some(vec![Val::String("x".to_string()), Val::I64(1)]));
+
+    h.set_preupdate_hook(None).unwrap();
+    h.run("DELETE FROM t2").unwrap();
+    assert_eq!(changes.borrow().len(), 4);
+}
+
+
+#[test]
+fn test_with_timeout() {
+    let h = DbHandle::new(":memory:".to_string()).unwrap();
+    h.run("CREATE TABLE t1(id INTEGER PRIMARY KEY, a)").unwrap();
+    let forever = "WITH RECURSIVE c(x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM c) SELECT count(*) FROM c";
+
+    // Assert: A read that never ends is interrupted, but the tx stays open.
+    h.run("BEGIN").unwrap();
+    h.run("INSERT INTO t1 (a) VALUES (1)").unwrap();
+
+    let (r, is_timeout) = h.with_timeout(Duration::from_millis(20), || h.run(forever));
+    assert!(is_timeout);
+    match r {
+        Err(rs) => assert_eq!(rs.primary.id, PrimaryRC::SQLITE_INTERRUPT),
+        Ok(_) => assert!(false)
+    }
+    assert!(!h.is_autocommit());
+
+    // Assert: The handler is removed afterwards.
+    let (r, is_timeout) = h.with_timeout(Duration::from_millis(20), || h.run("SELECT count(*) FROM t1"));
+    assert!(!is_timeout);
+    assert_eq!(r.unwrap().rows.data, vec![vec![Val::I64(1)]]);
+
+    // Assert: An interrupted write rolls back the whole tx.
+    let (r, is_timeout) = h.with_timeout(Duration::from_millis(20), || h.run(&format!("INSERT INTO t1 (a) {}", forever)));
+    assert!(is_timeout && r.is_err());
+    assert!(h.is_autocommit());
+    assert_eq!(h.run("SELECT count(*) FROM t1").unwrap().rows.data, vec![vec![Val::I64(0)]]);
+
+    // Assert: Returning `true` from a progress handler interrupts.
+    h.set_progress_handler(1, Some(Box::new(|| true)));
+    assert!(h.run("SELECT 1").is_err());
+    h.set_progress_handler(0, None);
+    assert!(h.run("SELECT 1").is_ok());
+}
@@ -0,0 +1,59 @@
+use super::*;
+use crate::limits::{
+    Limit,
+    Limits,
+    get_limit_exceeded,
+    set_soft_heap_limit,
+};
+
+
+fn get_exceeded(h: &DbHandle, q: &str) -> Option<Limit> {
+    match h.run(q) {
+        Err(rs) => get_limit_exceeded(&rs),
+        Ok(_) => panic!("Expected `{}` to fail", q)
+    }
+}
+
+
+#[test]
+fn test_limits() {
+    let h = DbHandle::new(":memory:".to_string()).unwrap();
+    let default_attached = h.get_limit(Limit::Attached);
+    assert_eq!(default_attached, 10);
+
+    // Assert: `None` leaves the current value.
+    h.set_limits(&Limits { attached: Some(0), sql_length: Some(100), ..Default::default() });
+    assert_eq!(h.get_limit(Limit::Attached), 0);
+    assert_eq!(h.get_limit(Limit::SqlLength), 100);
+    assert_eq!(h.get_limits().column, Some(2000));
+
+    // Assert: Values above the compile time maximum are truncated.
+    assert_eq!(h.set_limit(Limit::Attached, 1000), 0);
+    assert_eq!(h.get_limit(Limit::Attached), 10);
+    h.set_limit(Limit::Attached, 0);
+
+    assert_eq!(get_exceeded(&h, "ATTACH ':memory:' AS other"), Some(Limit::Attached));
+    assert_eq!(get_exceeded(&h, &format!("SELECT '{}'", "x".repeat(100))), Some(Limit::Length));
+
+    h.set_limits(&Limits::untrusted());
+    assert_eq!(get_exceeded(&h, "SELECT zeroblob(2000000)"), Some(Limit::Length));
+    assert_eq!(get_exceeded(&h, "SELECT 1 UNION SELECT 2 UNION SELECT 3 UNION SELECT 4"), Some(Limit::CompoundSelect));
+    assert_eq!(get_exceeded(&h, &format!("SELECT 1{}", " + 1".repeat(20))), Some(Limit::ExprDepth));
+    assert_eq!(get_exceeded(&h, "SELECT max(1, 2, 3, 4, 5, 6, 7, 8, 9)"), Some(Limit::FunctionArg));
+    assert_eq!(get_exceeded(&h, "SELECT ?101"), Some(Limit::VariableNumber));
+    assert_eq!(get_exceeded(&h, &format!("SELECT 'a' LIKE '{}'", "%".repeat(51))), Some(Limit::LikePatternLength));
+
+    let cols: Vec<String> = (0..101).map(|i| format!("c{}", i)).collect();
+    assert_eq!(get_exceeded(&h, &format!("CREATE TABLE t1({})", cols.join(", "))), Some(Limit::Column));
+
+    // Assert: Other errors are not limit errors.
+    assert_eq!(get_exceeded(&h, "SELECT * FROM missing"), None);
+}
+
+
+#[test]
+fn test_soft_heap_limit() {
+    let prev = set_soft_heap_limit(64 * 1024 * 1024);
+    assert_eq!(set_soft_heap_limit(-1), 64 * 1024 * 1024);
+    set_soft_heap_limit(prev);
+}
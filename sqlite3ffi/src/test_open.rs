@@ -0,0 +1,77 @@
+use super::*;
+use chrono::{DateTime, Utc};
+use crate::open::{OpenFlags, OpenMode, Threading};
+
+
+fn get_test_file() -> String {
+    let now: DateTime<Utc> = Utc::now();
+    format!("/tmp/del-test-open-{:?}.sqlite3", now)
+}
+
+
+#[test]
+fn test_open_missing_file() {
+    let file = get_test_file();
+
+    // Assert: Without `CREATE` a missing file is an error, not a new empty database.
+    let flags = OpenFlags {
+        mode: OpenMode::ReadWrite,
+        ..Default::default()
+    };
+    match DbHandle::new_with_flags(file.clone(), &flags) {
+        Err(rs) => assert_eq!(rs.primary.id, PrimaryRC::SQLITE_CANTOPEN),
+        Ok(_) => assert!(false)
+    }
+
+    match DbHandle::new_with_flags(file.clone(), &OpenFlags::read_only()) {
+        Err(rs) => assert_eq!(rs.primary.id, PrimaryRC::SQLITE_CANTOPEN),
+        Ok(_) => assert!(false)
+    }
+
+    assert!(!std::path::Path::new(&file).exists());
+}
+
+
+#[test]
+fn test_open_read_only() {
+    let file = get_test_file();
+
+    {
+        let h = DbHandle::new(file.clone()).unwrap();
+        h.run("CREATE TABLE t1(a)").unwrap();
+    }
+
+    let flags = OpenFlags {
+        threading: Some(Threading::NoMutex),
+        ..OpenFlags::read_only()
+    };
+    let h = DbHandle::new_with_flags(file.clone(), &flags).unwrap();
+
+    assert_eq!(h.run("SELECT count(*) FROM t1").unwrap().rows.data[0][0], Val::I64(0));
+
+    match h.run("INSERT INTO t1 VALUES (1)") {
+        Err(rs) => assert_eq!(rs.primary.id, PrimaryRC::SQLITE_READONLY),
+        Ok(_) => assert!(false)
+    }
+}
+
+
+#[test]
+fn test_open_uri_and_vfs() {
+    let file = get_test_file();
+    DbHandle::new(file.clone()).unwrap();
+
+    // Assert: `mode=ro` in the URI is only applied when `uri` is set.
+    let flags = OpenFlags {
+        uri: true,
+        ..Default::default()
+    };
+    let h = DbHandle::new_with_flags(format!("file:{}?mode=ro", file), &flags).unwrap();
+    assert!(h.run("CREATE TABLE t1(a)").is_err());
+
+    let flags = OpenFlags {
+        vfs: Some("does-not-exist".to_string()),
+        ..Default::default()
+    };
+    assert!(DbHandle::new_with_flags(file.clone(), &flags).is_err());
+}
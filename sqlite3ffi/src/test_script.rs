@@ -0,0 +1,76 @@
+use super::*;
+use crate::script::ScriptError;
+
+
+#[test]
+fn test_script_all_statements_run() {
+    let h = DbHandle::new(":memory:".to_string()).unwrap();
+
+    let q = "
+        CREATE TABLE t1(a PRIMARY KEY, b);
+        CREATE TABLE t2(a PRIMARY KEY, b);
+        INSERT INTO t1 (a, b) VALUES (1, 2), (3, 4);
+        SELECT * FROM t1;
+        -- Trailing comment.
+    ";
+
+    let rsets = h.exec_script(q).unwrap();
+    assert_eq!(rsets.len(), 4);
+    assert_eq!(rsets[2].rows_changed, Some(2));
+    assert_eq!(rsets[3].num_rows, 2);
+
+    // Assert: `run` still only runs the first statement.
+    h.run("CREATE TABLE t3(a); CREATE TABLE t4(a);").unwrap();
+
+    let rset = h.run("SELECT name FROM sqlite_master WHERE type = 'table' ORDER BY name").unwrap();
+    assert_eq!(rset.num_rows, 3);
+}
+
+
+#[test]
+fn test_script_empty() {
+    let h = DbHandle::new(":memory:".to_string()).unwrap();
+
+    assert_eq!(h.exec_script("").unwrap().len(), 0);
+    assert_eq!(h.exec_script("  ;; -- x\n").unwrap().len(), 0);
+    assert_eq!(h.exec_script(";; SELECT 1;; SELECT 2").unwrap().len(), 2);
+}
+
+
+#[test]
+fn test_script_is_read_only() {
+    let h = DbHandle::new(":memory:".to_string()).unwrap();
+    h.run("CREATE TABLE t1(a)").unwrap();
+
+    assert!(h.is_read_only_script("SELECT 1; SELECT * FROM t1;").unwrap());
+    assert!(h.is_read_only_script("").unwrap());
+    assert!(!h.is_read_only_script("SELECT 1; INSERT INTO t1 VALUES (1);").unwrap());
+
+    // Assert: Statements after the first write are not prepared (`t2` does not exist yet).
+    assert!(!h.is_read_only_script("CREATE TABLE t2(a); SELECT * FROM t2;").unwrap());
+    assert_eq!(h.exec_script("SELECT * FROM t1").unwrap()[0].num_rows, 0);
+}
+
+
+#[test]
+fn test_script_error_offset() {
+    let h = DbHandle::new(":memory:".to_string()).unwrap();
+
+    // Prepare error (no such table).
+    let q = "CREATE TABLE t1(a);\n  INSERT INTO t1 VALUES (1);\n  SELECT * FROM t2;\n  INSERT INTO t1 VALUES (2);";
+    let e = h.exec_script(q).unwrap_err();
+
+    assert_eq!((e.index, e.offset), (2, q.find("SELECT").unwrap()));
+    assert_eq!(e.return_status.primary.id, PrimaryRC::SQLITE_ERROR);
+
+    // Assert: Statements before the failure ran, statements after did not.
+    let rset = h.run("SELECT count(*) FROM t1").unwrap();
+    assert_eq!(rset.rows.data[0][0], Val::I64(1));
+
+    // Run error (constraint).
+    let q = "CREATE TABLE t2(a PRIMARY KEY); INSERT INTO t2 VALUES (1); INSERT INTO t2 VALUES (1);";
+    match h.exec_script(q) {
+        Err(ScriptError { index: 2, offset, .. }) => assert_eq!(offset, q.rfind("INSERT").unwrap()),
+        x => assert!(false, "{:?}", x)
+    }
+}
@@ -0,0 +1,116 @@
+use super::*;
+use serde::{Deserialize, Serialize};
+use chrono::NaiveDate;
+use uuid::Uuid;
+use crate::de::from_row;
+use crate::ser::to_key_val;
+use crate::test_utils::new_mem_db;
+
+
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Status {
+    Active,
+    Closed,
+}
+
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Serialize, Deserialize)]
+struct Account {
+    id: Uuid,
+    name: String,
+    balance: f64,
+    is_admin: bool,
+    status: Status,
+    avatar: Option<Vec<u8>>,
+    created_at: DateTime<Utc>,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    updated_at: DateTime<Utc>,
+}
+
+
+const SCHEMA: &str = "
+    CREATE TABLE accounts(id TEXT PRIMARY KEY, name TEXT, balance REAL, is_admin INTEGER, status TEXT, avatar BLOB, created_at TEXT, updated_at INTEGER);
+";
+
+
+// Assert: A struct can be written with `to_key_val` and read back with `into_iter`.
+#[test]
+fn test_serde_round_trip() {
+    let h = new_mem_db(SCHEMA);
+    let t = DateTime::<Utc>::from_utc(NaiveDate::from_ymd(2020, 1, 2).and_hms(3, 4, 5), Utc);
+
+    let a = Account {
+        id: Uuid::parse_str("936da01f-9abd-4d9d-80c7-02af85c822a8").unwrap(),
+        name: "a".to_string(),
+        balance: 10.0,
+        is_admin: true,
+        status: Status::Active,
+        avatar: Some(vec![1, 2, 3]),
+        created_at: t,
+        updated_at: t,
+    };
+
+    let kv = to_key_val(&a).unwrap();
+    assert_eq!(kv.data["id"], Val::String("936da01f-9abd-4d9d-80c7-02af85c822a8".to_string()));
+    assert_eq!(kv.data["is_admin"], Val::I64(1));
+    assert_eq!(kv.data["status"], Val::String("active".to_string()));
+    assert_eq!(kv.data["avatar"], Val::Blob(vec![1, 2, 3]));
+    assert_eq!(kv.data["created_at"], Val::String("2020-01-02T03:04:05Z".to_string()));
+    assert_eq!(kv.data["updated_at"], Val::I64(1577934245));
+
+    let q = "INSERT INTO accounts VALUES (:id, :name, :balance, :is_admin, :status, :avatar, :created_at, :updated_at)";
+    h.run_kv(q, &kv).unwrap();
+
+    // Assert: REAL columns store `10.0` as a float; `balance` also reads integers.
+    h.run("INSERT INTO accounts VALUES ('2c5ea4c0-4067-11e9-8bad-9b1deb4d3b7d', 'b', 5, 0, 'closed', NULL, '2020-01-02T03:04:05+00:00', 1577934245)").unwrap();
+
+    let rows: Vec<Account> = h.run("SELECT * FROM accounts ORDER BY name").unwrap().into_iter().map(|r| r.unwrap()).collect();
+    assert_eq!(rows[0], a);
+    assert_eq!((rows[1].balance, rows[1].is_admin, &rows[1].status, &rows[1].avatar), (5.0, false, &Status::Closed, &None));
+}
+
+
+#[test]
+fn test_serde_rows() {
+    let h = new_mem_db(SCHEMA);
+    h.run("INSERT INTO accounts (id, name, balance) VALUES ('x', 'a', 1.5), ('y', 'b', NULL)").unwrap();
+
+    // Assert: Tuples are read by column position.
+    let rset = h.run("SELECT name, balance FROM accounts ORDER BY name").unwrap();
+    let rows: Vec<(String, Option<f64>)> = rset.into_iter().map(|r| r.unwrap()).collect();
+    assert_eq!(rows, vec![("a".to_string(), Some(1.5)), ("b".to_string(), None)]);
+
+    // Assert: Structs are read by column name; extra columns are ignored.
+    #[derive(Debug)]
+    #[derive(Deserialize)]
+    struct Named {
+        n: String,
+    }
+    let rset = h.run("SELECT id, name AS n FROM accounts ORDER BY name").unwrap();
+    assert_eq!(from_row::<Named>(&rset.col_names, &rset.rows.data[1]).unwrap().n, "b");
+
+    // Assert: Type and missing column errors.
+    let e = h.run("SELECT name FROM accounts").unwrap().into_iter::<(i64,)>().next().unwrap();
+    assert!(e.is_err());
+    let e = h.run("SELECT id FROM accounts").unwrap().into_iter::<Named>().next().unwrap();
+    assert_eq!(e.unwrap_err().msg, "missing field `n`");
+
+    // Assert: Only structs and maps become placeholder data.
+    assert!(to_key_val(&1).is_err());
+    assert!(to_key_val(&Some(vec![vec![1]])).is_err());
+
+    // Assert: Only `u8` sequences become a BLOB; other element types are an error, not a BLOB.
+    #[derive(Serialize)]
+    struct Seqs<T> {
+        v: Vec<T>,
+    }
+    assert_eq!(to_key_val(&Seqs { v: vec![1u8, 255] }).unwrap().data["v"], Val::Blob(vec![1, 255]));
+    assert!(to_key_val(&Seqs { v: vec![1i64, 2] }).unwrap_err().msg.starts_with("Only sequences of `u8`"));
+    assert!(to_key_val(&Seqs { v: vec![1u16] }).is_err());
+    assert!(to_key_val(&Seqs { v: vec!["a"] }).is_err());
+    assert!(to_key_val(&Seqs { v: vec![vec![1u8]] }).is_err());
+}
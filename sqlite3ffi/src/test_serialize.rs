@@ -0,0 +1,56 @@
+use super::*;
+use std::time::Duration;
+use crate::backup::Backup;
+
+static TEST_OUTPUT_DIR: &'static str = "/tmp";
+
+fn get_test_file() -> String {
+    let now: DateTime<Utc> = Utc::now();
+    format!("{}/del-test-serialize-{:?}.sqlite3", TEST_OUTPUT_DIR, now)
+}
+
+
+// Assert: An image of a WAL mode file can be read in memory, and written back to another WAL mode file.
+#[test]
+fn test_serialize_deserialize() {
+    let src = DbHandle::new(get_test_file()).unwrap();
+    assert_eq!(src.serialize(None).unwrap(), Vec::<u8>::new());
+
+    src.run("PRAGMA journal_mode=WAL").unwrap();
+    src.run("CREATE TABLE t1(a)").unwrap();
+    src.run("INSERT INTO t1 (a) VALUES (1), (2)").unwrap();
+
+    let img = src.serialize(None).unwrap();
+    assert_eq!(img.len() % 4096, 0);
+    assert_eq!(&img[0..16], b"SQLite format 3\0");
+    assert!(src.serialize(Some("other")).is_err());
+
+    let mem = DbHandle::new(":memory:".to_string()).unwrap();
+    mem.deserialize(None, &img).unwrap();
+    assert_eq!(mem.run("SELECT count(*) FROM t1").unwrap().rows.data[0][0], Val::I64(2));
+
+    // Assert: The in-memory copy can grow past the size of the image.
+    mem.run("INSERT INTO t1 (a) VALUES (zeroblob(10000))").unwrap();
+
+    let dst = DbHandle::new(get_test_file()).unwrap();
+    dst.run("PRAGMA journal_mode=WAL").unwrap();
+    dst.run("CREATE TABLE old(a)").unwrap();
+    Backup::new(&mem, &dst).unwrap().run(-1, Duration::from_millis(0), Duration::from_secs(1), |_| {}).unwrap();
+
+    assert_eq!(dst.run("SELECT count(*) FROM t1").unwrap().rows.data[0][0], Val::I64(3));
+    assert!(dst.run("SELECT * FROM old").is_err());
+    assert_eq!(dst.run("PRAGMA journal_mode").unwrap().rows.data[0][0], Val::String("wal".to_string()));
+
+    // Assert: The source file is unchanged.
+    assert_eq!(src.run("SELECT count(*) FROM t1").unwrap().rows.data[0][0], Val::I64(2));
+}
+
+
+#[test]
+fn test_deserialize_invalid() {
+    let h = DbHandle::new(":memory:".to_string()).unwrap();
+
+    // Assert: The image is only read when queried.
+    h.deserialize(None, b"not a database").unwrap();
+    assert!(h.run("SELECT * FROM sqlite_master").is_err());
+}
@@ -0,0 +1,153 @@
+use super::*;
+use crate::hooks::ChangeOp;
+use crate::session::{
+    ConflictType,
+    ConflictAction,
+    Conflict,
+    invert_changeset,
+};
+use crate::test_utils::new_mem_db;
+use crate::errmap::PrimaryRC;
+
+
+const SCHEMA: &str = "
+    CREATE TABLE t1(id INTEGER PRIMARY KEY, a);
+    CREATE TABLE t2(id INTEGER PRIMARY KEY, b);
+";
+
+fn rows(h: &DbHandle, q: &str) -> Vec<Vec<Val>> {
+    h.run(q).unwrap().rows.data
+}
+
+
+#[test]
+fn test_changeset_apply() {
+    let src = new_mem_db(SCHEMA);
+    let dst = new_mem_db(SCHEMA);
+
+    let s = src.create_session().unwrap();
+    s.attach(Some("t1")).unwrap();
+    assert!(s.is_empty());
+
+    src.run("INSERT INTO t1 (id, a) VALUES (1, 'a'), (2, 'b')").unwrap();
+    src.run("UPDATE t1 SET a = 'c' WHERE id = 2").unwrap();
+    src.run("INSERT INTO t2 (id, b) VALUES (1, 'not attached')").unwrap();
+
+    let cs = s.changeset().unwrap();
+    let ps = s.patchset().unwrap();
+    assert!(!s.is_empty());
+    assert!(ps.len() <= cs.len());
+
+    dst.apply_changeset(&cs, &mut |_| ConflictAction::Abort).unwrap();
+
+    assert_eq!(rows(&dst, "SELECT id, a FROM t1"), vec![
+        vec![Val::I64(1), Val::String("a".to_string())],
+        vec![Val::I64(2), Val::String("c".to_string())],
+    ]);
+    assert_eq!(rows(&dst, "SELECT count(*) FROM t2"), vec![vec![Val::I64(0)]]);
+
+    drop(s);
+
+    // Assert: An empty session produces an empty changeset.
+    let s2 = dst.create_session().unwrap();
+    s2.attach(None).unwrap();
+    assert_eq!(s2.changeset().unwrap(), Vec::<u8>::new());
+}
+
+
+#[test]
+fn test_changeset_conflicts() {
+    let src = new_mem_db(SCHEMA);
+    let s = src.create_session().unwrap();
+    s.attach(None).unwrap();
+    src.run("INSERT INTO t1 (id, a) VALUES (1, 'from_src')").unwrap();
+    let cs = s.changeset().unwrap();
+
+    let dst = new_mem_db(SCHEMA);
+    dst.run("INSERT INTO t1 (id, a) VALUES (1, 'from_dst'), (2, 'x')").unwrap();
+
+    // Assert: Omit keeps the existing row.
+    let mut seen = vec![];
+    dst.apply_changeset(&cs, &mut |c| {
+        seen.push(c.clone());
+        ConflictAction::Omit
+    }).unwrap();
+
+    assert_eq!(seen, vec![Conflict { kind: ConflictType::Conflict, table: Some("t1".to_string()), op: Some(ChangeOp::Insert) }]);
+    assert_eq!(rows(&dst, "SELECT a FROM t1 WHERE id = 1"), vec![vec![Val::String("from_dst".to_string())]]);
+
+    // Assert: Abort returns an error.
+    match dst.apply_changeset(&cs, &mut |_| ConflictAction::Abort) {
+        Err(rs) => assert_eq!(rs.primary.id, PrimaryRC::SQLITE_ABORT),
+        Ok(_) => assert!(false)
+    }
+
+    // Assert: Replace overwrites the existing row.
+    dst.apply_changeset(&cs, &mut |_| ConflictAction::Replace).unwrap();
+    assert_eq!(rows(&dst, "SELECT a FROM t1 WHERE id = 1"), vec![vec![Val::String("from_src".to_string())]]);
+}
+
+
+// Assert: Applying an inverted changeset undoes it; changes made while disabled are not recorded.
+#[test]
+fn test_invert_changeset() {
+    let h = new_mem_db(SCHEMA);
+    h.run("INSERT INTO t1 (id, a) VALUES (1, 'a')").unwrap();
+
+    let s = h.create_session().unwrap();
+    s.attach(None).unwrap();
+    h.run("UPDATE t1 SET a = 'b' WHERE id = 1").unwrap();
+    h.run("INSERT INTO t1 (id, a) VALUES (2, 'c')").unwrap();
+
+    s.set_enabled(false);
+    h.run("INSERT INTO t2 (id, b) VALUES (1, 'x')").unwrap();
+    s.set_enabled(true);
+
+    let inv = invert_changeset(&s.changeset().unwrap()).unwrap();
+    drop(s);
+
+    h.apply_changeset(&inv, &mut |_| ConflictAction::Abort).unwrap();
+    assert_eq!(rows(&h, "SELECT id, a FROM t1"), vec![vec![Val::I64(1), Val::String("a".to_string())]]);
+    assert_eq!(rows(&h, "SELECT count(*) FROM t2"), vec![vec![Val::I64(1)]]);
+}
+
+
+// Assert: Tables rejected by the filter are not recorded by a session attached to all tables.
+#[test]
+fn test_session_table_filter() {
+    let h = new_mem_db(SCHEMA);
+    let mut s = h.create_session().unwrap();
+    s.attach(None).unwrap();
+    s.set_table_filter(Some(Box::new(|t| t != "t2")));
+
+    h.run("INSERT INTO t2 (id, b) VALUES (1, 'b')").unwrap();
+    assert!(s.is_empty());
+
+    h.run("INSERT INTO t1 (id, a) VALUES (1, 'a')").unwrap();
+    assert!(!s.is_empty());
+
+    let dst = new_mem_db(SCHEMA);
+    dst.apply_changeset(&s.changeset().unwrap(), &mut |_| ConflictAction::Abort).unwrap();
+    assert_eq!(rows(&dst, "SELECT count(*) FROM t1"), vec![vec![Val::I64(1)]]);
+    assert_eq!(rows(&dst, "SELECT count(*) FROM t2"), vec![vec![Val::I64(0)]]);
+}
+
+
+// Assert: The pre-update hook cannot be replaced while a session uses it.
+#[test]
+fn test_session_blocks_preupdate_hook() {
+    let h = new_mem_db(SCHEMA);
+    let s = h.create_session().unwrap();
+    let s2 = h.create_session().unwrap();
+
+    match h.set_preupdate_hook(Some(Box::new(|_| {}))) {
+        Err(rs) => assert_eq!(rs.primary.id, PrimaryRC::SQLITE_MISUSE),
+        Ok(_) => assert!(false)
+    }
+
+    drop(s);
+    assert!(h.set_preupdate_hook(None).is_err());
+
+    drop(s2);
+    h.set_preupdate_hook(None).unwrap();
+}
@@ -0,0 +1,74 @@
+use super::*;
+use crate::stmt_cache::StmtCacheStats;
+
+
+#[test]
+fn test_stmt_cache_hits() {
+    let h = DbHandle::new(":memory:".to_string()).unwrap();
+    h.run("CREATE TABLE t1(a PRIMARY KEY, b);").unwrap();
+
+    let before = h.get_stmt_cache_stats();
+
+    for i in 0..10 {
+        h.run_index("INSERT INTO t1 (a, b) VALUES (?, ?)", &vec![Val::I64(i), Val::I64(i)]).unwrap();
+    }
+
+    let after = h.get_stmt_cache_stats();
+
+    // Assert: First use prepares the stmt, all later uses re-use it.
+    assert_eq!(after.misses - before.misses, 1);
+    assert_eq!(after.hits - before.hits, 9);
+
+    let rset = h.run("SELECT count(*) FROM t1").unwrap();
+    assert_eq!(rset.rows.data[0][0], Val::I64(10));
+}
+
+
+#[test]
+fn test_stmt_cache_clears_bindings() {
+    let h = DbHandle::new(":memory:".to_string()).unwrap();
+    let q = "SELECT ?";
+
+    {
+        let s = h.new_stmt_cached(q).unwrap();
+        s.bind_index(&vec![Val::I64(1)]).unwrap();
+        assert_eq!(s.run().unwrap().rows.data[0][0], Val::I64(1));
+    }
+
+    // Assert: A re-used stmt does not keep the previous values (unbound = NULL).
+    {
+        let s = h.new_stmt_cached(q).unwrap();
+        assert_eq!(s.run().unwrap().rows.data[0][0], Val::Null);
+    }
+
+    assert_eq!(h.get_stmt_cache_stats().hits, 1);
+}
+
+
+#[test]
+fn test_stmt_cache_capacity() {
+    let h = DbHandle::new(":memory:".to_string()).unwrap();
+    h.set_stmt_cache_capacity(2);
+
+    h.run("SELECT 1").unwrap();
+    h.run("SELECT 2").unwrap();
+    h.run("SELECT 3").unwrap();
+
+    // Assert: Least recently used (`SELECT 1`) was evicted.
+    h.run("SELECT 1").unwrap();
+
+    assert_eq!(h.get_stmt_cache_stats(), StmtCacheStats {
+        capacity: 2,
+        len: 2,
+        hits: 0,
+        misses: 4,
+    });
+
+    // Assert: `0` disables the cache.
+    h.set_stmt_cache_capacity(0);
+    h.run("SELECT 1").unwrap();
+    h.run("SELECT 1").unwrap();
+
+    let stats = h.get_stmt_cache_stats();
+    assert_eq!((stats.len, stats.hits), (0, 0));
+}
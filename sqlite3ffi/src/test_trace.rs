@@ -0,0 +1,84 @@
+use super::*;
+use std::rc::Rc;
+use std::cell::RefCell;
+use crate::trace::{TraceEvent, Profile};
+
+
+fn get_profiles(events: &[TraceEvent]) -> Vec<Profile> {
+    events.iter().filter_map(|e| match e {
+        TraceEvent::Profile(p) => Some(p.clone()),
+        _ => None
+    }).collect()
+}
+
+
+#[test]
+fn test_trace() {
+    let h = DbHandle::new(":memory:".to_string()).unwrap();
+    h.run("CREATE TABLE t1(a, b)").unwrap();
+    h.run("INSERT INTO t1 (a, b) VALUES (1, 'x'), (2, 'y'), (3, 'z')").unwrap();
+
+    let events = Rc::new(RefCell::new(vec![]));
+    let e = events.clone();
+    h.set_trace(Some(Box::new(move |ev: &TraceEvent| e.borrow_mut().push(ev.clone()))));
+
+    let rset = h.run("SELECT * FROM t1 WHERE a > 1 ORDER BY b DESC").unwrap();
+    assert_eq!(rset.num_rows, 2);
+
+    {
+        let ev = events.borrow();
+        assert_eq!(ev[0], TraceEvent::Stmt { sql: "SELECT * FROM t1 WHERE a > 1 ORDER BY b DESC".to_string() });
+
+        let p = get_profiles(&ev);
+        assert_eq!(p.len(), 1);
+        assert_eq!(p[0].sql, "SELECT * FROM t1 WHERE a > 1 ORDER BY b DESC");
+        assert_eq!(p[0].normalized_sql, Some("SELECT*FROM t1 WHERE a>?ORDER BY b DESC;".to_string()));
+        assert_eq!(p[0].rows, 2);
+
+        // Assert: Every row is read without an index, and sorted.
+        assert_eq!(p[0].status.fullscan_steps, 2);
+        assert_eq!(p[0].status.sorts, 1);
+        assert_eq!(p[0].status.autoindexes, 0);
+        assert!(p[0].status.vm_steps > 0);
+    }
+
+    // Assert: Counters are per run for cached statements.
+    events.borrow_mut().clear();
+    h.run("SELECT * FROM t1 WHERE a > 1 ORDER BY b DESC").unwrap();
+    let p = get_profiles(&events.borrow());
+    assert_eq!((p[0].status.fullscan_steps, p[0].status.sorts, p[0].rows), (2, 1, 2));
+
+    // Assert: `Stmt` events have the placeholders, not the bound values.
+    events.borrow_mut().clear();
+    let mut kv = KeyVal { data: HashMap::new() };
+    kv.data.insert("a".to_string(), Val::I64(2));
+    h.run_kv("SELECT * FROM t1 WHERE a = :a", &kv).unwrap();
+    assert_eq!(events.borrow()[0], TraceEvent::Stmt { sql: "SELECT * FROM t1 WHERE a = :a".to_string() });
+
+    h.set_trace(None);
+    events.borrow_mut().clear();
+    h.run("SELECT * FROM t1").unwrap();
+    assert_eq!(events.borrow().len(), 0);
+}
+
+
+#[test]
+fn test_rset_timing() {
+    let h = DbHandle::new(":memory:".to_string()).unwrap();
+    h.run("CREATE TABLE t1(a)").unwrap();
+    h.run("INSERT INTO t1 (a) VALUES (1), (2), (3)").unwrap();
+
+    assert_eq!(h.run("SELECT * FROM t1").unwrap().timing, None);
+
+    h.set_rset_timing(true);
+    let t = h.run("SELECT * FROM t1 ORDER BY a DESC").unwrap().timing.unwrap();
+
+    // Assert: The first row is read by `Rewind`, not counted as a step.
+    assert_eq!(t.status.fullscan_steps, 2);
+    assert_eq!(t.status.sorts, 1);
+
+    // Assert: Omitted from JSON when not set.
+    h.set_rset_timing(false);
+    let json = serde_json::to_string(&h.run("SELECT * FROM t1").unwrap()).unwrap();
+    assert!(!json.contains("timing"));
+}
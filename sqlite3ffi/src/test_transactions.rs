@@ -101,3 +101,76 @@ fn test_transaction_basic_ok() {
 
     // @todo/important Test extended error codes.
 }
+
+
+// Assert: With a busy timeout, `BEGIN IMMEDIATE` waits for another processes write lock instead of returning `SQLITE_BUSY`.
+// - `fcntl` locks do not block within one process, so the lock is held by this test binary re-run as a child (see `test_busy_timeout_child`).
+#[test]
+fn test_busy_timeout() {
+    use std::env;
+    use std::thread;
+    use std::process::Command;
+    use std::time::{Duration, Instant};
+
+    let file = get_test_file();
+    let b = DbHandle::new(file.clone()).unwrap();
+    run(&b, "PRAGMA journal_mode=WAL");
+
+    let mut child = Command::new(env::current_exe().unwrap())
+        .args(&["--exact", "test_transactions::test_busy_timeout_child", "--test-threads=1"])
+        .env(CHILD_FILE_ENV, &file)
+        .spawn()
+        .unwrap();
+
+    // Wait for the child to take the lock.
+    let start = Instant::now();
+    loop {
+        match b.run("BEGIN IMMEDIATE") {
+            Err(rs) => {
+                assert_eq!(rs.primary.id, PrimaryRC::SQLITE_BUSY);
+                break;
+            }
+            Ok(_) => {
+                run(&b, "ROLLBACK");
+            }
+        }
+
+        assert!(start.elapsed() < Duration::from_secs(10), "Child did not take the write lock.");
+        thread::sleep(Duration::from_millis(5));
+    }
+
+    // Assert: Times out with `SQLITE_BUSY` when the lock is not released.
+    b.set_busy_timeout(Duration::from_millis(50)).unwrap();
+    let start = Instant::now();
+    assert!(b.run("BEGIN IMMEDIATE").is_err());
+    assert!(start.elapsed() >= Duration::from_millis(50));
+
+    // Assert: The lock is taken once the child releases it, not at the end of the timeout.
+    b.set_busy_timeout(Duration::from_millis(5000)).unwrap();
+    let start = Instant::now();
+    run(&b, "BEGIN IMMEDIATE");
+    run(&b, "ROLLBACK");
+    assert!(start.elapsed() < Duration::from_millis(2000));
+
+    assert!(child.wait().unwrap().success());
+}
+
+static CHILD_FILE_ENV: &str = "SQLITE3FFI_TEST_BUSY_FILE";
+
+// Only does something when run by `test_busy_timeout`: holds the write lock for 300ms.
+#[test]
+fn test_busy_timeout_child() {
+    use std::env;
+    use std::thread;
+    use std::time::Duration;
+
+    let file = match env::var(CHILD_FILE_ENV) {
+        Ok(f) => f,
+        Err(_) => return
+    };
+
+    let a = DbHandle::new(file).unwrap();
+    run(&a, "BEGIN IMMEDIATE");
+    thread::sleep(Duration::from_millis(300));
+    run(&a, "ROLLBACK");
+}
@@ -0,0 +1,9 @@
+use crate::db::DbHandle;
+
+
+// In-memory database with `schema` run as a script (E.g. `CREATE TABLE` and `INSERT` statements).
+pub fn new_mem_db(schema: &str) -> DbHandle {
+    let h = DbHandle::new(":memory:".to_string()).unwrap();
+    h.exec_script(schema).unwrap();
+    h
+}
@@ -0,0 +1,134 @@
+use std::ffi::CStr;
+use std::time::Duration;
+use std::os::raw::c_int;
+
+use serde::{Deserialize, Serialize};
+
+use crate::cffi::{
+    // Objects
+    sqlite3_stmt,
+
+    // Functions
+    sqlite3_stmt_status,
+    sqlite3_sql,
+    sqlite3_normalized_sql,
+
+    // Constants
+    SQLITE_STMTSTATUS_FULLSCAN_STEP,
+    SQLITE_STMTSTATUS_SORT,
+    SQLITE_STMTSTATUS_AUTOINDEX,
+    SQLITE_STMTSTATUS_VM_STEP,
+};
+
+
+// Counters for a single statement run.
+// @see https://www.sqlite.org/c3ref/c_stmtstatus_counter.html
+// - Reset when a `Cursor` starts, so cached statements do not carry counts between runs.
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Serialize, Deserialize)]
+#[derive(Clone)]
+#[derive(Default)]
+pub struct StmtStatus {
+    // Forward steps of a full table scan (rows scanned - 1); a large number suggests a missing index.
+    pub fullscan_steps: u32,
+
+    // Sort operations (`ORDER BY`, `GROUP BY` etc. not covered by an index).
+    pub sorts: u32,
+
+    // Rows inserted into automatic (transient) indexes; suggests a missing index.
+    pub autoindexes: u32,
+
+    // Virtual machine instructions; an approximation of the total work done.
+    pub vm_steps: u32,
+}
+
+
+// Timing of the run that produced an `RSet`; only set when enabled with `DbHandle::set_rset_timing`.
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Serialize, Deserialize)]
+#[derive(Clone)]
+pub struct QueryTiming {
+    // From the first `sqlite3_step` to the last.
+    pub duration_us: u64,
+    pub status: StmtStatus,
+}
+
+
+// A statement run that has finished (returned `SQLITE_DONE` or an error).
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Serialize, Deserialize)]
+#[derive(Clone)]
+pub struct Profile {
+    // As prepared.
+    pub sql: String,
+
+    // Literals replaced with `?` (E.g. `SELECT * FROM t1 WHERE a=?;`); use to group runs of the same query.
+    // - `None` if SQLite could not normalize the statement.
+    pub normalized_sql: Option<String>,
+
+    // Note: SQLite 3.28 measures this with the VFS clock, which has millisecond resolution.
+    pub duration_ms: u64,
+
+    // `SQLITE_ROW` results.
+    pub rows: u64,
+
+    pub status: StmtStatus,
+}
+
+
+// @see https://www.sqlite.org/c3ref/trace_v2.html
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Clone)]
+pub enum TraceEvent {
+    // The first `sqlite3_step` of a run; `sql` is the unexpanded SQL (placeholders, not bound values), or `-- TRIGGER <name>` for each trigger.
+    Stmt { sql: String },
+
+    Profile(Profile),
+}
+
+// Called on the connections thread, *during* the statement.
+// - Must not use the connection.
+pub type TraceFn = dyn FnMut(&TraceEvent);
+
+
+fn get_counter(stmt: *mut sqlite3_stmt, op: u32, reset: bool) -> u32 {
+    unsafe { sqlite3_stmt_status(stmt, op as c_int, reset as c_int) as u32 }
+}
+
+pub(crate) fn get_stmt_status(stmt: *mut sqlite3_stmt) -> StmtStatus {
+    StmtStatus {
+        fullscan_steps: get_counter(stmt, SQLITE_STMTSTATUS_FULLSCAN_STEP, false),
+        sorts: get_counter(stmt, SQLITE_STMTSTATUS_SORT, false),
+        autoindexes: get_counter(stmt, SQLITE_STMTSTATUS_AUTOINDEX, false),
+        vm_steps: get_counter(stmt, SQLITE_STMTSTATUS_VM_STEP, false),
+    }
+}
+
+pub(crate) fn reset_stmt_status(stmt: *mut sqlite3_stmt) {
+    for op in [SQLITE_STMTSTATUS_FULLSCAN_STEP, SQLITE_STMTSTATUS_SORT, SQLITE_STMTSTATUS_AUTOINDEX, SQLITE_STMTSTATUS_VM_STEP].iter() {
+        get_counter(stmt, *op, true);
+    }
+}
+
+pub(crate) fn get_profile(stmt: *mut sqlite3_stmt, elapsed_ns: i64, rows: u64) -> Profile {
+    let (sql, normalized_sql) = unsafe {
+        let norm = sqlite3_normalized_sql(stmt);
+
+        (
+            CStr::from_ptr(sqlite3_sql(stmt)).to_string_lossy().into_owned(),
+            if norm.is_null() { None } else { Some(CStr::from_ptr(norm).to_string_lossy().into_owned()) }
+        )
+    };
+
+    Profile {
+        sql,
+        normalized_sql,
+        duration_ms: Duration::from_nanos(elapsed_ns.max(0) as u64).as_millis() as u64,
+        rows,
+        status: get_stmt_status(stmt),
+    }
+}